@@ -0,0 +1,52 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The upscaling backends are git dependencies, so their versions are read from Cargo.lock for `info`
+const BACKENDS: [(&str, &str); 2] = [("realcugan_rs", "UPSCALER_REALCUGAN_RS"), ("realesrgan_rs", "UPSCALER_REALESRGAN_RS")];
+
+fn field<'a>(package: &'a str, key: &str) -> Option<&'a str> {
+    package.lines()
+        .find_map(|line| line.strip_prefix(key)?.trim().strip_prefix('=')?.trim().strip_prefix('"')?.strip_suffix('"'))
+}
+
+// Cargo.lock is not committed, and a workspace keeps it above the package
+fn find_lock_file(manifest_dir: &Path) -> Option<PathBuf> {
+    manifest_dir.ancestors().map(|dir| dir.join("Cargo.lock")).find(|path| path.is_file())
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    println!("cargo:rerun-if-changed={}", manifest_dir.join("Cargo.toml").display());
+    if env::var_os("CARGO_FEATURE_NCNN").is_none() {
+        return;
+    }
+
+    let Some(lock_file) = find_lock_file(&manifest_dir) else {
+        println!("cargo:rerun-if-changed={}", manifest_dir.join("Cargo.lock").display());
+        println!("cargo:warning=Cargo.lock not found, `info` will report the ncnn backend versions as unknown");
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+    let lock = match fs::read_to_string(&lock_file) {
+        Ok(lock) => lock,
+        Err(error) => {
+            println!("cargo:warning=Failed to read {}: {}", lock_file.display(), error);
+            return;
+        }
+    };
+
+    for (name, variable) in BACKENDS {
+        let Some(package) = lock.split("[[package]]").find(|package| field(package, "name") == Some(name)) else {
+            println!("cargo:warning={} is missing from {}", name, lock_file.display());
+            continue;
+        };
+        if let Some(version) = field(package, "version") {
+            println!("cargo:rustc-env={}_VERSION={}", variable, version);
+        }
+        // git sources end in #<commit>
+        if let Some(commit) = field(package, "source").and_then(|source| source.rsplit_once('#')).map(|(_, commit)| commit) {
+            println!("cargo:rustc-env={}_COMMIT={}", variable, commit);
+        }
+    }
+}
@@ -1,18 +1,68 @@
 use crate::error::Error;
+use crate::binary::Binary;
+use crate::settings::Settings;
+use crate::manifest::Manifest;
+use crate::job::Job;
+use crate::sidecar::Sidecar;
+use crate::rate_limit::RateLimit;
+use crate::external_tracks::{ExternalTrack, TrackKind};
+use crate::template::OutputTemplate;
+use crate::sequence::Sequence;
+use crate::priority::Priority;
+use crate::batch_order::BatchOrder;
+use crate::info::Info;
+use crate::organize::{Episode, Organize};
+use crate::remote::Remote;
+use crate::progress_mode::ProgressMode;
+use crate::compare::CompareModels;
+use crate::batch_state::BatchState;
+use crate::notify::Notify;
+use crate::report::Report;
+use crate::reprocess::ReprocessRange;
+use crate::skip_policy::SkipPolicy;
+use crate::media_info::MediaInfo;
+use crate::logger::{Level, Logger};
 
-use std::path::Path;
-use std::process::{exit, Command};
+use std::ffi::OsString;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Stdio};
 
 pub struct Arguments {
-    input: String,
-    output: Option<String>,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    output_template: Option<String>,
+    organize: Option<Organize>,
+    manifest: Option<PathBuf>,
     formats: Vec<String>,
-    pub files: Vec<(String, String)>,
-    pub width: Option<usize>,
-    pub height: Option<usize>,
-    pub encoder: String,
-    pub model: String,
-    pub duplicate_threshold: f64,
+    ffmpeg_path: Option<PathBuf>,
+    ffprobe_path: Option<PathBuf>,
+    log_level: Level,
+    log_file: Option<PathBuf>,
+    order: BatchOrder,
+    limit: Option<usize>,
+    resume_batch: bool,
+    report_path: Option<PathBuf>,
+    info: bool,
+    pub files: Vec<Job>,
+    pub settings: Settings,
+    pub parallel_files: usize,
+    pub preview_window: bool,
+    pub background: bool,
+    pub gpu_limit: u8,
+    pub max_fps: Option<RateLimit>,
+    pub queue_slots: Option<usize>,
+    pub stats: bool,
+    pub progress: ProgressMode,
+    pub compare: Option<CompareModels>,
+    pub batch: Option<BatchState>,
+    pub notify: Notify,
+    pub report: Report,
+    pub metrics: Option<SocketAddr>,
+    pub reprocess: Option<ReprocessRange>,
+    pub skip_policy: SkipPolicy,
+    pub keep_going: bool,
     pub replace_output: bool
 }
 
@@ -24,60 +74,245 @@ impl Default for Arguments {
             .collect::<Vec<String>>();
         
         Self {
-            input: String::new(),
+            input: PathBuf::new(),
             output: None,
-            width: None,
-            height: None,
-            encoder: String::from("libx264"),
+            output_template: None,
+            organize: None,
+            manifest: None,
             files: Vec::new(),
+            settings: Settings::default(),
             formats,
-            model: String::from("realesrgan"),
-            duplicate_threshold: 1.0,
+            ffmpeg_path: None,
+            ffprobe_path: None,
+            log_level: Level::Info,
+            log_file: None,
+            order: BatchOrder::Name,
+            limit: None,
+            resume_batch: false,
+            report_path: None,
+            info: false,
+            parallel_files: 1,
+            preview_window: false,
+            background: false,
+            gpu_limit: 100,
+            max_fps: None,
+            queue_slots: None,
+            stats: false,
+            progress: ProgressMode::detect(),
+            compare: None,
+            batch: None,
+            notify: Notify::default(),
+            report: Report::default(),
+            metrics: None,
+            reprocess: None,
+            skip_policy: SkipPolicy::Exists,
+            keep_going: Self::keep_going_default(),
             replace_output: false
         }
     }
 }
 
 impl Arguments {
+    const KEEP_GOING_ENV_VAR: &'static str = "UPSCALER_KEEP_GOING";
+
+    // Unattended setups turn --keep-going on for every run through the environment
+    fn keep_going_default() -> bool {
+        std::env::var(Self::KEEP_GOING_ENV_VAR).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+    }
+
     pub fn parse() -> Result<Self, Error> {
         let mut arguments = Self::default();
 
-        arguments.check_ffmpeg()?;
         arguments.parse_arguments()?;
-        arguments.validate_encoder()?;
-        arguments.validate_model()?;
-        arguments.validate_resolution_and_scale()?;
-        arguments.set_input_files()?;
+        Logger::init(arguments.log_level, arguments.log_file.as_deref())?;
+        if arguments.background {
+            Priority::lower();
+        }
+        arguments.configure_binaries()?;
+        if arguments.info {
+            Info::print(&arguments.settings);
+            exit(0);
+        }
+        arguments.check_ffmpeg()?;
+        let encoders = Self::get_encoders()?;
+        arguments.settings.validate(&encoders)?;
+        arguments.validate_parallel_files()?;
+        arguments.validate_gpu_limit()?;
+        arguments.validate_limit()?;
+        arguments.validate_queue_slots()?;
+        if let Some(mut compare) = arguments.compare.take() {
+            compare.prepare(&arguments.input, arguments.output.take(), arguments.replace_output, &arguments.settings, &encoders)?;
+            arguments.compare = Some(compare);
+            return Ok(arguments);
+        }
+        arguments.set_input_files(&encoders)?;
         arguments.set_output_files()?;
+        if let Some(range) = &arguments.reprocess {
+            range.validate(&arguments.files)?;
+        }
 
         Ok(arguments)
     }
 
-    fn get_next_arg(&self, args: &[String], index: &mut usize, arg_name: &str) -> Result<String, Error> {
+    // Runs the pipeline on prepared jobs, replacing their outputs
+    pub fn with_files(mut self, files: Vec<Job>) -> Self {
+        self.files = files;
+        self.compare = None;
+        self.batch = None;
+        self.report = Report::default();
+        self.reprocess = None;
+        self.replace_output = true;
+        self
+    }
+
+    fn compare_models(&mut self) -> Result<&mut CompareModels, Error> {
+        self.compare.as_mut().ok_or_else(|| Error::new(format!("This option is only valid with {}", CompareModels::COMMAND)))
+    }
+
+    fn get_next_os_arg(&self, args: &[OsString], index: &mut usize, arg_name: &str) -> Result<OsString, Error> {
         *index += 1;
         args.get(*index).cloned().ok_or_else(|| Error::new(format!("Missing value for argument: {}", arg_name)))
     }
+
+    fn get_next_arg(&self, args: &[OsString], index: &mut usize, arg_name: &str) -> Result<String, Error> {
+        self.get_next_os_arg(args, index, arg_name)?
+            .into_string()
+            .map_err(|_| Error::new(format!("Argument '{}' is not valid UTF-8", arg_name)))
+    }
+
+    fn get_next_path(&self, args: &[OsString], index: &mut usize, arg_name: &str) -> Result<PathBuf, Error> {
+        self.get_next_os_arg(args, index, arg_name).map(PathBuf::from)
+    }
     
     fn parse_arguments(&mut self) -> Result<(), Error> {
-        let args: Vec<String> = std::env::args().collect();
+        let args: Vec<OsString> = std::env::args_os().collect();
     
         if args.len() < 2 {
             Self::print_help();
         }
         
         let mut i = 1;
+        if args[1] == CompareModels::COMMAND {
+            self.compare = Some(CompareModels::default());
+            i += 1;
+        } else if args[1] == Info::COMMAND {
+            self.info = true;
+            i += 1;
+        }
         while i < args.len() {
-            match args[i].as_str() {
-                "-i" | "--input" => self.input = self.get_next_arg(&args, &mut i, "input")?,
-                "-o" | "--output" => self.output = Some(self.get_next_arg(&args, &mut i, "output")?),
-                "-w" | "--width" => self.width = Some(self.parse_numeric_arg(&args, &mut i, "width")?),
-                "-h" | "--height" => self.height = Some(self.parse_numeric_arg(&args, &mut i, "height")?),
-                "-e" | "--encoder" => self.encoder = self.get_next_arg(&args, &mut i, "encoder")?,
-                "-m" | "--model" => self.model = self.get_next_arg(&args, &mut i, "model")?,
-                "--replace_output" => self.replace_output = true,
-                "--duplicate_threshold" => self.duplicate_threshold = self.parse_numeric_arg(&args, &mut i, "duplicate_threshold")?,
+            match args[i].to_string_lossy().as_ref() {
+                "-i" | "--input" => self.input = self.get_next_path(&args, &mut i, "input")?,
+                "-o" | "--output" => self.output = Some(self.get_next_path(&args, &mut i, "output")?),
+                "--output-template" => self.output_template = Some(self.get_next_arg(&args, &mut i, "output-template")?),
+                "--organize" => self.organize = Some(self.get_next_arg(&args, &mut i, "organize")?.parse()?),
+                "--manifest" => self.manifest = Some(self.get_next_path(&args, &mut i, "manifest")?),
+                "-w" | "--width" => self.settings.width = Some(self.parse_numeric_arg(&args, &mut i, "width")?),
+                "-h" | "--height" => self.settings.height = Some(self.parse_numeric_arg(&args, &mut i, "height")?),
+                "--chapters" => self.settings.chapters = Some(self.get_next_arg(&args, &mut i, "chapters")?.parse()?),
+                "--chapter-output" => self.settings.chapter_output = self.get_next_arg(&args, &mut i, "chapter-output")?.parse()?,
+                "--crop" => self.settings.crop = Some(self.get_next_arg(&args, &mut i, "crop")?.parse()?),
+                "--roi" => self.settings.roi = Some(self.get_next_arg(&args, &mut i, "roi")?.parse()?),
+                "--roi-only" => self.settings.roi_only = true,
+                "--detelecine" => self.settings.detelecine = self.get_next_arg(&args, &mut i, "detelecine")?.parse()?,
+                "--add-audio" => {
+                    let value = self.get_next_arg(&args, &mut i, "add-audio")?;
+                    self.settings.external_tracks.push(ExternalTrack::parse(TrackKind::Audio, &value)?);
+                },
+                "--add-subs" => {
+                    let value = self.get_next_arg(&args, &mut i, "add-subs")?;
+                    self.settings.external_tracks.push(ExternalTrack::parse(TrackKind::Subtitle, &value)?);
+                },
+                "--external-tracks" => self.settings.external_track_mode = self.get_next_arg(&args, &mut i, "external-tracks")?.parse()?,
+                "--burn-subtitles" => self.settings.burn_subtitles = Some(self.get_next_arg(&args, &mut i, "burn-subtitles")?.parse()?),
+                "--watermark" => self.settings.watermark = Some(self.get_next_arg(&args, &mut i, "watermark")?.parse()?),
+                "--tonemap" => self.settings.tonemap = self.get_next_arg(&args, &mut i, "tonemap")?.parse()?,
+                "--color-range" => self.settings.color_range = Some(self.get_next_arg(&args, &mut i, "color-range")?.parse()?),
+                "--hwdec" => self.settings.hwdec = self.get_next_arg(&args, &mut i, "hwdec")?.parse()?,
+                "--gpu-pipeline" => self.settings.gpu_pipeline = true,
+                "--fps" => self.settings.fps = Some(self.parse_numeric_arg(&args, &mut i, "fps")?),
+                "--video-stream" => self.settings.video_stream = self.parse_numeric_arg(&args, &mut i, "video-stream")?,
+                "--final-scale" => self.settings.final_scale = self.get_next_arg(&args, &mut i, "final-scale")?.parse()?,
+                "--ar-policy" => self.settings.aspect_policy = self.get_next_arg(&args, &mut i, "ar-policy")?.parse()?,
+                "--no-downscale" => self.settings.no_downscale = true,
+                "-e" | "--encoder" => self.settings.encoder = self.get_next_arg(&args, &mut i, "encoder")?,
+                "--encoder-args" => self.settings.encoder_args = self.get_next_arg(&args, &mut i, "encoder-args")?.parse()?,
+                "--input-args" => self.settings.input_args = self.get_next_arg(&args, &mut i, "input-args")?.parse()?,
+                "--output-args" => self.settings.output_args = self.get_next_arg(&args, &mut i, "output-args")?.parse()?,
+                "--bit-depth" => self.settings.bit_depth = self.get_next_arg(&args, &mut i, "bit-depth")?.parse()?,
+                "--profile" => self.settings.set_profile(self.get_next_arg(&args, &mut i, "profile")?.parse()?),
+                "--preset" => self.settings.set_preset(self.get_next_arg(&args, &mut i, "preset")?.parse()?),
+                "-f" | "--format" => self.settings.format = Some(self.get_next_arg(&args, &mut i, "format")?.parse()?),
+                "--svt-preset" => self.settings.svt_preset = Some(self.parse_numeric_arg(&args, &mut i, "svt-preset")?),
+                "--film-grain" => self.settings.film_grain = Some(self.parse_numeric_arg(&args, &mut i, "film-grain")?),
+                "--grain" => self.settings.grain = Some(self.get_next_arg(&args, &mut i, "grain")?.parse()?),
+                "-m" | "--model" => self.settings.model = self.get_next_arg(&args, &mut i, "model")?,
+                "--external-upscaler" => self.settings.set_external_upscaler(&self.get_next_arg(&args, &mut i, "external-upscaler")?),
+                "--chain" => self.settings.chain = Some(self.get_next_arg(&args, &mut i, "chain")?.parse()?),
+                "--syncgap" => self.settings.syncgap = Some(self.parse_numeric_arg(&args, &mut i, "syncgap")?),
+                "--replace_output" | "--replace-output" => self.replace_output = true,
+                "--keep-going" => self.keep_going = true,
+                "--skip-policy" => self.skip_policy = self.get_next_arg(&args, &mut i, "skip-policy")?.parse()?,
+                "--verify" => self.settings.verify = true,
+                "--verify-frames" => self.settings.verify_frames = self.parse_numeric_arg(&args, &mut i, "verify-frames")?,
+                "--reference" => self.settings.reference = Some(self.get_next_path(&args, &mut i, "reference")?),
+                "--duplicate-threshold" | "--duplicate_threshold" => self.settings.duplicate_threshold = self.get_next_arg(&args, &mut i, "duplicate-threshold")?.parse()?,
+                "--duplicates" => self.settings.duplicate_mode = self.get_next_arg(&args, &mut i, "duplicates")?.parse()?,
+                "--duplicate-detector" => self.settings.duplicate_detector = self.get_next_arg(&args, &mut i, "duplicate-detector")?.parse()?,
+                "--max-duplicate-run" => self.settings.max_duplicate_run = Some(self.parse_numeric_arg(&args, &mut i, "max-duplicate-run")?),
+                "--on-skip" => self.settings.on_skip = self.get_next_arg(&args, &mut i, "on-skip")?.parse()?,
+                "--on-frame-error" => self.settings.on_frame_error = self.get_next_arg(&args, &mut i, "on-frame-error")?.parse()?,
+                "--backend" => self.settings.backend = self.get_next_arg(&args, &mut i, "backend")?.parse()?,
+                "--cpu-workers" => self.settings.cpu_workers = self.parse_numeric_arg(&args, &mut i, "cpu-workers")?,
+                "--skip-static-segments" => self.settings.skip_static_segments = true,
+                "--adaptive-scale" => self.settings.adaptive_scale = true,
+                "--quality-gate" => self.settings.quality_gate = true,
+                "--tile-size" => self.settings.tile_size = Some(self.parse_numeric_arg(&args, &mut i, "tile-size")?),
+                "--keep-frames" => self.settings.keep_frames = Some(self.get_next_path(&args, &mut i, "keep-frames")?),
+                "--frames-format" => self.settings.frames_format = self.get_next_arg(&args, &mut i, "frames-format")?.parse()?,
+                "--frames-only" => self.settings.frames_only = true,
+                "--screenshots" => self.settings.screenshots = Some(self.get_next_arg(&args, &mut i, "screenshots")?.parse()?),
+                "--cache-dir" => self.settings.cache_dir = Some(self.get_next_arg(&args, &mut i, "cache-dir")?),
+                "--parallel-files" => self.parallel_files = self.parse_numeric_arg(&args, &mut i, "parallel-files")?,
+                "--preview-window" => self.preview_window = true,
+                "--stats" => self.stats = true,
+                "--progress" => self.progress = self.get_next_arg(&args, &mut i, "progress")?.parse()?,
+                "--channel-capacity" => self.settings.channel_capacity = self.get_next_arg(&args, &mut i, "channel-capacity")?.parse()?,
+                "--order" => self.order = self.get_next_arg(&args, &mut i, "order")?.parse()?,
+                "--limit" => self.limit = Some(self.parse_numeric_arg(&args, &mut i, "limit")?),
+                "--resume-batch" => self.resume_batch = true,
+                "--fragmented" => self.settings.fragmented = true,
+                "--segment-time" => self.settings.segment_time = Some(self.parse_numeric_arg(&args, &mut i, "segment-time")?),
+                "--segment-name" => self.settings.segment_name = Some(self.get_next_arg(&args, &mut i, "segment-name")?),
+                "--package" => self.settings.package = Some(self.get_next_arg(&args, &mut i, "package")?.parse()?),
+                "--reprocess-range" => self.reprocess = Some(self.get_next_arg(&args, &mut i, "reprocess-range")?.parse()?),
+                "--notify-url" => self.notify.url = Some(self.get_next_arg(&args, &mut i, "notify-url")?),
+                "--notify-cmd" => self.notify.command = Some(self.get_next_arg(&args, &mut i, "notify-cmd")?),
+                "--report" => {
+                    let path = self.get_next_path(&args, &mut i, "report")?;
+                    Report::validate_path(&path)?;
+                    self.report_path = Some(path);
+                },
+                "--metrics" => {
+                    let address = self.get_next_arg(&args, &mut i, "metrics")?;
+                    self.metrics = Some(address.parse().map_err(|_| Error::new(format!(
+                        "Invalid metrics address: {}. Must be HOST:PORT, e.g. 127.0.0.1:9100", address
+                    )))?);
+                },
+                "--background" => self.background = true,
+                "--gpu-limit" => self.gpu_limit = self.parse_numeric_arg(&args, &mut i, "gpu-limit")?,
+                "--queue-slot" => self.queue_slots = Some(self.parse_numeric_arg(&args, &mut i, "queue-slot")?),
+                "--max-fps" => self.max_fps = Some(self.get_next_arg(&args, &mut i, "max-fps")?.parse()?),
+                "--ffmpeg-path" => self.ffmpeg_path = Some(self.get_next_path(&args, &mut i, "ffmpeg-path")?),
+                "--ffprobe-path" => self.ffprobe_path = Some(self.get_next_path(&args, &mut i, "ffprobe-path")?),
+                "-v" | "--verbose" => self.log_level = Level::Debug,
+                "--quiet" => self.log_level = Level::Error,
+                "--log-file" => self.log_file = Some(self.get_next_path(&args, &mut i, "log-file")?),
+                "--range" => self.compare_models()?.range = Some(CompareModels::parse_range(&self.get_next_arg(&args, &mut i, "range")?)?),
+                "--models" => self.compare_models()?.models = CompareModels::parse_models(&self.get_next_arg(&args, &mut i, "models")?),
+                "--layout" => self.compare_models()?.layout = self.get_next_arg(&args, &mut i, "layout")?.parse()?,
                 "--help" => Self::print_help(),
-                _ => return Err(Error::new(format!("Invalid argument: {}", args[i]))),
+                "--version" => self.info = true,
+                _ => return Err(Error::new(format!("Invalid argument: {}", args[i].to_string_lossy()))),
             }
             i += 1;
         }
@@ -87,38 +322,191 @@ impl Arguments {
 
     fn print_help() {
         println!("Usage: program_name [OPTIONS]");
+        println!("       program_name compare-models -i FILE --range START-END --models A,B[,...] [--layout cycle|grid] [OPTIONS]");
+        println!("       program_name info [--ffmpeg-path PATH] [--ffprobe-path PATH] [--cache-dir DIR]");
+        println!();
+        println!("Commands:");
+        println!("  compare-models             Upscale the same clip with each model and write one labelled comparison video");
+        println!("      --range START-END      Clip to compare, in seconds or [HH:]MM:SS (e.g. 1:00-1:10)");
+        println!("      --models A,B[,...]     Models to compare, at least two");
+        println!("      --layout LAYOUT        cycle plays the versions one after another, grid shows them side by side (default: cycle)");
         println!();
         println!("Options:");
-        println!("  -i, --input FILE           Specify the input video file or directory");
-        println!("  -o, --output FILE          Specify the output video file");
+        println!("  -i, --input FILE           Specify the input video file, directory or image sequence (e.g. frames/%06d.png)");
+        println!("                             @LIST reads one input per line from the file LIST, - reads them from stdin");
+        println!("                             or a URL ffmpeg can read (e.g. https://example.com/video.mp4)");
+        println!("  -o, --output FILE          Specify the output video file or image sequence (e.g. upscaled/%06d.png)");
+        println!("      --output-template NAME Name outputs from a template (default: {{stem}}_converted.{{ext}})");
+        println!("                             tokens: {{stem}} {{ext}} {{date}} {{model}} {{scale}} {{width}} {{height}}");
+        println!("                             {{show}} {{season}} {{episode}} (from SxxEyy in the file name)");
+        println!("      --organize LAYOUT      Write episodes into Show/Season NN folders of the --output directory,");
+        println!("                             named for: plex | jellyfin");
+        println!("      --manifest FILE        Read jobs from a CSV or JSON manifest with per-file options");
+        println!("                             columns/keys: input, output and any option (e.g. width, model)");
         println!("  -w, --width WIDTH          Set the target video width (in pixels)");
         println!("  -h, --height HEIGHT        Set the target video height (in pixels)");
+        println!("      --chapters LIST        Only upscale these chapters of the input, e.g. 1,3-5");
+        println!("      --chapter-output MODE  Selected chapters as one joined output or split into one output each: join | split (default: join)");
+        println!("      --crop CROP            Crop the input before upscaling: auto | W:H:X:Y");
+        println!("                             auto detects black bars with ffmpeg cropdetect");
+        println!("      --roi X,Y,W,H          Only run the model on this region and paste it over a bilinear-scaled frame");
+        println!("      --roi-only             Output just the --roi region, upscaled");
+        println!("      --detelecine MODE      Inverse telecine 29.97fps content to 23.976fps: auto | on | off (default: off)");
+        println!("      --burn-subtitles SUBS  Burn subtitles into the upscaled frames: a subtitle stream index or a file (e.g. subs.ass)");
+        println!("      --watermark FILE[:pos=POS][:opacity=O]");
+        println!("                             Composite an image or looping animation onto the output at its final size");
+        println!("                             (pos: tl | tr | bl | br | center, default: br; opacity 0-1, default: 1)");
+        println!("                             rendered at the output resolution, the burned stream is not copied");
+        println!("      --add-audio FILE       Mux the first audio stream of FILE into the output, LANG=FILE also tags its");
+        println!("                             language, e.g. eng=dub.flac (repeatable)");
+        println!("      --add-subs FILE        Mux the first subtitle stream of FILE into the output, e.g. por=subs.srt (repeatable)");
+        println!("      --external-tracks MODE Keep the original audio and subtitles next to the added ones or replace them:");
+        println!("                             add | replace (default: add), replace only drops kinds that have an added track");
+        println!("      --tonemap MODE         Tone-map HDR sources to SDR before upscaling: hable | reinhard | off (default: off)");
+        println!("      --color-range RANGE    Color range of the source, kept in the output: limited | full (default: detected)");
+        println!("      --hwdec MODE           Decode the input on the GPU while extracting: auto | vaapi | nvdec | qsv | off (default: off)");
+        println!("                             frames are downloaded back to system memory, auto falls back to software decoding");
+        println!("      --gpu-pipeline         Decode with nvdec and encode with nvenc from GPU memory, only frames for the model are downloaded");
+        println!("      --fps FPS              Frame rate of an image sequence input (default: 24)");
+        println!("      --video-stream INDEX   Upscale this video stream and copy the others untouched (default: 0)");
+        println!("      --final-scale FILTER   Resize the model output to the target size with: lanczos | bicubic | spline | none");
+        println!("                             (default: lanczos, none keeps the model output size)");
+        println!("      --ar-policy POLICY     Meet a target size with another aspect ratio: keep | pad | crop | stretch (default: keep,");
+        println!("                             which shrinks the target to the input aspect ratio)");
+        println!("      --no-downscale         Keep the model output size instead of scaling it down to the target");
         println!("  -e, --encoder ENCODER      Choose the video encoder (default: libx264)");
+        println!("      --encoder-args ARGS    Extra ffmpeg arguments after the encoder options when merging, e.g. \"-x265-params aq-mode=3\"");
+        println!("      --input-args ARGS      Extra ffmpeg arguments before the source input when merging");
+        println!("      --output-args ARGS     Extra ffmpeg arguments before the output file when merging");
+        println!("      --svt-preset N         SVT-AV1 speed preset, 0 (slowest) to 13 (default: 8, libsvtav1 only)");
+        println!("      --film-grain N         Synthesize AV1 film grain at strength 0-50 (libsvtav1 and libaom-av1)");
+        println!("                             AV1 encoders default to crf 30 with a keyframe every 10 seconds");
+        println!("      --grain MODE           Keep grain the model removes: preserve (re-apply the input's grain layer)");
+        println!("                             | synthesize[=1-50] (AV1 film grain, or a noise filter for other encoders)");
+        println!("      --bit-depth N          Output bit depth: 8 | 10 (default: 8), 10 needs an encoder such as libx265");
+        println!("  -f, --format FORMAT        Set the output container format: mp4 | mkv | webm | mov | mxf");
+        println!("                             (default: inferred from the output file extension)");
+        println!("      --profile PROFILE      Editing intermediate preset: prores-hq | prores-4444 (10-bit mov, prores_ks)");
+        println!("                             | dnxhr-hqx (10-bit mxf, dnxhd); sets the encoder, pixel format and container");
+        println!("      --preset PRESET        Delivery preset: web | mobile | tv | archive; sets the container, encoder,");
+        println!("                             pixel format, level and audio codec");
         println!("  -m, --model MODEL          Select the AI model for upscaling: (default: realesrgan)");
-        println!("                             realcugan | realesrgan | realesrgan-anime | realesr-anime");
-        println!("      --duplicate_threshold  Set the similarity threshold for identifying duplicate frames (default: 1.0)");
-        println!("      --replace_output       Replace the output file if it already exists");
+        println!("                             auto | realcugan | realcugan-pro | realesrgan | realesrgan-anime | realesr-anime");
+        println!("                             auto detects animated or live-action content per file");
+        println!("                             plugin:NAME loads an upscaler plugin from $UPSCALER_PLUGIN_PATH or ./plugins");
+        println!("      --external-upscaler CMD Upscale by piping RGB24 frames through CMD, e.g. \"python sr.py {{width}} {{height}} {{scale}}\"");
+        println!("      --chain STEPS          Upscale in several passes, e.g. 2x,2x or realcugan:2x,realesr-anime:2x, resizing");
+        println!("                             between passes so the last one lands on the target size");
+        println!("      --syncgap N            RealCUGAN tile sync gap 0-3, lower is faster and uses less VRAM (default: 3)");
+        println!("      --duplicate-threshold T Similarity threshold for identifying duplicate frames: 0-1 | auto (default: 1.0)");
+        println!("                             auto samples the first seconds of each file and reports the chosen value");
+        println!("      --duplicates MODE      Set how duplicate frames are handled: collapse | keep | blend | decimate");
+        println!("                             (default: collapse), decimate drops them in ffmpeg for slideshow inputs");
+        println!("                             keep disables filtering, blend averages near-duplicate frames");
+        println!("      --duplicate-detector D Duplicate comparison: fast | accurate (default: fast)");
+        println!("                             fast skips the full comparison when small thumbnails clearly differ");
+        println!("      --max-duplicate-run N  Longest run of frames collapsed into one (default: unlimited)");
+        println!("      --on-skip MODE         What to do with files that need no upscaling: copy | transcode | skip (default: skip)");
+        println!("      --on-frame-error MODE  What to do when a frame fails to decode or upscale: abort | skip | repeat-previous");
+        println!("                             (default: abort), repeat-previous keeps audio in sync; failures are summarized at the end");
+        println!("      --backend BACKEND      Inference backend: ncnn | coreml | cpu (default: ncnn, cpu in cpu-only builds)");
+        println!("      --cpu-workers N        Also upscale on N CPU threads, sharing frames by measured speed (default: 0)");
+        println!("                             with --backend cpu, the number of threads (default: one per core)");
+        println!("      --skip-static-segments Scale black or unchanging runs of 2 seconds or more with lanczos instead of the model");
+        println!("      --adaptive-scale       Experimental: upscale low-detail scenes with the 2x variant of the model and lanczos");
+        println!("      --quality-gate         Use a lanczos resize instead of the model output for frames where the model adds noise");
+        println!("      --tile-size N          Upscale on the GPU in NxN tiles instead of sizing them from free VRAM");
+        println!("      --keep-frames DIR      Also save upscaled frames to DIR/<input name>/00000000.png (before final scaling)");
+        println!("      --frames-format FMT    Image format for --keep-frames: png | tiff | bmp (default: png)");
+        println!("      --frames-only          Only save the frames from --keep-frames, without merging a video");
+        println!("      --screenshots OPTS     Save original/upscaled stills while processing, e.g. every=5m,dir=shots (default: every=5m,dir=screenshots)");
+        println!("      --cache-dir DIR        Cache upscaled frames in DIR so re-runs of the same input skip inference");
+        println!("      --parallel-files N     Process up to N files concurrently (default: 1)");
+        println!("      --order ORDER          Order of files in a directory: name | size | duration | shortest-first (default: name)");
+        println!("      --limit N              Only process the first N files of a directory");
+        println!("      --resume-batch         Continue a batch from its state file, skipping files it records as done");
+        println!("                             (batches write .upscaler-batch.json next to their outputs)");
+        println!("      --fragmented           Write fragmented mp4/mov so an interrupted output stays playable and --resume-batch");
+        println!("                             continues it from where it stopped instead of starting over");
+        println!("      --package FORMAT       Write each output as a streaming package in its own folder: hls | dash");
+        println!("                             (a .m3u8 or .mpd output is packaged without it)");
+        println!("      --segment-time SECONDS Segment length of a package (default: 6), or write numbered files of SECONDS");
+        println!("                             without one, e.g. for live rtsp:// or rtmp:// inputs");
+        println!("      --segment-name NAME    Name segment files NAME-00000.ts and so on (default: the output file name)");
+        println!("      --progress MODE        Progress output: bar | plain | json | none (default: bar on a terminal, plain otherwise)");
+        println!("      --stats                Print channel occupancy and blocked sends every few seconds while processing");
+        println!("      --channel-capacity C   Frames buffered after each stage, e.g. extract=4,upscale=8 (default: 1, upscale: 4)");
+        println!("                             stages: extract | static-segments | adaptive-scale | screenshots | upscale | progress |");
+        println!("                             keep-frames");
+        println!("      --reprocess-range R    Redo START..END (e.g. 1:20:00..1:30:00) of an existing output and stitch it back in");
+        println!("                             the range widens to the output's keyframes, the rest is copied losslessly");
+        println!("      --notify-url URL       POST a JSON result (status, input, output, error, elapsed_seconds) when each file ends");
+        println!("      --notify-cmd CMD       Run CMD through the shell when each file ends, with UPSCALER_STATUS, UPSCALER_INPUT,");
+        println!("                             UPSCALER_OUTPUT, UPSCALER_ERROR and UPSCALER_ELAPSED set");
+        println!("      --report FILE          Write the end-of-run summary of every file to FILE (.json or .csv)");
+        println!("      --metrics ADDRESS      Serve Prometheus metrics of the run at http://ADDRESS/metrics (e.g. 127.0.0.1:9100)");
+        println!("      --preview-window       Show the latest upscaled frame in a window, refreshed every second");
+        println!("      --background           Lower CPU/IO priority and run one upscale at a time to keep the machine usable");
+        println!("      --gpu-limit PERCENT    Pause between frames to keep GPU utilization around PERCENT (default: 100)");
+        println!("      --queue-slot N         Share the GPU with other running upscalers, at most N of them upscale at once");
+        println!("      --max-fps N            Upscale at most N frames per second across all workers and files, leaving");
+        println!("                             thermal headroom on laptops (default: unlimited)");
+        println!("      --replace-output       Replace the output file if it already exists (also --replace_output)");
+        println!("                             outputs are written to FILE.part and renamed once complete");
+        println!("      --skip-policy POLICY   When an output exists: exists (skip it) | valid (skip it unless it is truncated) | never");
+        println!("                             (default: exists)");
+        println!("      --keep-going           Log a failed file and continue with the next one, exiting with an error at the end");
+        println!("                             (default: on when UPSCALER_KEEP_GOING is 1 or true)");
+        println!("      --verify               Check the frame count, duration, resolution and streams of each output before keeping it");
+        println!("      --verify-frames N      With --verify, also decode N random frames of the output and fail if any is corrupt or black (default: 0)");
+        println!("      --reference FILE       After merging, measure PSNR, SSIM and VMAF against the master FILE and write <output>.metrics.json");
+        println!("      --ffmpeg-path PATH     Use this ffmpeg binary (default: $FFMPEG_PATH, then PATH and common locations)");
+        println!("      --ffprobe-path PATH    Use this ffprobe binary (default: $FFPROBE_PATH, then PATH and common locations)");
+        println!("  -v, --verbose              Show debug output, including ffmpeg's own messages");
+        println!("      --quiet                Only show errors and hide the progress bar");
+        println!("      --log-file PATH        Append all messages, including ffmpeg output, to a log file");
         println!("      --help                 Display this help message and exit");
+        println!("      --version              Same as info: print versions, ffmpeg, GPUs, models and plugins, then exit");
         exit(0);
     }
 
-    fn parse_numeric_arg<O: std::str::FromStr>(&self, args: &[String], index: &mut usize, arg_name: &str) -> Result<O, Error> {
+    fn parse_numeric_arg<O: std::str::FromStr>(&self, args: &[OsString], index: &mut usize, arg_name: &str) -> Result<O, Error> {
         let value = self.get_next_arg(args, index, arg_name)?;
         value.parse().map_err(|_| Error::new(format!("Argument '{}' must be a number", arg_name)))
     }
 
-    fn set_input_files(&mut self) -> Result<(), Error> {
-        if self.input.is_empty() {
+    fn set_input_files(&mut self, encoders: &str) -> Result<(), Error> {
+        if let Some(manifest) = self.manifest.take() {
+            return self.set_manifest_files(&manifest, encoders);
+        }
+
+        if self.input.as_os_str().is_empty() {
             return Err(Error::new("Input is empty".to_string()));
         }
+
+        if let Some(input_files) = self.read_input_list()? {
+            self.files = input_files
+                .into_iter()
+                .map(|file| self.create_job(file, &[], encoders))
+                .collect::<Result<Vec<Job>, Error>>()?;
+            return Ok(());
+        }
+
+        if Sequence::is_pattern(&self.input) || Remote::is_url(&self.input) {
+            self.files = vec![Job::new(self.input.clone(), self.settings.clone())];
+            return Ok(());
+        }
         
-        let path = Path::new(&self.input);
+        let path = self.input.as_path();
         if !path.exists() {
             return Err(Error::new(format!("Input file or directory not found: {}", path.display())));
         }
 
         let input_files = if path.is_dir() {
-            self.get_files_from_directory(path)?
+            let mut files = self.get_files_from_directory(path)?;
+            self.order.sort(&mut files);
+            files.truncate(self.limit.unwrap_or(files.len()));
+            files
         } else {
             vec![self.get_file_if_valid(path).ok_or_else(|| Error::new(format!("Input file not found: {}", path.display())))?]
         };
@@ -127,21 +515,103 @@ impl Arguments {
             return Err(Error::new("No valid input files found".to_string()));
         }
 
-        self.files = input_files.into_iter().map(|f| (f, String::new())).collect();
+        self.files = input_files
+            .into_iter()
+            .map(|file| self.create_job(file, &[], encoders))
+            .collect::<Result<Vec<Job>, Error>>()?;
+        Ok(())
+    }
+
+    // `-i @list.txt` or `-i -` for stdin, with one path per line; blank lines and # comments are ignored
+    fn read_input_list(&self) -> Result<Option<Vec<PathBuf>>, Error> {
+        let input = self.input.to_string_lossy();
+        let (source, content) = if input == "-" {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|e| Error::new(format!("Failed to read the input list from stdin: {}", e)))?;
+            (String::from("stdin"), content)
+        } else if let Some(list) = input.strip_prefix('@') {
+            let content = std::fs::read_to_string(list)
+                .map_err(|e| Error::new(format!("Failed to read input list {}: {}", list, e)))?;
+            (list.to_string(), content)
+        } else {
+            return Ok(None);
+        };
+
+        let input_files = content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_number, line)| {
+                let path = PathBuf::from(line);
+                if path.is_file() || Remote::is_url(&path) || Sequence::is_pattern(&path) {
+                    Ok(path)
+                } else if path.is_dir() {
+                    Err(Error::new(format!("{} line {}: {} is a directory, list its files instead", source, line_number, line)))
+                } else {
+                    Err(Error::new(format!("{} line {}: input file not found: {}", source, line_number, line)))
+                }
+            })
+            .collect::<Result<Vec<PathBuf>, Error>>()?;
+
+        if input_files.is_empty() {
+            return Err(Error::new(format!("Input list {} has no files", source)));
+        }
+        Ok(Some(input_files))
+    }
+
+    // Options layer as command line, then the input's sidecar file, then the manifest row
+    fn create_job(&self, input: PathBuf, overrides: &[(String, String)], encoders: &str) -> Result<Job, Error> {
+        let sidecar = Sidecar::load(&input)?;
+        let mut job = Job::new(input, self.settings.clone());
+        if sidecar.is_none() && overrides.is_empty() {
+            return Ok(job);
+        }
+        if sidecar.is_some() {
+            Logger::debug(format!("Using options from {}", Sidecar::path(&job.input).display()));
+        }
+        for (key, value) in sidecar.iter().flatten().chain(overrides) {
+            job.settings.set(key, value)
+                .map_err(|e| Error::new(format!("{}: {}", job.input.display(), e)))?;
+        }
+        job.settings.validate(encoders)
+            .map_err(|e| Error::new(format!("{}: {}", job.input.display(), e)))?;
+        Ok(job)
+    }
+
+    fn set_manifest_files(&mut self, manifest: &Path, encoders: &str) -> Result<(), Error> {
+        for entry in Manifest::load(manifest)? {
+            if !entry.input.is_file() && !Remote::is_url(&entry.input) {
+                return Err(Error::new(format!("Input file not found: {}", entry.input.display())));
+            }
+
+            let mut job = self.create_job(entry.input, &entry.overrides, encoders)?;
+            if let Some(output) = entry.output {
+                job.output = output;
+            }
+            self.files.push(job);
+        }
+
+        if self.files.is_empty() {
+            return Err(Error::new("No valid input files found".to_string()));
+        }
+
         Ok(())
     }
 
-    fn get_file_if_valid(&self, path: &Path) -> Option<String> {
+    fn get_file_if_valid(&self, path: &Path) -> Option<PathBuf> {
         path.is_file().then(|| {
             path.extension()
                 .and_then(std::ffi::OsStr::to_str)
                 .map(str::to_lowercase)
                 .filter(|ext| self.formats.contains(ext))
-                .map(|_| path.to_string_lossy().into_owned())
+                .map(|_| path.to_path_buf())
         }).flatten()
     }
 
-    fn get_files_from_directory(&self, dir: &Path) -> Result<Vec<String>, Error> {
+    fn get_files_from_directory(&self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
         std::fs::read_dir(dir)
             .map_err(|e| Error::new(format!("Failed to read directory: {}", e)))?
             .filter_map(|entry| entry.ok().and_then(|e| self.get_file_if_valid(&e.path())))
@@ -152,22 +622,53 @@ impl Arguments {
     }
 
     fn set_output_files(&mut self) -> Result<(), Error> {
+        if self.organize.is_some() && self.output.as_ref().is_none_or(|output| output.is_file() || output.extension().is_some()) {
+            return Err(Error::new("--organize needs an --output directory to build the library in"));
+        }
         if let Some(output) = self.output.take() {
             self.set_output_with_path(&output)?;
         } else {
             self.set_default_output()?;
         }
 
-        if self.files.iter().any(|(_, output)| output.is_empty()) {
-            return Err(Error::new(format!("Failed to create output file: {}", self.input)));
+        if self.files.iter().any(|job| job.output.as_os_str().is_empty()) {
+            return Err(Error::new(format!("Failed to create output file: {}", self.input.display())));
+        }
+        for job in &mut self.files {
+            if let Some(package) = job.settings.package {
+                job.output = package.output_path(&job.output);
+            }
+        }
+
+        for job in &self.files {
+            if let Some(output_dir) = job.output.parent() {
+                std::fs::create_dir_all(output_dir)
+                    .map_err(|e| Error::new(format!("Failed to create output directory: {}", e)))?;
+            }
+        }
+
+        if self.files.len() > 1 || self.resume_batch {
+            let batch = BatchState::open(&self.files, self.resume_batch)?;
+            if self.resume_batch {
+                // the state file decides what is left, unfinished outputs are redone
+                self.files.retain(|job| !batch.is_done(job));
+                self.replace_output = true;
+            }
+            self.batch = Some(batch);
         }
+        self.report = Report::new(self.report_path.clone(), self.files.len());
 
         self.files = self.files
             .clone()
             .into_iter()
-            .filter(|(_, output)| {
-                if Path::new(output).exists() && !self.replace_output {
-                    println!("Skipping {} output file already exists", output);
+            .filter(|job| {
+                let existing = job.output.exists() && !self.replace_output && self.reprocess.is_none();
+                if existing && self.skip_policy.skips(&job.output, || MediaInfo::probe(&job.input).ok()?.duration) {
+                    Logger::info(format!("Skipping {} output file already exists", job.output.display()));
+                    if let Some(batch) = &self.batch {
+                        batch.skip(job);
+                    }
+                    self.report.skip(job);
                     false
                 } else {
                     true
@@ -178,10 +679,13 @@ impl Arguments {
         Ok(())
     }
 
-    fn set_output_with_path(&mut self, output: &str) -> Result<(), Error> {
-        let path = Path::new(output);
+    fn pending_outputs(&mut self) -> impl Iterator<Item = &mut Job> {
+        self.files.iter_mut().filter(|job| job.output.as_os_str().is_empty())
+    }
+
+    fn set_output_with_path(&mut self, path: &Path) -> Result<(), Error> {
         if (path.exists() && path.is_file()) || path.extension().is_some() {
-            if self.files.len() > 1 {
+            if self.files.iter().filter(|job| job.output.as_os_str().is_empty()).count() > 1 {
                 return Err(Error::new(format!("Output file already exists: {}", path.display())));
             }
             self.set_single_output_file(path)?;
@@ -193,84 +697,121 @@ impl Arguments {
     }
 
     fn set_single_output_file(&mut self, output_path: &Path) -> Result<(), Error> {
-        if let Some(output_dir) = output_path.parent() {
-            std::fs::create_dir_all(output_dir)
-                .map_err(|e| Error::new(format!("Failed to create output directory: {}", e)))?;
-            self.files[0].1 = output_path.to_string_lossy().into_owned();
-        } else {
+        if output_path.parent().is_none() {
             return Err(Error::new(format!("Failed to create output file: {}", output_path.display())));
         }
+        for job in self.pending_outputs() {
+            job.output = output_path.to_path_buf();
+        }
         Ok(())
     }
 
     fn set_multiple_output_files(&mut self, output_path: &Path) -> Result<(), Error> {
-        for (input, output) in &mut self.files {
-            let input_path = Path::new(input);
-            *output = output_path.join(input_path.file_name().unwrap()).to_string_lossy().into_owned();
+        let (template, organize) = (self.output_template.clone(), self.organize);
+        for job in self.pending_outputs() {
+            let input = Remote::file_path(&job.input);
+            let episode = organize.and_then(|organize| match Episode::detect(&input) {
+                Some(episode) => Some((organize, episode)),
+                None => {
+                    Logger::warning(format!("No SxxEyy episode in {}, leaving it out of the library folders", input.display()));
+                    None
+                },
+            });
+            let output_file = match (&template, episode) {
+                (_, Some((organize, episode))) => {
+                    let template = template.as_deref().unwrap_or(organize.template());
+                    output_path.join(organize.folder(&episode)).join(OutputTemplate::render_file_name(template, &input, job.settings.format))
+                },
+                (Some(template), None) => output_path.join(OutputTemplate::render_file_name(template, &input, job.settings.format)),
+                (None, None) => {
+                    let mut output_file = output_path.join(input.file_name().unwrap_or_default());
+                    if let Some(format) = job.settings.format {
+                        output_file.set_extension(format.extension());
+                    }
+                    output_file
+                },
+            };
+            job.output = output_file;
         }
-        std::fs::create_dir_all(output_path)
-            .map_err(|e| Error::new(format!("Failed to create output directory: {}", e)))?;
         Ok(())
     }
 
     fn set_default_output(&mut self) -> Result<(), Error> {
-        for (input, output) in &mut self.files {
-            let input_path = Path::new(input);
-            let output_path = input_path.parent().unwrap_or_else(|| Path::new("."));
-            let mut file_name = input_path.file_stem().unwrap().to_string_lossy().to_string();
-            file_name.push_str("_converted");
-            file_name.push_str(&input_path.extension().unwrap().to_string_lossy());
-            *output = output_path.join(file_name).to_string_lossy().into_owned();
+        let template = self.output_template.clone().unwrap_or_else(|| OutputTemplate::DEFAULT.to_string());
+        for job in self.pending_outputs() {
+            // remote inputs are written to the working directory
+            let input = Remote::file_path(&job.input);
+            let output_path = input.parent().unwrap_or_else(|| Path::new("."));
+            let file_name = OutputTemplate::render_file_name(&template, &input, job.settings.format);
+            job.output = output_path.join(file_name);
         }
         Ok(())
     }
 
+    fn configure_binaries(&mut self) -> Result<(), Error> {
+        Binary::Ffmpeg.configure(self.ffmpeg_path.take())?;
+        Binary::Ffprobe.configure(self.ffprobe_path.take())
+    }
+
     fn check_ffmpeg(&self) -> Result<(), Error> {
-        match Command::new("ffmpeg").spawn() {
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(Error::new(format!("Cannot find ffmpeg: {}", &e))),
-            Err(_) => Ok(()),
-            Ok(mut c) => {
-                let _ = c.kill();
-                Ok(())
-            },
+        for binary in [Binary::Ffmpeg, Binary::Ffprobe] {
+            match binary.command().arg("-version").stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Error::new(format!("Cannot find {}: {}", binary.path().display(), &e)))
+                },
+                Err(_) => {},
+                Ok(mut c) => {
+                    let _ = c.kill();
+                    let _ = c.wait();
+                },
+            }
         }
+        Ok(())
+    }
+
+    // Existing outputs that reach the pipeline were judged worth redoing by the skip policy
+    pub fn replaces_output(&self) -> bool {
+        self.replace_output || self.skip_policy != SkipPolicy::Exists
     }
 
-    fn validate_model(&self) -> Result<(), Error> {
-        match self.model.as_str() {
-            "realcugan" | "realesrgan" | "realesrgan-anime" | "realesr-anime" => Ok(()),
-            _ => Err(Error::new(format!("Invalid model: {}. Must be realcugan, realesrgan, realesrgan-anime or realesr-anime", self.model))),
+    fn validate_parallel_files(&self) -> Result<(), Error> {
+        if self.parallel_files == 0 {
+            return Err(Error::new("Invalid parallel files: 0. Must be at least 1"));
         }
+
+        Ok(())
     }
 
-    fn validate_resolution_and_scale(&mut self) -> Result<(), Error> {
-        if let Some(width) = self.width {
-            if width < 16 || width > 7680 {
-                return Err(Error::new(format!("Invalid width: {}. Must be between 16 and 7680", width)));
-            }
+    fn validate_limit(&self) -> Result<(), Error> {
+        if self.limit == Some(0) {
+            return Err(Error::new("Invalid limit: 0. Must be at least 1"));
         }
-    
-        if let Some(height) = self.height {
-            if height < 16 || height > 4320 {
-                return Err(Error::new(format!("Invalid height: {}. Must be between 16 and 4320", height)));
-            }
+
+        Ok(())
+    }
+
+    fn validate_queue_slots(&self) -> Result<(), Error> {
+        if self.queue_slots == Some(0) {
+            return Err(Error::new("Invalid queue slots: 0. Must be at least 1"));
         }
-    
+
         Ok(())
     }
 
-    fn validate_encoder(&self) -> Result<(), Error> {
-        let output = Command::new("ffmpeg")
+    fn validate_gpu_limit(&self) -> Result<(), Error> {
+        if self.gpu_limit == 0 || self.gpu_limit > 100 {
+            return Err(Error::new(format!("Invalid GPU limit: {}. Must be between 1 and 100", self.gpu_limit)));
+        }
+
+        Ok(())
+    }
+
+    fn get_encoders() -> Result<String, Error> {
+        let output = Binary::Ffmpeg.command()
             .args(&["-hide_banner", "-encoders"])
             .output()
             .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
 
-        let encoders = String::from_utf8_lossy(&output.stdout);
-        
-        if !encoders.contains(&self.encoder) {
-            return Err(Error::new(format!("Invalid encoder: {}. Available encoders: {}", self.encoder, encoders)));
-        }
-
-        Ok(())
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+use crate::error::Error;
+
+// How a target size with another aspect ratio than the input is met: keep shrinks the target to the input's aspect
+// ratio, pad and crop fit or fill the exact target, stretch ignores the aspect ratio
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AspectPolicy {
+    Keep,
+    Pad,
+    Crop,
+    Stretch,
+}
+
+impl AspectPolicy {
+    const TOLERANCE: f64 = 0.01;
+
+    // The size the picture itself is scaled to inside the target
+    pub fn content_size(&self, width: usize, height: usize, aspect_ratio: f64) -> (usize, usize) {
+        let target_aspect_ratio = width as f64 / height as f64;
+        if (target_aspect_ratio - aspect_ratio).abs() <= Self::TOLERANCE || *self == AspectPolicy::Stretch {
+            return (width, height);
+        }
+        let wider = target_aspect_ratio > aspect_ratio;
+        match (self, wider) {
+            (AspectPolicy::Keep | AspectPolicy::Pad, true) | (AspectPolicy::Crop, false) => {
+                ((height as f64 * aspect_ratio).round() as usize, height)
+            },
+            _ => (width, (width as f64 / aspect_ratio).round() as usize),
+        }
+    }
+
+    pub fn output_size(&self, target: (usize, usize), content: (usize, usize)) -> (usize, usize) {
+        match self {
+            AspectPolicy::Keep => content,
+            AspectPolicy::Pad | AspectPolicy::Crop | AspectPolicy::Stretch => target,
+        }
+    }
+
+    pub fn get_filter(&self, width: usize, height: usize, flags: &str) -> String {
+        match self {
+            AspectPolicy::Keep => format!("scale={}x{}:flags={}", width, height, flags),
+            AspectPolicy::Stretch => format!("scale={}x{}:flags={},setsar=1", width, height, flags),
+            AspectPolicy::Pad => format!(
+                "scale={w}x{h}:flags={}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:black",
+                flags, w = width, h = height
+            ),
+            AspectPolicy::Crop => format!(
+                "scale={w}x{h}:flags={}:force_original_aspect_ratio=increase,crop={w}:{h}", flags, w = width, h = height
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for AspectPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "keep" => Ok(AspectPolicy::Keep),
+            "pad" => Ok(AspectPolicy::Pad),
+            "crop" => Ok(AspectPolicy::Crop),
+            "stretch" => Ok(AspectPolicy::Stretch),
+            _ => Err(Error::new(format!("Invalid aspect ratio policy: {}. Must be keep, pad, crop or stretch", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for AspectPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AspectPolicy::Keep => write!(f, "keep"),
+            AspectPolicy::Pad => write!(f, "pad"),
+            AspectPolicy::Crop => write!(f, "crop"),
+            AspectPolicy::Stretch => write!(f, "stretch"),
+        }
+    }
+}
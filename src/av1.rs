@@ -0,0 +1,79 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Av1Tuning {
+    pub preset: Option<u8>,
+    pub film_grain: Option<u8>,
+}
+
+impl Av1Tuning {
+    const SVT_AV1: &'static str = "libsvtav1";
+    const AOM_AV1: &'static str = "libaom-av1";
+    const MAX_PRESET: u8 = 13;
+    const MAX_FILM_GRAIN: u8 = 50;
+    const DEFAULT_PRESET: u8 = 8;
+    const DEFAULT_CPU_USED: &'static str = "4";
+    const DEFAULT_CRF: &'static str = "30";
+    const KEYFRAME_SECONDS: f64 = 10.0;
+
+    pub fn supports_film_grain(encoder: &str) -> bool {
+        encoder == Self::SVT_AV1 || encoder == Self::AOM_AV1
+    }
+
+    pub fn validate(&self, encoder: &str) -> Result<(), Error> {
+        if let Some(preset) = self.preset {
+            if encoder != Self::SVT_AV1 {
+                return Err(Error::new(format!("--svt-preset requires the {} encoder, not {}", Self::SVT_AV1, encoder)));
+            }
+            if preset > Self::MAX_PRESET {
+                return Err(Error::new(format!("Invalid SVT-AV1 preset: {}. Must be between 0 and {}", preset, Self::MAX_PRESET)));
+            }
+        }
+
+        if let Some(film_grain) = self.film_grain {
+            if !Self::supports_film_grain(encoder) {
+                return Err(Error::new(format!(
+                    "--film-grain requires the {} or {} encoder, not {}", Self::SVT_AV1, Self::AOM_AV1, encoder
+                )));
+            }
+            if film_grain > Self::MAX_FILM_GRAIN {
+                return Err(Error::new(format!("Invalid film grain: {}. Must be between 0 and {}", film_grain, Self::MAX_FILM_GRAIN)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_args(&self, encoder: &str, stream: usize, frame_rate: f64) -> Vec<String> {
+        let keyint = ((frame_rate * Self::KEYFRAME_SECONDS).round() as usize).max(1).to_string();
+        let option = |name: &str| format!("-{}:v:{}", name, stream);
+
+        match encoder {
+            Self::SVT_AV1 => {
+                let mut args = vec![
+                    option("crf"), Self::DEFAULT_CRF.to_string(),
+                    option("g"), keyint,
+                    option("preset"), self.preset.unwrap_or(Self::DEFAULT_PRESET).to_string(),
+                ];
+                if let Some(film_grain) = self.film_grain {
+                    args.extend([option("svtav1-params"), format!("film-grain={}:film-grain-denoise=0", film_grain)]);
+                }
+                args
+            },
+            Self::AOM_AV1 => {
+                let mut args = vec![
+                    option("crf"), Self::DEFAULT_CRF.to_string(),
+                    option("b"), "0".to_string(),
+                    option("g"), keyint,
+                    option("cpu-used"), Self::DEFAULT_CPU_USED.to_string(),
+                    option("row-mt"), "1".to_string(),
+                ];
+                if let Some(film_grain) = self.film_grain {
+                    args.extend([option("denoise-noise-level"), film_grain.to_string()]);
+                }
+                args
+            },
+            _ => Vec::new(),
+        }
+    }
+}
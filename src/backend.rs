@@ -0,0 +1,62 @@
+use crate::error::Error;
+use crate::logger::Logger;
+
+use std::sync::Once;
+
+// ncnn is built with Vulkan, so a build without the GPU has to leave it out altogether
+#[cfg(all(feature = "cpu-only", feature = "ncnn"))]
+compile_error!("cpu-only builds leave out ncnn and Vulkan, build them with --no-default-features --features cpu-only");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Ncnn,
+    CoreMl,
+    Cpu,
+}
+
+impl Backend {
+    pub const COREML_AVAILABLE: bool = cfg!(all(feature = "coreml", target_os = "macos"));
+    pub const GPU_AVAILABLE: bool = cfg!(feature = "ncnn");
+
+    pub fn default_backend() -> Self {
+        if Self::GPU_AVAILABLE { Backend::Ncnn } else { Backend::Cpu }
+    }
+
+    // CoreML needs macOS and a build with the coreml feature, anywhere else the ncnn models are used.
+    // Builds without ncnn run everything on the CPU
+    pub fn resolve(self) -> Self {
+        static WARNING: Once = Once::new();
+        if self != Backend::Cpu && !Self::GPU_AVAILABLE {
+            WARNING.call_once(|| Logger::warning(format!("This build has no GPU backends, using the CPU instead of {}", self)));
+            return Backend::Cpu;
+        }
+        if self == Backend::CoreMl && !Self::COREML_AVAILABLE {
+            WARNING.call_once(|| Logger::warning("The CoreML backend is not available in this build, using ncnn"));
+            return Backend::Ncnn;
+        }
+        self
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "ncnn" => Ok(Backend::Ncnn),
+            "coreml" => Ok(Backend::CoreMl),
+            "cpu" => Ok(Backend::Cpu),
+            _ => Err(Error::new(format!("Invalid backend: {}. Must be ncnn, coreml or cpu", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Ncnn => write!(f, "ncnn"),
+            Backend::CoreMl => write!(f, "coreml"),
+            Backend::Cpu => write!(f, "cpu"),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::Error;
+use crate::media_info::MediaInfo;
+use crate::logger::Logger;
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchOrder {
+    Name,
+    Size,
+    Duration,
+    ShortestFirst,
+}
+
+impl BatchOrder {
+    fn probe_duration(path: &Path) -> Option<f64> {
+        match MediaInfo::probe(path) {
+            Ok(media) => media.duration,
+            Err(e) => {
+                Logger::warning(format!("Cannot read the duration of {}: {}", path.display(), e));
+                None
+            },
+        }
+    }
+
+    // Files whose key is unknown go last
+    fn sort_by_key<K: PartialOrd>(files: &mut Vec<PathBuf>, key: impl Fn(&Path) -> Option<K>, descending: bool) {
+        let mut keyed = files.drain(..).map(|path| (key(&path), path)).collect::<Vec<_>>();
+        keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => {
+                let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                if descending { ordering.reverse() } else { ordering }
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        files.extend(keyed.into_iter().map(|(_, path)| path));
+    }
+
+    pub fn sort(&self, files: &mut Vec<PathBuf>) {
+        files.sort();
+        match self {
+            BatchOrder::Name => (),
+            BatchOrder::Size => Self::sort_by_key(files, |path| path.metadata().ok().map(|metadata| metadata.len()), true),
+            BatchOrder::Duration => Self::sort_by_key(files, Self::probe_duration, true),
+            BatchOrder::ShortestFirst => Self::sort_by_key(files, Self::probe_duration, false),
+        }
+    }
+}
+
+impl std::str::FromStr for BatchOrder {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "name" => Ok(BatchOrder::Name),
+            "size" => Ok(BatchOrder::Size),
+            "duration" => Ok(BatchOrder::Duration),
+            "shortest-first" => Ok(BatchOrder::ShortestFirst),
+            _ => Err(Error::new(format!("Invalid order: {}. Must be name, size, duration or shortest-first", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for BatchOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchOrder::Name => write!(f, "name"),
+            BatchOrder::Size => write!(f, "size"),
+            BatchOrder::Duration => write!(f, "duration"),
+            BatchOrder::ShortestFirst => write!(f, "shortest-first"),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::error::Error;
+use crate::job::Job;
+use crate::logger::Logger;
+use crate::cancel::Cancel;
+
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Pending,
+    Completed,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pending => "pending",
+            Status::Completed => "completed",
+            Status::Skipped => "skipped",
+            Status::Failed => "failed",
+            Status::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Status::Pending),
+            "completed" => Some(Status::Completed),
+            "skipped" => Some(Status::Skipped),
+            "failed" => Some(Status::Failed),
+            "cancelled" => Some(Status::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    input: String,
+    output: String,
+    options: String,
+    status: Status,
+    error: Option<String>,
+}
+
+impl Entry {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    fn new(job: &Job) -> Self {
+        let options = format!("{:?}", job.settings)
+            .bytes()
+            .fold(Self::FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(Self::FNV_PRIME));
+        Self {
+            input: job.input.display().to_string(),
+            output: job.output.display().to_string(),
+            options: format!("{:016x}", options),
+            status: Status::Pending,
+            error: None,
+        }
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        Some(Self {
+            input: value.get("input")?.as_str()?.to_string(),
+            output: value.get("output")?.as_str()?.to_string(),
+            options: value.get("options")?.as_str()?.to_string(),
+            status: Status::parse(value.get("status")?.as_str()?)?,
+            error: value.get("error").and_then(Value::as_str).map(String::from),
+        })
+    }
+
+    fn same_job(&self, other: &Entry) -> bool {
+        self.input == other.input && self.output == other.output && self.options == other.options
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self.status, Status::Completed | Status::Skipped)
+    }
+}
+
+// Records every input of a batch and what happened to it, so an interrupted batch can be resumed
+#[derive(Clone)]
+pub struct BatchState {
+    path: PathBuf,
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl BatchState {
+    const FILE_NAME: &'static str = ".upscaler-batch.json";
+    const VERSION: u64 = 1;
+
+    fn load(path: &Path) -> Result<Vec<Entry>, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::new(format!("Failed to read batch state {}: {}", path.display(), e)))?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| Error::new(format!("Failed to parse batch state {}: {}", path.display(), e)))?;
+        let files = value.get("files")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::new(format!("Invalid batch state: {}", path.display())))?;
+        Ok(files.iter().filter_map(Entry::parse).collect())
+    }
+
+    pub fn open(jobs: &[Job], resume: bool) -> Result<Self, Error> {
+        let directory = jobs.first().and_then(|job| job.output.parent()).unwrap_or_else(|| Path::new("."));
+        let path = directory.join(Self::FILE_NAME);
+        let previous = if resume { Self::load(&path)? } else { Vec::new() };
+
+        // Inputs whose output or options changed since the last run start over
+        let entries = jobs.iter().map(Entry::new).map(|entry| {
+            match previous.iter().find(|previous| previous.same_job(&entry)) {
+                Some(previous) => previous.clone(),
+                None => entry,
+            }
+        }).collect::<Vec<_>>();
+
+        if resume {
+            let done = entries.iter().filter(|entry| entry.is_done()).count();
+            Logger::info(format!("Resuming batch from {}: {} done, {} remaining", path.display(), done, entries.len() - done));
+        }
+
+        let state = Self { path, entries: Arc::new(Mutex::new(entries)) };
+        state.save(&state.entries.lock().unwrap())?;
+        Ok(state)
+    }
+
+    fn save(&self, entries: &[Entry]) -> Result<(), Error> {
+        let files = entries.iter().map(|entry| json!({
+            "input": entry.input,
+            "output": entry.output,
+            "options": entry.options,
+            "status": entry.status.as_str(),
+            "error": entry.error,
+        })).collect::<Vec<Value>>();
+        let content = serde_json::to_string_pretty(&json!({ "version": Self::VERSION, "files": files }))
+            .map_err(|e| Error::new(format!("Failed to serialize batch state: {}", e)))?;
+
+        let partial_path = self.path.with_extension("part");
+        fs::write(&partial_path, content)
+            .and_then(|_| fs::rename(&partial_path, &self.path))
+            .map_err(|e| Error::new(format!("Failed to write batch state {}: {}", self.path.display(), e)))
+    }
+
+    fn set_status(&self, job: &Job, status: Status, error: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        let job = Entry::new(job);
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.same_job(&job)) {
+            entry.status = status;
+            entry.error = error;
+        }
+        if let Err(e) = self.save(&entries) {
+            Logger::warning(e.to_string());
+        }
+    }
+
+    pub fn is_done(&self, job: &Job) -> bool {
+        let job = Entry::new(job);
+        self.entries.lock().unwrap().iter().any(|entry| entry.same_job(&job) && entry.is_done())
+    }
+
+    // A failed or cancelled file may have left a partial output worth resuming
+    pub fn was_interrupted(&self, job: &Job) -> bool {
+        let job = Entry::new(job);
+        self.entries.lock().unwrap().iter().any(|entry| entry.same_job(&job) && matches!(entry.status, Status::Failed | Status::Cancelled))
+    }
+
+    pub fn skip(&self, job: &Job) {
+        self.set_status(job, Status::Skipped, None);
+    }
+
+    pub fn update(&self, job: &Job, result: &Result<(), Error>) {
+        match result {
+            Ok(()) => self.set_status(job, Status::Completed, None),
+            Err(_) if Cancel::requested() => self.set_status(job, Status::Cancelled, None),
+            Err(e) => self.set_status(job, Status::Failed, Some(e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::error::Error;
+use crate::logger::{Capture, Logger};
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+static FFMPEG: OnceLock<PathBuf> = OnceLock::new();
+static FFPROBE: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Binary {
+    Ffmpeg,
+    Ffprobe,
+}
+
+impl Binary {
+    const COMMON_LOCATIONS: &'static [&'static str] = &[
+        "/usr/local/bin",
+        "/usr/bin",
+        "/opt/homebrew/bin",
+        "/opt/local/bin",
+        "/snap/bin",
+        "C:\\ffmpeg\\bin",
+        "C:\\Program Files\\ffmpeg\\bin",
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Binary::Ffmpeg => "ffmpeg",
+            Binary::Ffprobe => "ffprobe",
+        }
+    }
+
+    fn env_var(&self) -> &'static str {
+        match self {
+            Binary::Ffmpeg => "FFMPEG_PATH",
+            Binary::Ffprobe => "FFPROBE_PATH",
+        }
+    }
+
+    fn cell(&self) -> &'static OnceLock<PathBuf> {
+        match self {
+            Binary::Ffmpeg => &FFMPEG,
+            Binary::Ffprobe => &FFPROBE,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}{}", self.name(), env::consts::EXE_SUFFIX)
+    }
+
+    fn find_in(&self, directory: &Path) -> Option<PathBuf> {
+        let path = directory.join(self.file_name());
+        path.is_file().then_some(path)
+    }
+
+    fn locate(&self) -> PathBuf {
+        let bundled = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let search_path = env::var_os("PATH").map(|path| env::split_paths(&path).collect::<Vec<_>>()).unwrap_or_default();
+
+        bundled
+            .into_iter()
+            .chain(search_path)
+            .chain(Self::COMMON_LOCATIONS.iter().map(PathBuf::from))
+            .find_map(|directory| self.find_in(&directory))
+            .unwrap_or_else(|| PathBuf::from(self.name()))
+    }
+
+    fn resolve(&self, path: Option<PathBuf>) -> Result<PathBuf, Error> {
+        match path.or_else(|| env::var_os(self.env_var()).map(PathBuf::from)) {
+            Some(path) if path.is_file() => Ok(path),
+            Some(path) => Err(Error::new(format!("Cannot find {}: {}", self.name(), path.display()))),
+            None => Ok(self.locate()),
+        }
+    }
+
+    pub fn configure(&self, path: Option<PathBuf>) -> Result<(), Error> {
+        let path = self.resolve(path)?;
+        self.cell()
+            .set(path)
+            .map_err(|_| Error::new(format!("{} path is already configured", self.name())))
+    }
+
+    pub fn path(&self) -> &'static Path {
+        self.cell().get_or_init(|| self.locate())
+    }
+
+    pub fn command(&self) -> Command {
+        Command::new(self.path())
+    }
+
+    // Runs to completion, only logging the output when it fails
+    pub fn run(&self, args: Vec<OsString>, action: &str) -> Result<(), Error> {
+        let output = self.command()
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute {}: {}", self.name(), e)))?;
+
+        if !output.status.success() {
+            for line in String::from_utf8_lossy(&output.stderr).lines() {
+                Logger::debug(format!("[{}] {}", self.name(), line));
+            }
+            return Err(Capture::output_error(format!("{} failed to {}", self.name(), action), &output.stderr));
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    pub fn path_arg(path: &Path) -> OsString {
+        const MAX_PATH: usize = 260;
+        const VERBATIM_PREFIX: &str = r"\\?\";
+
+        if crate::remote::Remote::is_url(path) {
+            return path.as_os_str().to_os_string();
+        }
+
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        if absolute.as_os_str().len() < MAX_PATH || absolute.to_string_lossy().starts_with(VERBATIM_PREFIX) {
+            return path.as_os_str().to_os_string();
+        }
+
+        let mut verbatim = OsString::from(VERBATIM_PREFIX);
+        verbatim.push(absolute);
+        verbatim
+    }
+
+    #[cfg(not(windows))]
+    pub fn path_arg(path: &Path) -> OsString {
+        path.as_os_str().to_os_string()
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error::Error;
+use crate::binary::Binary;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Ten,
+}
+
+impl BitDepth {
+    const TEN_BIT_FORMATS: [&'static str; 2] = ["yuv420p10le", "p010le"];
+
+    fn supported_formats(encoder: &str) -> Result<Option<Vec<String>>, Error> {
+        let output = Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-h", &format!("encoder={}", encoder)])
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Supported pixel formats:"))
+            .map(|formats| formats.split_whitespace().map(String::from).collect()))
+    }
+
+    pub fn pixel_format(&self, encoder: &str) -> Result<&'static str, Error> {
+        if *self == BitDepth::Eight {
+            return Ok("yuv420p");
+        }
+
+        match Self::supported_formats(encoder)? {
+            None => Ok(Self::TEN_BIT_FORMATS[0]),
+            Some(formats) => Self::TEN_BIT_FORMATS
+                .into_iter()
+                .find(|format| formats.iter().any(|supported| supported == format))
+                .ok_or_else(|| Error::new(format!("Encoder {} does not support 10-bit output", encoder))),
+        }
+    }
+
+    // Final scaling runs at 16 bits so the reduction to 10 bits is dithered instead of rounding 8-bit steps
+    pub fn get_precision_filter(&self) -> Option<&'static str> {
+        match self {
+            BitDepth::Eight => None,
+            BitDepth::Ten => Some("format=gbrp16le"),
+        }
+    }
+}
+
+impl std::str::FromStr for BitDepth {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "8" => Ok(BitDepth::Eight),
+            "10" => Ok(BitDepth::Ten),
+            _ => Err(Error::new(format!("Invalid bit depth: {}. Must be 8 or 10", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for BitDepth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitDepth::Eight => write!(f, "8"),
+            BitDepth::Ten => write!(f, "10"),
+        }
+    }
+}
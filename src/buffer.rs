@@ -0,0 +1,78 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct BufferPool {
+    buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+    capacity: usize,
+}
+
+impl BufferPool {
+    // Frames start on a cache line, which the SIMD loops of the backends and image crate prefer
+    const ALIGNMENT: usize = 64;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffers: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    // A reused buffer still holds the pixels of an earlier frame, every caller overwrites all of it
+    pub fn acquire(&self, size: usize) -> Buffer {
+        let reused = {
+            let mut buffers = self.buffers.lock().unwrap();
+            buffers
+                .iter()
+                .position(|buffer| buffer.len() >= size + Self::ALIGNMENT)
+                .map(|position| buffers.swap_remove(position))
+        };
+
+        let data = reused.unwrap_or_else(|| vec![0; size + Self::ALIGNMENT]);
+        let offset = data.as_ptr().align_offset(Self::ALIGNMENT);
+        Buffer { data, offset, len: size, pool: Some(self.clone()) }
+    }
+
+    fn release(&self, data: Vec<u8>) {
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < self.capacity {
+            buffers.push(data);
+        }
+    }
+}
+
+pub struct Buffer {
+    data: Vec<u8>,
+    offset: usize,
+    len: usize,
+    pool: Option<BufferPool>,
+}
+
+impl From<Vec<u8>> for Buffer {
+    fn from(data: Vec<u8>) -> Self {
+        let len = data.len();
+        Self { data, offset: 0, len, pool: None }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data[self.offset..self.offset + self.len]
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data[self.offset..self.offset + self.len]
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.release(std::mem::take(&mut self.data));
+        }
+    }
+}
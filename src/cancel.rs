@@ -0,0 +1,37 @@
+use crate::error::Error;
+use crate::logger::Logger;
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// SIGTERM, SIGINT and SIGHUP stop decoding, let the encoder finish what it was given and clean up the file in progress
+pub struct Cancel;
+
+impl Cancel {
+    // 128 + SIGTERM, the status shells and systemd expect from a process stopped by a signal
+    pub const EXIT_CODE: i32 = 143;
+    pub const ERROR_CODE: i32 = 1;
+
+    // A second signal exits immediately, for when the cleanup itself hangs
+    pub fn install() {
+        let result = ctrlc::set_handler(|| {
+            if REQUESTED.swap(true, Ordering::SeqCst) {
+                process::exit(Self::EXIT_CODE);
+            }
+            Logger::warning("Cancelling, finishing the current write (signal again to exit immediately)");
+        });
+        if let Err(e) = result {
+            Logger::warning(format!("Failed to install the signal handler: {}", e));
+        }
+    }
+
+    pub fn requested() -> bool {
+        REQUESTED.load(Ordering::SeqCst)
+    }
+
+    pub fn error() -> Error {
+        Error::new("Cancelled")
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::Error;
+use crate::binary::Binary;
+use crate::settings::Settings;
+use crate::tonemap::Tonemap;
+use crate::telecine::Detelecine;
+use crate::duplicate_mode::DuplicateMode;
+use crate::logger::Logger;
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+// What the installed ffmpeg can do, read once from -version and -filters so missing features fail up front
+pub struct Capabilities {
+    version: Option<(u32, u32)>,
+    filters: HashSet<String>,
+}
+
+impl Capabilities {
+    // -fps_mode and -svtav1-params both arrived in 5.1
+    const FPS_MODE_VERSION: (u32, u32) = (5, 1);
+    const SVTAV1_PARAMS_VERSION: (u32, u32) = (5, 1);
+
+    // Release builds report "ffmpeg version 6.1.1" or "n6.1.1"; git builds ("N-112345-g...") are taken to be current
+    fn parse_version(output: &str) -> Option<(u32, u32)> {
+        let version = output.split("version ").nth(1)?.split_whitespace().next()?.trim_start_matches('n');
+        let mut parts = version.split(|c: char| !c.is_ascii_digit()).map(str::parse::<u32>);
+        Some((parts.next()?.ok()?, parts.next().and_then(Result::ok).unwrap_or(0)))
+    }
+
+    // Filter lines look like " T.C zscale            V->V       Apply resizing...", after a legend ending in " = "
+    fn parse_filters(output: &str) -> HashSet<String> {
+        output.lines()
+            .filter(|line| !line.contains(" = "))
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .map(String::from)
+            .collect()
+    }
+
+    fn detect() -> Self {
+        let run = |arg: &str| Binary::Ffmpeg.command()
+            .args(["-hide_banner", arg])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        let capabilities = Self { version: Self::parse_version(&run("-version")), filters: Self::parse_filters(&run("-filters")) };
+        Logger::debug(format!("ffmpeg {} with {} filters", capabilities.describe(), capabilities.filters.len()));
+        capabilities
+    }
+
+    pub fn get() -> &'static Self {
+        CAPABILITIES.get_or_init(Self::detect)
+    }
+
+    pub fn describe(&self) -> String {
+        self.version.map_or(String::from("(unknown version)"), |(major, minor)| format!("{}.{}", major, minor))
+    }
+
+    fn at_least(&self, version: (u32, u32)) -> bool {
+        self.version.is_none_or(|detected| detected >= version)
+    }
+
+    pub fn has_filter(&self, filter: &str) -> bool {
+        self.filters.contains(filter)
+    }
+
+    // Before 5.1 the same option was spelled -vsync
+    pub fn fps_mode_option(&self) -> &'static str {
+        if self.at_least(Self::FPS_MODE_VERSION) { "-fps_mode" } else { "-vsync" }
+    }
+
+    fn require_filters(&self, feature: &str, filters: &[&str]) -> Result<(), Error> {
+        match filters.iter().find(|filter| !self.has_filter(filter)) {
+            Some(filter) => Err(Error::new(format!(
+                "ffmpeg {} detected without the {} filter, which {} requires", self.describe(), filter, feature
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn require_version(&self, feature: &str, (major, minor): (u32, u32)) -> Result<(), Error> {
+        if self.at_least((major, minor)) {
+            return Ok(());
+        }
+        Err(Error::new(format!("ffmpeg {} detected, {} requires {}.{}+", self.describe(), feature, major, minor)))
+    }
+
+    pub fn validate(&self, settings: &Settings) -> Result<(), Error> {
+        if settings.tonemap != Tonemap::Off {
+            self.require_filters("--tonemap", &["zscale", "tonemap"])?;
+        }
+        if settings.detelecine != Detelecine::Off {
+            self.require_filters("--detelecine", &["idet", "fieldmatch", "decimate"])?;
+        }
+        if settings.duplicate_mode == DuplicateMode::Decimate {
+            self.require_filters("--duplicates decimate", &["mpdecimate"])?;
+        }
+        if settings.burn_subtitles.is_some() {
+            self.require_filters("--burn-subtitles", &["subtitles"])?;
+        }
+        if settings.watermark.is_some() {
+            self.require_filters("--watermark", &["movie", "overlay", "colorchannelmixer"])?;
+        }
+        if settings.gpu_pipeline {
+            self.require_filters("--gpu-pipeline", &["hwupload_cuda", "scale_cuda"])?;
+        }
+        if settings.reference.is_some() {
+            self.require_filters("--reference", &["psnr", "ssim"])?;
+        }
+        if settings.encoder == "libsvtav1" && settings.av1_tuning().film_grain.is_some() {
+            self.require_version("AV1 film grain with libsvtav1", Self::SVTAV1_PARAMS_VERSION)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::error::Error;
+use crate::model::Model;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainStep {
+    pub model: Option<String>,
+    pub scale: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chain {
+    pub steps: Vec<ChainStep>,
+}
+
+// A pass after the first model, upscaling frames resized to width x height
+#[derive(Clone, Copy, Debug)]
+pub struct ChainPass {
+    pub model: Model,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Chain {
+    pub fn total_scale(&self) -> usize {
+        self.steps.iter().map(|step| step.scale).product()
+    }
+
+    fn parse_scale(value: &str) -> Option<usize> {
+        let value = value.trim();
+        value.strip_suffix(['x', 'X']).unwrap_or(value).parse().ok().filter(|&scale| scale >= 2)
+    }
+
+    fn parse_step(value: &str) -> Result<ChainStep, Error> {
+        let invalid = || Error::new(format!("Invalid chain step: {}. Must be SCALE or MODEL:SCALE, e.g. 2x or realcugan:2x", value));
+        match value.rsplit_once(':') {
+            Some((model, scale)) if !model.trim().is_empty() => Ok(ChainStep {
+                model: Some(model.trim().to_string()),
+                scale: Self::parse_scale(scale).ok_or_else(invalid)?,
+            }),
+            Some(_) => Err(invalid()),
+            None => Ok(ChainStep { model: None, scale: Self::parse_scale(value).ok_or_else(invalid)? }),
+        }
+    }
+}
+
+impl std::str::FromStr for Chain {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let steps = value
+            .split(',')
+            .filter(|step| !step.trim().is_empty())
+            .map(Self::parse_step)
+            .collect::<Result<Vec<_>, Error>>()?;
+        if steps.len() < 2 {
+            return Err(Error::new(format!("Invalid chain: {}. Must have at least two steps, e.g. 2x,2x", value)));
+        }
+        Ok(Self { steps })
+    }
+}
+
+impl std::fmt::Display for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps = self.steps.iter().map(|step| match &step.model {
+            Some(model) => format!("{}:{}x", model, step.scale),
+            None => format!("{}x", step.scale),
+        }).collect::<Vec<_>>();
+        write!(f, "{}", steps.join(","))
+    }
+}
@@ -0,0 +1,49 @@
+use crate::error::Error;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChannelCapacity {
+    capacities: Vec<(String, usize)>,
+}
+
+impl ChannelCapacity {
+    pub const STAGES: [&'static str; 7] = ["extract", "static-segments", "adaptive-scale", "screenshots", "upscale", "progress", "keep-frames"];
+
+    pub fn get(&self, stage: &str, default: usize) -> usize {
+        self.capacities
+            .iter()
+            .rev()
+            .find(|(name, _)| name == stage)
+            .map_or(default, |&(_, capacity)| capacity)
+    }
+}
+
+impl std::str::FromStr for ChannelCapacity {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let capacities = value
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (stage, capacity) = entry.split_once('=')
+                    .map(|(stage, capacity)| (stage.trim().to_lowercase(), capacity.trim()))
+                    .ok_or_else(|| Error::new(format!("Invalid channel capacity: {}. Must be STAGE=N", entry)))?;
+                if !Self::STAGES.contains(&stage.as_str()) {
+                    return Err(Error::new(format!("Invalid channel stage: {}. Must be one of {}", stage, Self::STAGES.join(", "))));
+                }
+                match capacity.parse::<usize>() {
+                    Ok(capacity) if capacity > 0 => Ok((stage, capacity)),
+                    _ => Err(Error::new(format!("Invalid capacity for {}: {}. Must be at least 1", stage, capacity))),
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Self { capacities })
+    }
+}
+
+impl std::fmt::Display for ChannelCapacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self.capacities.iter().map(|(stage, capacity)| format!("{}={}", stage, capacity)).collect::<Vec<_>>();
+        write!(f, "{}", entries.join(","))
+    }
+}
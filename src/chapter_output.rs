@@ -0,0 +1,28 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChapterOutput {
+    Split,
+    Join,
+}
+
+impl std::str::FromStr for ChapterOutput {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "split" => Ok(ChapterOutput::Split),
+            "join" => Ok(ChapterOutput::Join),
+            _ => Err(Error::new(format!("Invalid chapter output: {}. Must be split or join", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for ChapterOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChapterOutput::Split => write!(f, "split"),
+            ChapterOutput::Join => write!(f, "join"),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::Error;
+use crate::binary::Binary;
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chapter {
+    pub number: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Chapter {
+    const CONCAT_LIST: &'static str = "chapters.txt";
+
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    // Input options, so extraction and the streams copied at merge cover the same range
+    pub fn get_seek_args(&self) -> Vec<String> {
+        vec![
+            String::from("-ss"), format!("{:.3}", self.start),
+            String::from("-to"), format!("{:.3}", self.end),
+        ]
+    }
+
+    // episode.mkv becomes episode_chapter03.mkv
+    pub fn output_path(&self, output: &Path) -> PathBuf {
+        let mut name = output.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!("_chapter{:02}", self.number));
+        if let Some(extension) = output.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        output.with_file_name(name)
+    }
+
+    // Parts share the same encoder settings, so they are joined without re-encoding
+    pub fn concat(parts: &[PathBuf], directory: &Path, output: &Path) -> Result<(), Error> {
+        let list = parts.iter()
+            .filter_map(|part| part.file_name())
+            .map(|name| format!("file '{}'\n", name.to_string_lossy().replace('\'', "'\\''")))
+            .collect::<String>();
+        let list_path = directory.join(Self::CONCAT_LIST);
+        fs::write(&list_path, list)
+            .map_err(|e| Error::new(format!("Failed to write {}: {}", list_path.display(), e)))?;
+
+        let args: Vec<OsString> = vec![
+            "-hide_banner".into(), "-f".into(), "concat".into(), "-safe".into(), "0".into(),
+            "-i".into(), Binary::path_arg(&list_path),
+            "-map".into(), "0".into(), "-c".into(), "copy".into(),
+            "-y".into(), Binary::path_arg(output),
+        ];
+        Binary::Ffmpeg.run(args, "join the upscaled chapters")
+    }
+}
+
+// 1-based chapter numbers and inclusive ranges, e.g. 1,3-5
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChapterSelection {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl ChapterSelection {
+    pub fn select(&self, chapters: &[Chapter], input: &Path) -> Result<Vec<Chapter>, Error> {
+        if chapters.is_empty() {
+            return Err(Error::new(format!("{} has no chapters to select", input.display())));
+        }
+        if let Some(&(_, last)) = self.ranges.iter().find(|(_, last)| *last > chapters.len()) {
+            return Err(Error::new(format!("{} has {} chapters, chapter {} does not exist", input.display(), chapters.len(), last)));
+        }
+        Ok(chapters.iter()
+            .filter(|chapter| self.ranges.iter().any(|(first, last)| (*first..=*last).contains(&chapter.number)))
+            .copied()
+            .collect())
+    }
+}
+
+impl std::str::FromStr for ChapterSelection {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse = |number: &str| number.trim().parse::<usize>().ok().filter(|&number| number > 0);
+        value
+            .split(',')
+            .map(|part| match part.split_once('-') {
+                Some((first, last)) => parse(first).zip(parse(last)).filter(|(first, last)| first <= last),
+                None => parse(part).map(|number| (number, number)),
+            })
+            .collect::<Option<Vec<(usize, usize)>>>()
+            .filter(|ranges| !ranges.is_empty())
+            .map(|ranges| Self { ranges })
+            .ok_or_else(|| Error::new(format!("Invalid chapters: {}. Must be chapter numbers and ranges like 1,3-5", value)))
+    }
+}
+
+impl std::fmt::Display for ChapterSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges = self.ranges.iter()
+            .map(|(first, last)| if first == last { first.to_string() } else { format!("{}-{}", first, last) })
+            .collect::<Vec<String>>();
+        write!(f, "{}", ranges.join(","))
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Matrix {
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Range {
+    Limited,
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub matrix: Matrix,
+    pub range: Range,
+}
+
+impl Color {
+    const HD_HEIGHT: usize = 720;
+    pub const SDR: Color = Color { matrix: Matrix::Bt709, range: Range::Limited };
+
+    // MJPEG and other JPEG-based streams are full range but often leave color_range unset
+    pub fn detect(color_space: &str, color_range: &str, pixel_format: &str, height: usize) -> Self {
+        let matrix = match color_space {
+            "bt709" => Matrix::Bt709,
+            "smpte170m" | "bt470bg" => Matrix::Bt601,
+            "bt2020nc" | "bt2020c" => Matrix::Bt2020,
+            _ if height >= Self::HD_HEIGHT => Matrix::Bt709,
+            _ => Matrix::Bt601,
+        };
+        let range = match color_range {
+            "pc" => Range::Full,
+            "tv" => Range::Limited,
+            _ if pixel_format.starts_with("yuvj") => Range::Full,
+            _ => Range::Limited,
+        };
+        Self { matrix, range }
+    }
+
+    fn scale_matrix(&self) -> &'static str {
+        match self.matrix {
+            Matrix::Bt601 => "bt601",
+            Matrix::Bt709 => "bt709",
+            Matrix::Bt2020 => "bt2020",
+        }
+    }
+
+    fn colorspace(&self) -> &'static str {
+        match self.matrix {
+            Matrix::Bt601 => "smpte170m",
+            Matrix::Bt709 => "bt709",
+            Matrix::Bt2020 => "bt2020nc",
+        }
+    }
+
+    fn range(&self) -> &'static str {
+        match self.range {
+            Range::Limited => "tv",
+            Range::Full => "pc",
+        }
+    }
+
+    pub fn get_input_filter(&self) -> String {
+        format!("scale=in_color_matrix={}:in_range={}", self.scale_matrix(), self.range())
+    }
+
+    pub fn get_output_filter(&self, pixel_format: &str) -> String {
+        format!("scale=out_color_matrix={}:out_range={},format={}", self.scale_matrix(), self.range(), pixel_format)
+    }
+
+    pub fn get_tags(&self) -> [String; 4] {
+        [
+            String::from("-colorspace:v:0"), self.colorspace().to_string(),
+            String::from("-color_range:v:0"), self.range().to_string(),
+        ]
+    }
+}
+
+impl std::str::FromStr for Range {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "limited" | "tv" => Ok(Range::Limited),
+            "full" | "pc" => Ok(Range::Full),
+            _ => Err(Error::new(format!("Invalid color range: {}. Must be limited or full", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Range::Limited => write!(f, "limited"),
+            Range::Full => write!(f, "full"),
+        }
+    }
+}
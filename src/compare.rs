@@ -0,0 +1,157 @@
+use crate::error::Error;
+use crate::arguments::Arguments;
+use crate::binary::Binary;
+use crate::compare_layout::CompareLayout;
+use crate::job::Job;
+use crate::logger::Logger;
+use crate::media_info::MediaInfo;
+use crate::pipeline::Pipeline;
+use crate::remote::Remote;
+use crate::settings::Settings;
+use crate::skip_mode::SkipMode;
+use crate::timestamp::Timestamp;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[derive(Clone, Debug, Default)]
+pub struct CompareModels {
+    pub range: Option<(f64, f64)>,
+    pub models: Vec<String>,
+    pub layout: CompareLayout,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+impl CompareModels {
+    pub const COMMAND: &'static str = "compare-models";
+    const OUTPUT_SUFFIX: &'static str = "_compare.mp4";
+    const CLIP_CODEC: &'static str = "ffv1";
+
+    pub fn parse_range(value: &str) -> Result<(f64, f64), Error> {
+        Timestamp::parse_range(value, "-")
+            .ok_or_else(|| Error::new(format!("Invalid range: {}. Must be START-END, e.g. 1:00-1:10", value)))
+    }
+
+    pub fn parse_models(value: &str) -> Vec<String> {
+        value.split(',').map(str::trim).filter(|model| !model.is_empty()).map(String::from).collect()
+    }
+
+    pub fn prepare(&mut self, input: &Path, output: Option<PathBuf>, replace_output: bool, settings: &Settings, encoders: &str) -> Result<(), Error> {
+        if self.range.is_none() {
+            return Err(Error::new("compare-models requires --range START-END"));
+        }
+        if self.models.len() < 2 {
+            return Err(Error::new("compare-models requires at least two --models"));
+        }
+        if !input.is_file() && !Remote::is_url(input) {
+            return Err(Error::new(format!("Input file not found: {}", input.display())));
+        }
+        for model in &self.models {
+            let mut settings = settings.clone();
+            settings.set("model", model)?;
+            settings.validate(encoders)?;
+        }
+
+        let file_path = Remote::file_path(input);
+        let stem = file_path.file_stem().unwrap_or_default().to_string_lossy();
+        self.input = input.to_path_buf();
+        self.output = output.unwrap_or_else(|| file_path.with_file_name(format!("{}{}", stem, Self::OUTPUT_SUFFIX)));
+        if self.output.exists() && !replace_output {
+            return Err(Error::new(format!("Output file already exists: {}", self.output.display())));
+        }
+        Ok(())
+    }
+
+    // The clip is stored losslessly so every model starts from the same frames
+    fn cut_clip(&self, clip: &Path) -> Result<(), Error> {
+        let (start, end) = self.range.unwrap_or_default();
+        Binary::Ffmpeg.run(vec![
+            "-hide_banner".into(), "-ss".into(), format!("{:.3}", start).into(), "-to".into(), format!("{:.3}", end).into(),
+            "-i".into(), Binary::path_arg(&self.input),
+            "-map".into(), "0:v:0".into(), "-c:v".into(), Self::CLIP_CODEC.into(), "-an".into(), "-sn".into(),
+            "-y".into(), Binary::path_arg(clip),
+        ], "cut the comparison clip")
+    }
+
+    fn escape_label(label: &str) -> String {
+        label.chars().fold(String::new(), |mut escaped, c| {
+            if matches!(c, '\\' | '\'' | ':' | '%' | ',') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+    }
+
+    fn get_filter(&self, width: usize, height: usize) -> String {
+        let count = self.models.len();
+        let labelled = self.models.iter().enumerate().map(|(index, model)| format!(
+            "[{index}:v]scale={width}:{height},setsar=1,drawtext=text='{}':x=h/40:y=h/40:fontsize=h/20:fontcolor=white:box=1:boxcolor=black@0.5[v{index}]",
+            Self::escape_label(model)
+        ));
+        let inputs = (0..count).map(|index| format!("[v{}]", index)).collect::<String>();
+        let combined = match self.layout {
+            CompareLayout::Cycle => format!("{}concat=n={}:v=1:a=0[out]", inputs, count),
+            CompareLayout::Grid => {
+                let columns = (count as f64).sqrt().ceil() as usize;
+                let layout = (0..count)
+                    .map(|index| format!("{}_{}", index % columns * width, index / columns * height))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                format!("{}xstack=inputs={}:layout={}:fill=black[out]", inputs, count, layout)
+            },
+        };
+        labelled.chain([combined]).collect::<Vec<_>>().join(";")
+    }
+
+    fn combine(&self, outputs: &[PathBuf], encoder: &str) -> Result<(), Error> {
+        let first = MediaInfo::probe(&outputs[0])?;
+        let stream = first.video_stream(0).ok_or_else(|| Error::new("Upscaled comparison clip has no video stream"))?;
+        let (width, height) = (stream.width, stream.height);
+
+        let mut args = vec!["-hide_banner".into()];
+        for output in outputs {
+            args.extend(["-i".into(), Binary::path_arg(output)]);
+        }
+        args.extend([
+            "-filter_complex".into(), self.get_filter(width, height).into(),
+            "-map".into(), "[out]".into(),
+            "-c:v".into(), encoder.into(), "-pix_fmt".into(), "yuv420p".into(),
+            "-y".into(), Binary::path_arg(&self.output),
+        ]);
+        Binary::Ffmpeg.run(args, "combine the comparison clips")
+    }
+
+    fn run(&self, arguments: Arguments, directory: &Path) -> Result<(), Error> {
+        let clip = directory.join("clip.mkv");
+        self.cut_clip(&clip)?;
+
+        let encoder = arguments.settings.encoder.clone();
+        let jobs = self.models.iter().enumerate().map(|(index, model)| {
+            let mut settings = arguments.settings.clone();
+            settings.set("model", model)?;
+            settings.format = None;
+            settings.on_skip = SkipMode::Transcode;
+            let mut job = Job::new(clip.clone(), settings);
+            job.output = directory.join(format!("{}.mkv", index));
+            Ok(job)
+        }).collect::<Result<Vec<_>, Error>>()?;
+        let outputs = jobs.iter().map(|job| job.output.clone()).collect::<Vec<_>>();
+
+        Pipeline::execute(arguments.with_files(jobs))?;
+        self.combine(&outputs, &encoder)?;
+        Logger::info(format!("Model comparison written to {}", self.output.display()));
+        Ok(())
+    }
+
+    pub fn execute(self, arguments: Arguments) -> Result<(), Error> {
+        let directory = std::env::temp_dir().join(format!("simple-video-upscaler-compare-{}", process::id()));
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create {}: {}", directory.display(), e)))?;
+        let result = self.run(arguments, &directory);
+        let _ = fs::remove_dir_all(&directory);
+        result
+    }
+}
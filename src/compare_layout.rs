@@ -0,0 +1,29 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CompareLayout {
+    #[default]
+    Cycle,
+    Grid,
+}
+
+impl std::str::FromStr for CompareLayout {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "cycle" => Ok(CompareLayout::Cycle),
+            "grid" => Ok(CompareLayout::Grid),
+            _ => Err(Error::new(format!("Invalid compare layout: {}. Must be cycle or grid", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for CompareLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareLayout::Cycle => write!(f, "cycle"),
+            CompareLayout::Grid => write!(f, "grid"),
+        }
+    }
+}
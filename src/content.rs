@@ -0,0 +1,98 @@
+use crate::error::Error;
+use crate::binary::Binary;
+
+use std::path::Path;
+use std::process::Stdio;
+use image::{DynamicImage, ImageFormat};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Content {
+    Animation,
+    LiveAction,
+}
+
+impl Content {
+    const SAMPLE_COUNT: usize = 5;
+    const SAMPLE_WIDTH: usize = 320;
+    const FLAT_GRADIENT: u8 = 2;
+    const ANIMATION_FLAT_RATIO: f64 = 0.5;
+
+    fn sample_frame(input: &Path, stream: usize, timestamp: f64) -> Result<DynamicImage, Error> {
+        let output = Binary::Ffmpeg.command()
+            .args(["-ss", &format!("{:.3}", timestamp), "-i"])
+            .arg(Binary::path_arg(input))
+            .args(["-map", &format!("0:v:{}", stream)])
+            .args([
+                "-frames:v", "1",
+                "-vf", &format!("scale={}:-2", Self::SAMPLE_WIDTH),
+                "-vcodec", "png",
+                "-f", "image2pipe",
+                "pipe:1"
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
+
+        image::load_from_memory_with_format(&output.stdout, ImageFormat::Png)
+            .map_err(|e| Error::new(format!("Failed to load sample frame: {}", e)))
+    }
+
+    fn flat_ratio(frame: &DynamicImage) -> f64 {
+        let image = frame.to_luma8();
+        let (width, height) = image.dimensions();
+        if width < 2 || height < 2 {
+            return 0.0;
+        }
+
+        let mut flat = 0usize;
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let pixel = image.get_pixel(x, y)[0];
+                let horizontal = pixel.abs_diff(image.get_pixel(x + 1, y)[0]);
+                let vertical = pixel.abs_diff(image.get_pixel(x, y + 1)[0]);
+                if horizontal.max(vertical) <= Self::FLAT_GRADIENT {
+                    flat += 1;
+                }
+            }
+        }
+
+        flat as f64 / ((width - 1) * (height - 1)) as f64
+    }
+
+    pub fn detect(input: &Path, stream: usize, frame_count: usize, frame_rate: f64) -> Result<Self, Error> {
+        let duration = if frame_rate > 0.0 { frame_count as f64 / frame_rate } else { 0.0 };
+        let ratios = (1..=Self::SAMPLE_COUNT)
+            .map(|sample| duration * sample as f64 / (Self::SAMPLE_COUNT + 1) as f64)
+            .filter_map(|timestamp| Self::sample_frame(input, stream, timestamp).ok())
+            .map(|frame| Self::flat_ratio(&frame))
+            .collect::<Vec<f64>>();
+
+        if ratios.is_empty() {
+            return Err(Error::new(format!("Failed to sample frames for content detection: {}", input.display())));
+        }
+
+        let average = ratios.iter().sum::<f64>() / ratios.len() as f64;
+        if average >= Self::ANIMATION_FLAT_RATIO {
+            Ok(Content::Animation)
+        } else {
+            Ok(Content::LiveAction)
+        }
+    }
+
+    pub fn preferred_model(&self) -> &'static str {
+        match self {
+            Content::Animation => "realcugan",
+            Content::LiveAction => "realesrgan",
+        }
+    }
+}
+
+impl std::fmt::Display for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Content::Animation => write!(f, "animation"),
+            Content::LiveAction => write!(f, "live-action"),
+        }
+    }
+}
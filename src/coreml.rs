@@ -0,0 +1,137 @@
+use crate::error::Error;
+use crate::model::Model;
+
+use std::env;
+use std::path::PathBuf;
+
+use objc2::rc::Retained;
+use objc2::runtime::ProtocolObject;
+use objc2::ClassType;
+use objc2_foundation::{NSArray, NSDictionary, NSError, NSNumber, NSString, NSURL};
+use objc2_core_ml::{
+    MLComputeUnits, MLDictionaryFeatureProvider, MLFeatureProvider, MLFeatureValue, MLModel, MLModelConfiguration, MLMultiArray,
+    MLMultiArrayDataType,
+};
+
+// Compiled models are looked up by model name, e.g. realesrgan-x4.mlmodelc, and take a float32
+// 1x3xHxW "input" in 0..1 with flexible height and width, returning the scaled "output" the same way
+pub struct CoreMlUpscaler {
+    model: Retained<MLModel>,
+    name: String,
+    scale: usize,
+}
+
+// MLModel predictions are thread safe, like the ncnn backends
+unsafe impl Send for CoreMlUpscaler {}
+unsafe impl Sync for CoreMlUpscaler {}
+
+impl CoreMlUpscaler {
+    const PATH_ENV_VAR: &'static str = "UPSCALER_COREML_PATH";
+    const BUNDLED_DIRECTORY: &'static str = "coreml";
+    const INPUT: &'static str = "input";
+    const OUTPUT: &'static str = "output";
+    const CHANNELS: usize = 3;
+
+    fn find(name: &str) -> Result<PathBuf, Error> {
+        let file_name = format!("{}.mlmodelc", name);
+        let search_path = env::var_os(Self::PATH_ENV_VAR).map(|path| env::split_paths(&path).collect::<Vec<_>>()).unwrap_or_default();
+        let bundled = env::current_exe().ok().and_then(|exe| exe.parent().map(|parent| parent.join(Self::BUNDLED_DIRECTORY)));
+
+        search_path
+            .into_iter()
+            .chain(bundled)
+            .map(|directory| directory.join(&file_name))
+            .find(|path| path.is_dir())
+            .ok_or_else(|| Error::new(format!(
+                "Cannot find CoreML model {}: {} not found in ${} or the coreml directory", name, file_name, Self::PATH_ENV_VAR
+            )))
+    }
+
+    fn describe(error: Retained<NSError>) -> String {
+        error.localizedDescription().to_string()
+    }
+
+    pub fn new(model: &Model) -> Result<Self, Error> {
+        let name = model.to_string();
+        let path = Self::find(&name)?;
+        // SAFETY: the URL and configuration are valid objects for the duration of the call
+        let loaded = unsafe {
+            let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+            let configuration = MLModelConfiguration::new();
+            configuration.setComputeUnits(MLComputeUnits::All);
+            MLModel::modelWithContentsOfURL_configuration_error(&url, &configuration)
+        }.map_err(|e| Error::new(format!("Failed to load CoreML model {}: {}", path.display(), Self::describe(e))))?;
+
+        Ok(Self { model: loaded, name, scale: model.get_scale() as usize })
+    }
+
+    fn shape(values: &[usize]) -> Retained<NSArray<NSNumber>> {
+        NSArray::from_vec(values.iter().map(|&value| NSNumber::new_usize(value)).collect())
+    }
+
+    fn to_input(&self, input: &[u8], width: usize, height: usize) -> Result<Retained<MLMultiArray>, Error> {
+        let plane = width * height;
+        // SAFETY: a new float32 array of this shape is contiguous and holds 3 * plane values
+        unsafe {
+            let array = MLMultiArray::initWithShape_dataType_error(
+                MLMultiArray::alloc(), &Self::shape(&[1, Self::CHANNELS, height, width]), MLMultiArrayDataType::Float32
+            ).map_err(|e| Error::new(format!("CoreML model {} input allocation failed: {}", self.name, Self::describe(e))))?;
+            let data = std::slice::from_raw_parts_mut(array.dataPointer().as_ptr() as *mut f32, Self::CHANNELS * plane);
+            for (index, pixel) in input.chunks_exact(Self::CHANNELS).enumerate() {
+                for (channel, &value) in pixel.iter().enumerate() {
+                    data[channel * plane + index] = value as f32 / 255.0;
+                }
+            }
+            Ok(array)
+        }
+    }
+
+    // The output is read through its strides, which CoreML may pad for alignment
+    fn from_output(&self, output: &MLMultiArray, width: usize, pixels: &mut [u8]) -> Result<(), Error> {
+        // SAFETY: the strides and element count describe the memory behind dataPointer
+        unsafe {
+            if output.dataType() != MLMultiArrayDataType::Float32 {
+                return Err(Error::new(format!("CoreML model {} must return a float32 output", self.name)));
+            }
+            let strides = output.strides().iter().map(|stride| stride.as_usize()).collect::<Vec<usize>>();
+            let [_, channel_stride, row_stride, column_stride] = strides[..] else {
+                return Err(Error::new(format!("CoreML model {} must return a 1x3xHxW output", self.name)));
+            };
+            let data = std::slice::from_raw_parts(output.dataPointer().as_ptr() as *const f32, output.count() as usize);
+
+            for (index, pixel) in pixels.chunks_exact_mut(Self::CHANNELS).enumerate() {
+                let (y, x) = (index / width, index % width);
+                for (channel, value) in pixel.iter_mut().enumerate() {
+                    let sample = data.get(channel * channel_stride + y * row_stride + x * column_stride).copied().unwrap_or(0.0);
+                    *value = (sample.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub fn process(&self, input: &[u8], width: usize, height: usize, pixels: &mut [u8]) -> Result<(), Error> {
+        if input.len() != width * height * Self::CHANNELS {
+            return Err(Error::new(format!("CoreML model {} got a frame of unexpected size: {}", self.name, input.len())));
+        }
+        if pixels.len() != width * self.scale * height * self.scale * Self::CHANNELS {
+            return Err(Error::new(format!("CoreML model {} got an output buffer of unexpected size: {}", self.name, pixels.len())));
+        }
+
+        let array = self.to_input(input, width, height)?;
+        // SAFETY: every object passed to CoreML is retained for the duration of the prediction
+        let output = unsafe {
+            let value = MLFeatureValue::featureValueWithMultiArray(&array);
+            let features = NSDictionary::from_vec(&[&*NSString::from_str(Self::INPUT)], vec![Retained::into_super(Retained::into_super(value))]);
+            let provider = MLDictionaryFeatureProvider::initWithDictionary_error(MLDictionaryFeatureProvider::alloc(), &features)
+                .map_err(|e| Error::new(format!("CoreML model {} input failed: {}", self.name, Self::describe(e))))?;
+            let prediction = self.model.predictionFromFeatures_error(ProtocolObject::<dyn MLFeatureProvider>::from_ref(&*provider))
+                .map_err(|e| Error::new(format!("CoreML upscale failed: {}", Self::describe(e))))?;
+            prediction
+                .featureValueForName(&NSString::from_str(Self::OUTPUT))
+                .and_then(|value| value.multiArrayValue())
+                .ok_or_else(|| Error::new(format!("CoreML model {} has no multi-array output named {}", self.name, Self::OUTPUT)))?
+        };
+        self.from_output(&output, width * self.scale, pixels)
+    }
+}
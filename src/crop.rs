@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::binary::Binary;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crop {
+    pub width: usize,
+    pub height: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CropMode {
+    Auto,
+    Manual(Crop),
+}
+
+impl Crop {
+    const SAMPLE_FRAMES: usize = 60;
+
+    fn parse(value: &str) -> Option<Self> {
+        let parts = value
+            .split(':')
+            .map(|part| part.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<usize>>>()?;
+        match parts.as_slice() {
+            &[width, height, x, y] => Some(Self { width, height, x, y }),
+            _ => None,
+        }
+    }
+
+    pub fn detect(input: &Path, stream: usize, frame_count: usize, frame_rate: f64) -> Result<Option<Self>, Error> {
+        let middle = if frame_rate > 0.0 { frame_count as f64 / frame_rate / 2.0 } else { 0.0 };
+        let output = Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-ss", &format!("{:.3}", middle), "-i"])
+            .arg(Binary::path_arg(input))
+            .args(["-map", &format!("0:v:{}", stream)])
+            .args([
+                "-vf", "cropdetect=round=2",
+                "-frames:v", &Self::SAMPLE_FRAMES.to_string(),
+                "-f", "null",
+                "-"
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
+
+        let mut detected: HashMap<&str, usize> = HashMap::new();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for line in stderr.lines() {
+            if let Some((_, crop)) = line.rsplit_once("crop=") {
+                *detected.entry(crop.trim()).or_default() += 1;
+            }
+        }
+
+        Ok(detected
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .and_then(|(crop, _)| Self::parse(crop)))
+    }
+
+    pub fn fits(&self, width: usize, height: usize) -> bool {
+        self.width > 0 && self.height > 0 && self.x + self.width <= width && self.y + self.height <= height
+    }
+
+    pub fn get_filter(&self) -> String {
+        format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl std::str::FromStr for CropMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(CropMode::Auto);
+        }
+
+        Crop::parse(value)
+            .map(CropMode::Manual)
+            .ok_or_else(|| Error::new(format!("Invalid crop: {}. Must be auto or W:H:X:Y", value)))
+    }
+}
+
+impl std::fmt::Display for Crop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
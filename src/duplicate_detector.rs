@@ -0,0 +1,28 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateDetector {
+    Fast,
+    Accurate,
+}
+
+impl std::str::FromStr for DuplicateDetector {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "fast" => Ok(DuplicateDetector::Fast),
+            "accurate" => Ok(DuplicateDetector::Accurate),
+            _ => Err(Error::new(format!("Invalid duplicate detector: {}. Must be fast or accurate", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for DuplicateDetector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicateDetector::Fast => write!(f, "fast"),
+            DuplicateDetector::Accurate => write!(f, "accurate"),
+        }
+    }
+}
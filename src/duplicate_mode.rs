@@ -0,0 +1,35 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateMode {
+    Collapse,
+    Keep,
+    Blend,
+    // Dropped by ffmpeg's mpdecimate during extraction, for slideshows and other very low frame rate content
+    Decimate,
+}
+
+impl std::str::FromStr for DuplicateMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "collapse" => Ok(DuplicateMode::Collapse),
+            "keep" => Ok(DuplicateMode::Keep),
+            "blend" => Ok(DuplicateMode::Blend),
+            "decimate" => Ok(DuplicateMode::Decimate),
+            _ => Err(Error::new(format!("Invalid duplicates mode: {}. Must be collapse, keep, blend or decimate", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for DuplicateMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicateMode::Collapse => write!(f, "collapse"),
+            DuplicateMode::Keep => write!(f, "keep"),
+            DuplicateMode::Blend => write!(f, "blend"),
+            DuplicateMode::Decimate => write!(f, "decimate"),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateThreshold {
+    Fixed(f64),
+    Auto,
+}
+
+impl std::str::FromStr for DuplicateThreshold {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Ok(DuplicateThreshold::Auto),
+            value => value
+                .parse::<f64>()
+                .ok()
+                .filter(|threshold| (0.0..=1.0).contains(threshold))
+                .map(DuplicateThreshold::Fixed)
+                .ok_or_else(|| Error::new(format!("Invalid duplicate threshold: {}. Must be auto or a number from 0 to 1", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for DuplicateThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicateThreshold::Fixed(threshold) => write!(f, "{}", threshold),
+            DuplicateThreshold::Auto => write!(f, "auto"),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExternalTrackMode {
+    Add,
+    // Drops the original streams of every kind that has an external track
+    Replace,
+}
+
+impl std::str::FromStr for ExternalTrackMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "add" => Ok(ExternalTrackMode::Add),
+            "replace" => Ok(ExternalTrackMode::Replace),
+            _ => Err(Error::new(format!("Invalid external tracks mode: {}. Must be add or replace", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for ExternalTrackMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExternalTrackMode::Add => write!(f, "add"),
+            ExternalTrackMode::Replace => write!(f, "replace"),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::error::Error;
+
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackKind {
+    Audio,
+    Subtitle,
+}
+
+impl TrackKind {
+    pub fn specifier(&self) -> &'static str {
+        match self {
+            TrackKind::Audio => "a",
+            TrackKind::Subtitle => "s",
+        }
+    }
+}
+
+// An audio or subtitle file muxed into the output, written as [LANG=]FILE with an ISO 639 language code
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalTrack {
+    pub kind: TrackKind,
+    pub path: PathBuf,
+    pub language: Option<String>,
+}
+
+impl ExternalTrack {
+    pub fn parse(kind: TrackKind, value: &str) -> Result<Self, Error> {
+        let value = value.trim();
+        let (language, path) = match value.split_once('=') {
+            Some((language, path)) if (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic()) => {
+                (Some(language.to_lowercase()), path)
+            },
+            _ => (None, value),
+        };
+        if path.is_empty() {
+            return Err(Error::new(format!("Invalid external track: {}. Must be FILE or LANG=FILE", value)));
+        }
+        Ok(Self { kind, path: PathBuf::from(path), language })
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.path.is_file() {
+            return Err(Error::new(format!("External track not found: {}", self.path.display())));
+        }
+        Ok(())
+    }
+
+    // Maps the first stream of that kind in the file; `output_index` is its position among the output's streams of that kind
+    pub fn get_args(&self, input: usize, output_index: usize) -> Vec<String> {
+        let specifier = self.kind.specifier();
+        let mut args = vec![String::from("-map"), format!("{}:{}:0", input, specifier)];
+        if let Some(language) = &self.language {
+            args.push(format!("-metadata:s:{}:{}", specifier, output_index));
+            args.push(format!("language={}", language));
+        }
+        args
+    }
+}
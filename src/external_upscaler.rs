@@ -0,0 +1,141 @@
+use crate::error::Error;
+use crate::logger::{Capture, Logger};
+use crate::notify::Notify;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+// A user command that reads packed RGB24 frames on stdin and writes them upscaled on stdout, see "External upscalers" in the README
+pub struct ExternalCommand {
+    command: String,
+}
+
+impl ExternalCommand {
+    pub const MODEL: &'static str = "external";
+    pub const SCALES: &'static [usize] = &[2, 3, 4];
+
+    fn registry() -> &'static Mutex<HashMap<String, &'static ExternalCommand>> {
+        static COMMANDS: OnceLock<Mutex<HashMap<String, &'static ExternalCommand>>> = OnceLock::new();
+        COMMANDS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    pub fn load(command: &str) -> &'static Self {
+        let mut commands = Self::registry().lock().unwrap();
+        commands.entry(command.to_string())
+            .or_insert_with(|| Box::leak(Box::new(Self { command: command.to_string() })))
+    }
+
+    // The process is started per frame size, so it has to be told that size
+    pub fn validate(command: &str) -> Result<(), Error> {
+        if !command.contains("{width}") || !command.contains("{height}") {
+            return Err(Error::new(format!("--external-upscaler must pass {{width}} and {{height}} to the command: {}", command)));
+        }
+        Ok(())
+    }
+
+    fn render(&self, width: usize, height: usize, scale: usize) -> String {
+        self.command
+            .replace("{width}", &width.to_string())
+            .replace("{height}", &height.to_string())
+            .replace("{scale}", &scale.to_string())
+    }
+
+    pub fn create(&'static self, scale: u8) -> ExternalUpscaler {
+        ExternalUpscaler { command: self, scale: scale as usize, idle: Mutex::new(Vec::new()) }
+    }
+}
+
+impl std::fmt::Debug for ExternalCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExternalCommand({})", self.command)
+    }
+}
+
+struct ExternalProcess {
+    width: usize,
+    height: usize,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: ChildStdout,
+    capture: Capture,
+}
+
+impl ExternalProcess {
+    // stdin is written from another thread, so a command that streams its output cannot block on a full pipe
+    fn upscale(&mut self, input: &[u8], output: &mut [u8]) -> std::io::Result<()> {
+        let (stdin, stdout) = (self.stdin.as_mut().expect("stdin is open while the process is running"), &mut self.stdout);
+        thread::scope(|scope| {
+            let writer = scope.spawn(|| stdin.write_all(input).and_then(|_| stdin.flush()));
+            let read = stdout.read_exact(output);
+            let written = writer.join().unwrap_or_else(|_| Err(std::io::Error::other("writer thread panicked")));
+            read.and(written)
+        })
+    }
+
+    fn close(mut self) -> Capture {
+        drop(self.stdin.take());
+        if !matches!(self.child.try_wait(), Ok(Some(_))) {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+        self.capture
+    }
+}
+
+// One process per frame size is kept running between frames, and each worker thread gets its own
+pub struct ExternalUpscaler {
+    command: &'static ExternalCommand,
+    scale: usize,
+    idle: Mutex<Vec<ExternalProcess>>,
+}
+
+impl ExternalUpscaler {
+    fn spawn(&self, width: usize, height: usize) -> Result<ExternalProcess, Error> {
+        let command = self.command.render(width, height, self.scale);
+        Logger::debug(format!("Starting external upscaler: {}", command));
+        let mut child = Notify::shell(&command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::new(format!("Failed to run external upscaler {}: {}", command, e)))?;
+        let capture = Logger::capture("external", child.stderr.take());
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(ExternalProcess { width, height, child, stdin, stdout, capture })
+    }
+
+    fn take_idle(&self, width: usize, height: usize) -> Option<ExternalProcess> {
+        let mut idle = self.idle.lock().unwrap();
+        let index = idle.iter().position(|process| process.width == width && process.height == height)?;
+        Some(idle.swap_remove(index))
+    }
+
+    pub fn process(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        if output.len() != width * self.scale * height * self.scale * 3 {
+            return Err(Error::new(format!("External upscaler got an output buffer of unexpected size: {}", output.len())));
+        }
+        let mut process = match self.take_idle(width, height) {
+            Some(process) => process,
+            None => self.spawn(width, height)?,
+        };
+        match process.upscale(input, output) {
+            Ok(()) => {
+                self.idle.lock().unwrap().push(process);
+                Ok(())
+            },
+            Err(e) => Err(process.close().error(format!("External upscaler failed on a {}x{} frame: {}", width, height, e))),
+        }
+    }
+}
+
+impl Drop for ExternalUpscaler {
+    fn drop(&mut self) {
+        for process in self.idle.get_mut().unwrap().drain(..) {
+            process.close();
+        }
+    }
+}
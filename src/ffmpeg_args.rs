@@ -0,0 +1,56 @@
+use crate::error::Error;
+
+// Raw ffmpeg arguments from the command line, split like a shell would without expanding anything
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FfmpegArgs {
+    args: Vec<String>,
+}
+
+impl FfmpegArgs {
+    // These would add inputs or change how the output file is replaced, which the pipeline relies on
+    const RESERVED: [&'static str; 3] = ["-i", "-y", "-n"];
+
+    fn split(value: &str) -> Result<Vec<String>, Error> {
+        let mut args = Vec::new();
+        let mut current: Option<String> = None;
+        let mut quote = None;
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(open), c) if c == open => quote = None,
+                (Some('"'), '\\') | (None, '\\') => {
+                    let escaped = chars.next().ok_or_else(|| Error::new(format!("Trailing backslash in ffmpeg arguments: {}", value)))?;
+                    current.get_or_insert_with(String::new).push(escaped);
+                },
+                (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    current.get_or_insert_with(String::new);
+                },
+                (None, c) if c.is_whitespace() => args.extend(current.take()),
+                (None, c) => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if quote.is_some() {
+            return Err(Error::new(format!("Unterminated quote in ffmpeg arguments: {}", value)));
+        }
+        args.extend(current);
+        Ok(args)
+    }
+
+    pub fn get(&self) -> &[String] {
+        &self.args
+    }
+}
+
+impl std::str::FromStr for FfmpegArgs {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let args = Self::split(value)?;
+        if let Some(reserved) = args.iter().find(|arg| Self::RESERVED.contains(&arg.as_str())) {
+            return Err(Error::new(format!("ffmpeg argument {} cannot be passed through, it is set by the upscaler", reserved)));
+        }
+        Ok(Self { args })
+    }
+}
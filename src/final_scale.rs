@@ -0,0 +1,34 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FinalScale {
+    Lanczos,
+    Bicubic,
+    Spline,
+    None,
+}
+
+impl FinalScale {
+    pub fn flags(&self) -> Option<&'static str> {
+        match self {
+            FinalScale::Lanczos => Some("lanczos"),
+            FinalScale::Bicubic => Some("bicubic"),
+            FinalScale::Spline => Some("spline"),
+            FinalScale::None => None,
+        }
+    }
+}
+
+impl std::str::FromStr for FinalScale {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "lanczos" => Ok(FinalScale::Lanczos),
+            "bicubic" => Ok(FinalScale::Bicubic),
+            "spline" => Ok(FinalScale::Spline),
+            "none" => Ok(FinalScale::None),
+            _ => Err(Error::new(format!("Invalid final scale: {}. Must be lanczos, bicubic, spline or none", value))),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Mp4,
+    Mkv,
+    Webm,
+    Mov,
+    Mxf,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Mp4 => "mp4",
+            Format::Mkv => "mkv",
+            Format::Webm => "webm",
+            Format::Mov => "mov",
+            Format::Mxf => "mxf",
+        }
+    }
+
+    pub fn muxer(&self) -> &'static str {
+        match self {
+            Format::Mp4 => "mp4",
+            Format::Mkv => "matroska",
+            Format::Webm => "webm",
+            Format::Mov => "mov",
+            Format::Mxf => "mxf",
+        }
+    }
+
+    pub fn muxer_flags(&self) -> &'static [&'static str] {
+        match self {
+            Format::Mp4 | Format::Mov => &["-movflags", "+faststart"],
+            Format::Mkv | Format::Webm | Format::Mxf => &[],
+        }
+    }
+
+    // Written so an interrupted file plays up to its last fragment; matroska already does without flags
+    pub fn fragmented_flags(&self) -> &'static [&'static str] {
+        match self {
+            Format::Mp4 | Format::Mov => &["-movflags", "+frag_keyframe+empty_moov+default_base_moof"],
+            Format::Mkv | Format::Webm | Format::Mxf => &[],
+        }
+    }
+
+    pub fn audio_codec(&self) -> &'static str {
+        match self {
+            Format::Webm => "libopus",
+            Format::Mxf => "pcm_s24le",
+            Format::Mp4 | Format::Mkv | Format::Mov => "copy",
+        }
+    }
+
+    pub fn subtitle_codec(&self) -> &'static str {
+        match self {
+            Format::Mp4 | Format::Mov => "mov_text",
+            Format::Webm => "webvtt",
+            Format::Mkv | Format::Mxf => "copy",
+        }
+    }
+
+    pub fn supports_subtitles(&self) -> bool {
+        *self != Format::Mxf
+    }
+
+    pub fn supports_encoder(&self, encoder: &str) -> bool {
+        let starts_with_any = |prefixes: &[&str]| prefixes.iter().any(|prefix| encoder.starts_with(prefix));
+        match self {
+            Format::Webm => starts_with_any(&["libvpx", "libaom-av1", "libsvtav1", "librav1e", "av1_"]),
+            Format::Mp4 => !starts_with_any(&["prores", "dnxhd", "ffv1", "huffyuv", "utvideo", "libvpx"]) || encoder == "libvpx-vp9",
+            Format::Mov => !starts_with_any(&["ffv1", "libvpx", "libaom-av1", "libsvtav1", "librav1e", "av1_"]),
+            Format::Mxf => starts_with_any(&["dnxhd", "mpeg2video"]),
+            Format::Mkv => true,
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "mp4" => Ok(Format::Mp4),
+            "mkv" | "matroska" => Ok(Format::Mkv),
+            "webm" => Ok(Format::Webm),
+            "mov" => Ok(Format::Mov),
+            "mxf" => Ok(Format::Mxf),
+            _ => Err(Error::new(format!("Invalid format: {}. Must be mp4, mkv, webm, mov or mxf", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
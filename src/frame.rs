@@ -1,38 +1,55 @@
-use crate::error::Error;
-
-use std::io::Cursor;
-use image::{DynamicImage, ImageFormat};
+use crate::buffer::Buffer;
 
+use image::{ImageBuffer, Rgb};
 
 pub struct Frame {
     pub index: usize,
+    pub pts: f64,
+    pub duration: f64,
     pub duplicates: usize,
-    pub image: DynamicImage,
+    // Part of a black or static run, scaled without the model
+    pub static_segment: bool,
+    // In a scene with little detail, upscaled by the cheaper model of --adaptive-scale
+    pub low_detail: bool,
+    pub width: u32,
+    pub height: u32,
+    pub buffer: Buffer,
 }
 
 impl Frame {
-    pub fn new(index: usize, image: DynamicImage) -> Self {
+    pub const CHANNELS: usize = 3;
+
+    pub fn new(index: usize, pts: f64, duration: f64, width: u32, height: u32, buffer: Buffer) -> Self {
         Self {
             index,
-            image,
-            duplicates: 0
+            pts,
+            duration,
+            width,
+            height,
+            buffer,
+            duplicates: 0,
+            static_segment: false,
+            low_detail: false,
         }
     }
 
-    pub fn from_bytes(index: usize, bytes: &[u8]) -> Result<Self, Error> {
-        image::load_from_memory_with_format(bytes, ImageFormat::Png)
-            .map_err(|e| Error::new(format!("Failed to load image from bytes: {}", e)))
-            .map(|image| Self::new(index, image))
+    pub fn size(width: u32, height: u32) -> usize {
+        width as usize * height as usize * Self::CHANNELS
     }
 
-    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
-        let mut cursor = Cursor::new(Vec::new());
-        self.image.write_to(&mut cursor, ImageFormat::Png)
-            .map_err(|e| Error::new(format!("Failed to write image to bytes: {}", e)))?;
-        Ok(cursor.into_inner())
+    // A view of the pixels for the image crate, without copying them
+    pub fn image(&self) -> ImageBuffer<Rgb<u8>, &[u8]> {
+        ImageBuffer::from_raw(self.width, self.height, &*self.buffer)
+            .expect("frame buffer size matches its dimensions")
     }
 
-    pub fn add_duplicate(&mut self) {
+    // A copy of this frame's pixels shown in place of a frame that failed
+    pub fn repeated(&self, index: usize, pts: f64, duration: f64) -> Self {
+        Self::new(index, pts, duration, self.width, self.height, Buffer::from(self.buffer.to_vec()))
+    }
+
+    pub fn add_duplicate(&mut self, duration: f64) {
         self.duplicates += 1;
+        self.duration += duration;
     }
-}
\ No newline at end of file
+}
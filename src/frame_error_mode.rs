@@ -0,0 +1,41 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameErrorMode {
+    Abort,
+    Skip,
+    RepeatPrevious,
+}
+
+impl FrameErrorMode {
+    pub fn action(&self) -> &'static str {
+        match self {
+            FrameErrorMode::Abort => "aborted",
+            FrameErrorMode::Skip => "skipped",
+            FrameErrorMode::RepeatPrevious => "replaced by the previous frame",
+        }
+    }
+}
+
+impl std::str::FromStr for FrameErrorMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "abort" => Ok(FrameErrorMode::Abort),
+            "skip" => Ok(FrameErrorMode::Skip),
+            "repeat-previous" | "repeat_previous" => Ok(FrameErrorMode::RepeatPrevious),
+            _ => Err(Error::new(format!("Invalid on-frame-error mode: {}. Must be skip, abort or repeat-previous", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for FrameErrorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameErrorMode::Abort => write!(f, "abort"),
+            FrameErrorMode::Skip => write!(f, "skip"),
+            FrameErrorMode::RepeatPrevious => write!(f, "repeat-previous"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::error::Error;
+
+use image::ImageFormat;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FramesFormat {
+    Png,
+    Tiff,
+    // uncompressed, the fastest to write
+    Bmp,
+}
+
+impl FramesFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FramesFormat::Png => "png",
+            FramesFormat::Tiff => "tiff",
+            FramesFormat::Bmp => "bmp",
+        }
+    }
+
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            FramesFormat::Png => ImageFormat::Png,
+            FramesFormat::Tiff => ImageFormat::Tiff,
+            FramesFormat::Bmp => ImageFormat::Bmp,
+        }
+    }
+}
+
+impl std::str::FromStr for FramesFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "png" => Ok(FramesFormat::Png),
+            "tiff" | "tif" => Ok(FramesFormat::Tiff),
+            "bmp" => Ok(FramesFormat::Bmp),
+            _ => Err(Error::new(format!("Invalid frames format: {}. Must be png, tiff or bmp", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for FramesFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
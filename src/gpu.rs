@@ -0,0 +1,44 @@
+use crate::backend::Backend;
+
+use std::process::{Command, Stdio};
+
+pub struct Gpu;
+
+impl Gpu {
+    const BYTES_PER_MIB: u64 = 1024 * 1024;
+
+    fn query_nvidia_smi(field: &str) -> Option<u64> {
+        // cpu-only builds never use the GPU, so its memory is not worth reporting or budgeting for
+        if !Backend::GPU_AVAILABLE {
+            return None;
+        }
+        let output = Command::new("nvidia-smi")
+            .args([&format!("--query-gpu={}", field), "--format=csv,noheader,nounits"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .and_then(|line| line.trim().parse::<u64>().ok())
+            .map(|mib| mib * Self::BYTES_PER_MIB)
+    }
+
+    pub fn free_memory() -> Option<u64> {
+        Self::query_nvidia_smi("memory.free")
+    }
+
+    pub fn used_memory() -> Option<u64> {
+        Self::query_nvidia_smi("memory.used")
+    }
+
+    pub fn total_memory() -> Option<u64> {
+        Self::query_nvidia_smi("memory.total")
+    }
+}
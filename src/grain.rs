@@ -0,0 +1,60 @@
+use crate::error::Error;
+use crate::av1::Av1Tuning;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grain {
+    // The grain removed by the model is measured on the input and added back at the output size
+    Preserve,
+    // New grain from the encoder's film grain synthesis, or a noise filter on merge for other encoders
+    Synthesize(u8),
+}
+
+impl Grain {
+    const MAX_STRENGTH: u8 = 50;
+    const DEFAULT_STRENGTH: u8 = 10;
+
+    pub fn get_film_grain(&self, encoder: &str) -> Option<u8> {
+        match self {
+            Grain::Synthesize(strength) if Av1Tuning::supports_film_grain(encoder) => Some(*strength),
+            _ => None,
+        }
+    }
+
+    // Temporal noise so it changes every frame like real grain
+    pub fn get_filter(&self, encoder: &str) -> Option<String> {
+        match self {
+            Grain::Synthesize(strength) if !Av1Tuning::supports_film_grain(encoder) => Some(format!("noise=alls={}:allf=t", strength)),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Grain {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(format!(
+            "Invalid grain: {}. Must be preserve, synthesize or synthesize=STRENGTH from 1 to {}", value, Self::MAX_STRENGTH
+        ));
+        match value.trim().to_lowercase().as_str() {
+            "preserve" => Ok(Grain::Preserve),
+            "synthesize" => Ok(Grain::Synthesize(Self::DEFAULT_STRENGTH)),
+            other => {
+                let strength = other.strip_prefix("synthesize=").and_then(|strength| strength.parse::<u8>().ok()).ok_or_else(invalid)?;
+                match (1..=Self::MAX_STRENGTH).contains(&strength) {
+                    true => Ok(Grain::Synthesize(strength)),
+                    false => Err(invalid()),
+                }
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Grain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Grain::Preserve => write!(f, "preserve"),
+            Grain::Synthesize(strength) => write!(f, "synthesize={}", strength),
+        }
+    }
+}
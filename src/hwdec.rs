@@ -0,0 +1,70 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HwDecode {
+    Auto,
+    Vaapi,
+    Nvdec,
+    Qsv,
+    Off,
+}
+
+impl HwDecode {
+    pub fn input_args(&self) -> &'static [&'static str] {
+        match self {
+            HwDecode::Auto => &["-hwaccel", "auto"],
+            HwDecode::Vaapi => &["-hwaccel", "vaapi", "-hwaccel_output_format", "vaapi"],
+            HwDecode::Nvdec => &["-hwaccel", "cuda", "-hwaccel_output_format", "cuda"],
+            HwDecode::Qsv => &["-hwaccel", "qsv", "-hwaccel_output_format", "qsv"],
+            HwDecode::Off => &[],
+        }
+    }
+
+    fn surface_format(pixel_format: &str) -> &'static str {
+        match pixel_format {
+            format if format.contains("p10") || format.contains("p12") => "p010le",
+            _ => "nv12",
+        }
+    }
+
+    // Frames decoded into GPU memory are copied back before the software filters,
+    // auto lets ffmpeg do that itself and fall back to software decoding
+    pub fn get_download_filter(&self, pixel_format: &str) -> Option<String> {
+        match self {
+            HwDecode::Vaapi | HwDecode::Nvdec | HwDecode::Qsv => Some(format!("hwdownload,format={}", Self::surface_format(pixel_format))),
+            HwDecode::Auto | HwDecode::Off => None,
+        }
+    }
+
+    // The reverse for --gpu-pipeline, so nvenc reads the frames from GPU memory
+    pub fn get_cuda_upload_filter(pixel_format: &str) -> String {
+        format!("format={},hwupload_cuda", Self::surface_format(pixel_format))
+    }
+}
+
+impl std::str::FromStr for HwDecode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(HwDecode::Auto),
+            "vaapi" => Ok(HwDecode::Vaapi),
+            "nvdec" | "cuda" => Ok(HwDecode::Nvdec),
+            "qsv" => Ok(HwDecode::Qsv),
+            "off" => Ok(HwDecode::Off),
+            _ => Err(Error::new(format!("Invalid hwdec mode: {}. Must be auto, vaapi, nvdec, qsv or off", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for HwDecode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HwDecode::Auto => write!(f, "auto"),
+            HwDecode::Vaapi => write!(f, "vaapi"),
+            HwDecode::Nvdec => write!(f, "nvdec"),
+            HwDecode::Qsv => write!(f, "qsv"),
+            HwDecode::Off => write!(f, "off"),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::backend::Backend;
+use crate::binary::Binary;
+use crate::capabilities::Capabilities;
+use crate::gpu::Gpu;
+use crate::memory::Memory;
+use crate::plugin::Plugin;
+use crate::settings::Settings;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// What this build and machine offer, printed by `info` or --version for bug reports
+pub struct Info;
+
+impl Info {
+    pub const COMMAND: &'static str = "info";
+    const MODELS: [&'static str; 5] = ["realcugan", "realcugan-pro", "realesrgan", "realesrgan-anime", "realesr-anime"];
+
+    fn backend_version(version: Option<&str>, commit: Option<&str>) -> String {
+        match (version, commit) {
+            (Some(version), Some(commit)) => format!("{} (git {})", version, &commit[..commit.len().min(12)]),
+            (Some(version), None) => version.to_string(),
+            _ => String::from("unknown"),
+        }
+    }
+
+    fn binary(binary: Binary) -> String {
+        let found = binary.command().arg("-version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok();
+        match found {
+            true => binary.path().display().to_string(),
+            false => format!("not found ({})", binary.path().display()),
+        }
+    }
+
+    // vulkaninfo ships with the Vulkan loader on most systems; ncnn sees the same devices
+    fn vulkan_devices() -> Option<Vec<String>> {
+        let output = Command::new("vulkaninfo").arg("--summary").stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("deviceName")?.trim().strip_prefix('=').map(|name| name.trim().to_string()))
+            .collect())
+    }
+
+    fn directory_size(directory: &Path) -> u64 {
+        fs::read_dir(directory).into_iter().flatten().flatten().map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => Self::directory_size(&entry.path()),
+            _ => entry.metadata().map_or(0, |metadata| metadata.len()),
+        }).sum()
+    }
+
+    fn print_plugins() {
+        let extension = std::env::consts::DLL_EXTENSION;
+        for directory in Plugin::search_directories() {
+            let plugins = fs::read_dir(&directory).into_iter().flatten().flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|value| value == extension))
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().trim_start_matches("lib").to_string()))
+                .collect::<Vec<_>>();
+            if !plugins.is_empty() {
+                println!("  {}: {}", directory.display(), plugins.iter().map(|name| format!("{}{}", Plugin::PREFIX, name)).collect::<Vec<_>>().join(", "));
+            }
+        }
+    }
+
+    fn print_cache(cache_dir: &str) {
+        let entries = fs::read_dir(cache_dir).into_iter().flatten().flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        let size = entries.iter().map(|entry| Self::directory_size(entry)).sum::<u64>();
+        println!("Frame cache: {} ({} cached videos, {})", cache_dir, entries.len(), Memory::format(size));
+    }
+
+    pub fn print(settings: &Settings) {
+        println!("simple-video-upscaler {}", env!("CARGO_PKG_VERSION"));
+        let backends = [
+            (Backend::GPU_AVAILABLE, "ncnn"),
+            (Backend::COREML_AVAILABLE, "coreml"),
+            (true, "cpu"),
+        ].iter().filter(|(available, _)| *available).map(|(_, name)| *name).collect::<Vec<_>>();
+        println!("Backends: {}", backends.join(", "));
+        // builds without ncnn leave both libraries out
+        if Backend::GPU_AVAILABLE {
+            println!("realcugan-rs: {}", Self::backend_version(option_env!("UPSCALER_REALCUGAN_RS_VERSION"), option_env!("UPSCALER_REALCUGAN_RS_COMMIT")));
+            println!("realesrgan-rs: {}", Self::backend_version(option_env!("UPSCALER_REALESRGAN_RS_VERSION"), option_env!("UPSCALER_REALESRGAN_RS_COMMIT")));
+        }
+        println!("ffmpeg: {} {}", Self::binary(Binary::Ffmpeg), Capabilities::get().describe());
+        println!("ffprobe: {}", Self::binary(Binary::Ffprobe));
+
+        match Self::vulkan_devices() {
+            Some(devices) if !devices.is_empty() => {
+                println!("Vulkan devices:");
+                for (index, device) in devices.iter().enumerate() {
+                    println!("  {}: {}", index, device);
+                }
+            },
+            Some(_) => println!("Vulkan devices: none"),
+            None => println!("Vulkan devices: unknown (vulkaninfo not found)"),
+        }
+        if let (Some(used), Some(total)) = (Gpu::used_memory(), Gpu::total_memory()) {
+            println!("GPU memory: {} of {} in use", Memory::format(used), Memory::format(total));
+        }
+
+        println!("Models: {}", Self::MODELS.join(", "));
+        println!("Plugins:");
+        Self::print_plugins();
+        if let Some(cache_dir) = &settings.cache_dir {
+            Self::print_cache(cache_dir);
+        }
+    }
+}
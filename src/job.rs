@@ -0,0 +1,34 @@
+use crate::settings::Settings;
+use crate::chapters::Chapter;
+use crate::content::Content;
+
+use std::path::PathBuf;
+
+#[derive(Clone)]
+pub struct Job {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub settings: Settings,
+    pub chapter: Option<Chapter>,
+}
+
+impl Job {
+    pub fn new(input: PathBuf, settings: Settings) -> Self {
+        Self {
+            input,
+            output: PathBuf::new(),
+            settings,
+            chapter: None,
+        }
+    }
+
+    // Whether the job may load the model picked with -m `name`, with auto standing for either model it detects
+    pub fn uses_model(&self, name: &str) -> bool {
+        let settings = &self.settings;
+        let steps = settings.chain.iter().flat_map(|chain| &chain.steps).map(|step| step.model.as_deref().unwrap_or(&settings.model));
+        std::iter::once(settings.model.as_str()).chain(steps).any(|model| match model {
+            "auto" => [Content::Animation, Content::LiveAction].iter().any(|content| content.preferred_model() == name),
+            model => model == name,
+        })
+    }
+}
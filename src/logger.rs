@@ -0,0 +1,168 @@
+use crate::error::Error;
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+pub struct Logger {
+    level: Level,
+    file: Option<Mutex<File>>,
+}
+
+impl Level {
+    fn label(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warning => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        }
+    }
+}
+
+impl Logger {
+    pub fn init(level: Level, path: Option<&Path>) -> Result<(), Error> {
+        let file = path
+            .map(|path| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| Error::new(format!("Failed to open log file {}: {}", path.display(), e)))
+            })
+            .transpose()?;
+
+        LOGGER
+            .set(Self { level, file: file.map(Mutex::new) })
+            .map_err(|_| Error::new("Logger is already initialized"))
+    }
+
+    fn get() -> &'static Self {
+        LOGGER.get_or_init(|| Self { level: Level::Info, file: None })
+    }
+
+    pub fn enabled(level: Level) -> bool {
+        level <= Self::get().level
+    }
+
+    fn write_file(&self, level: Level, message: &str) {
+        if let Some(file) = &self.file {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs_f64())
+                .unwrap_or_default();
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "[{:.3}] [{}] {}", timestamp, level.label(), message);
+            }
+        }
+    }
+
+    pub fn log(level: Level, message: impl AsRef<str>) {
+        let logger = Self::get();
+        let message = message.as_ref();
+        logger.write_file(level, message);
+
+        if level > logger.level {
+            return;
+        }
+
+        match level {
+            Level::Error => eprintln!("Error: {}", message),
+            Level::Warning => println!("Warning: {}", message),
+            Level::Info | Level::Debug => println!("{}", message),
+        }
+    }
+
+    pub fn error(message: impl AsRef<str>) {
+        Self::log(Level::Error, message)
+    }
+
+    pub fn warning(message: impl AsRef<str>) {
+        Self::log(Level::Warning, message)
+    }
+
+    pub fn info(message: impl AsRef<str>) {
+        Self::log(Level::Info, message)
+    }
+
+    pub fn debug(message: impl AsRef<str>) {
+        Self::log(Level::Debug, message)
+    }
+
+    pub fn capture<R: Read + Send + 'static>(name: &'static str, stream: Option<R>) -> Capture {
+        Capture::spawn(stream, move |line| {
+            Self::debug(format!("[{}] {}", name, line));
+            true
+        })
+    }
+}
+
+// Reads a child process's stderr and keeps its last lines, to explain why the process failed
+pub struct Capture {
+    tail: Arc<Mutex<VecDeque<String>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Capture {
+    const TAIL_LINES: usize = 50;
+
+    // handle_line sees every line and returns whether it belongs in the tail
+    pub fn spawn<R, F>(stream: Option<R>, mut handle_line: F) -> Self
+    where
+        R: Read + Send + 'static,
+        F: FnMut(&str) -> bool + Send + 'static,
+    {
+        let tail = Arc::new(Mutex::new(VecDeque::with_capacity(Self::TAIL_LINES)));
+        let reader = stream.map(|stream| {
+            let tail = tail.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if handle_line(&line) && !line.trim().is_empty() {
+                        let mut tail = tail.lock().unwrap();
+                        if tail.len() == Self::TAIL_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line);
+                    }
+                }
+            })
+        });
+        Self { tail, reader }
+    }
+
+    fn with_tail<'a>(message: &str, lines: impl DoubleEndedIterator<Item = &'a str>) -> Error {
+        let mut tail = lines.rev().filter(|line| !line.trim().is_empty()).take(Self::TAIL_LINES).collect::<Vec<&str>>();
+        if tail.is_empty() {
+            return Error::new(message);
+        }
+        tail.reverse();
+        Error::new(format!("{}. Last output:\n  {}", message, tail.join("\n  ")))
+    }
+
+    // Only call once the process has exited, since this waits for the end of its output
+    pub fn error(mut self, message: impl AsRef<str>) -> Error {
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        let tail = self.tail.lock().unwrap();
+        Self::with_tail(message.as_ref(), tail.iter().map(String::as_str))
+    }
+
+    pub fn output_error(message: impl AsRef<str>, stderr: &[u8]) -> Error {
+        Self::with_tail(message.as_ref(), String::from_utf8_lossy(stderr).lines())
+    }
+}
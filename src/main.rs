@@ -1,17 +1,104 @@
 mod frame;
+mod buffer;
 mod error;
 mod pipeline;
 mod arguments;
 mod video;
 mod model;
+mod format;
+mod profile;
+mod presets;
+mod content;
+mod duplicate_mode;
+mod duplicate_detector;
+mod duplicate_threshold;
+mod skip_mode;
+mod final_scale;
+mod crop;
+mod roi;
+mod binary;
+mod settings;
+mod manifest;
+mod job;
+mod gpu;
+mod logger;
+mod telecine;
+mod tonemap;
+mod color;
+mod template;
+mod sequence;
+mod priority;
+mod plugin;
+mod av1;
+mod subtitles;
+mod media_info;
+mod frames_format;
+mod bit_depth;
+mod batch_order;
+mod screenshots;
+mod channel_capacity;
+mod chain;
+mod remote;
+mod progress_mode;
+mod compare;
+mod compare_layout;
+mod frame_error_mode;
+mod batch_state;
+mod hwdec;
+mod notify;
+mod report;
+mod capabilities;
+mod external_upscaler;
+mod skip_policy;
+mod aspect_policy;
+mod organize;
+mod metrics;
+mod info;
+mod watermark;
+mod segments;
+mod package;
+mod pipe;
+mod timestamp;
+mod reprocess;
+mod sidecar;
+mod backend;
+mod chapters;
+mod chapter_output;
+mod memory;
+mod ffmpeg_args;
+mod cancel;
+mod rate_limit;
+mod grain;
+mod external_tracks;
+mod external_track_mode;
+#[cfg(all(feature = "coreml", target_os = "macos"))]
+mod coreml;
 
 use arguments::Arguments;
 use pipeline::Pipeline;
+use logger::Logger;
+use cancel::Cancel;
 
 fn main() {
-    if let Err(error) = Arguments::parse().and_then(Pipeline::execute) {
-        eprintln!("Error: {}", error);
-    } else {
-        println!("Completed!");
+    Cancel::install();
+    let result = Arguments::parse().and_then(|mut arguments| {
+        if let Some(compare) = arguments.compare.take() {
+            return compare.execute(arguments);
+        }
+        if let Some(range) = arguments.reprocess.take() {
+            return range.execute(arguments);
+        }
+        Pipeline::execute(arguments)
+    });
+    match result {
+        Ok(()) => Logger::info("Completed!"),
+        Err(_) if Cancel::requested() => {
+            Logger::warning(Cancel::error().to_string());
+            std::process::exit(Cancel::EXIT_CODE);
+        },
+        Err(error) => {
+            Logger::error(error.to_string());
+            std::process::exit(Cancel::ERROR_CODE);
+        },
     }
 }
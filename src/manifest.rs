@@ -0,0 +1,166 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+pub struct ManifestEntry {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub overrides: Vec<(String, String)>,
+}
+
+pub struct Manifest;
+
+impl Manifest {
+    fn resolve_path(base: &Path, value: &str) -> PathBuf {
+        let path = Path::new(value);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        }
+    }
+
+    fn create_entry(base: &Path, row: Vec<(String, String)>, line: usize) -> Result<ManifestEntry, Error> {
+        let mut input = None;
+        let mut output = None;
+        let mut overrides = Vec::new();
+
+        for (key, value) in row {
+            if value.is_empty() {
+                continue;
+            }
+            match key.as_str() {
+                "input" => input = Some(Self::resolve_path(base, &value)),
+                "output" => output = Some(Self::resolve_path(base, &value)),
+                _ => overrides.push((key, value)),
+            }
+        }
+
+        input
+            .map(|input| ManifestEntry { input, output, overrides })
+            .ok_or_else(|| Error::new(format!("Manifest entry {} is missing an input", line)))
+    }
+
+    // Quoted fields may hold commas and "" for a quote, as in RFC 4180; a field cannot span lines
+    fn split_csv_line(line: &str, number: usize) -> Result<Vec<String>, Error> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                ('"', true) => quoted = false,
+                ('"', false) if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                },
+                (',', false) => fields.push(std::mem::take(&mut field).trim().to_string()),
+                _ => field.push(c),
+            }
+        }
+        if quoted {
+            return Err(Error::new(format!("Manifest line {} has an unterminated quoted field", number)));
+        }
+        fields.push(field.trim().to_string());
+        Ok(fields)
+    }
+
+    fn parse_csv(base: &Path, content: &str) -> Result<Vec<ManifestEntry>, Error> {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+        let header = lines
+            .next()
+            .map(|(index, line)| Self::split_csv_line(line, index + 1))
+            .ok_or_else(|| Error::new("Manifest is empty"))??;
+
+        lines
+            .map(|(index, line)| {
+                let fields = Self::split_csv_line(line, index + 1)?;
+                if fields.len() > header.len() {
+                    return Err(Error::new(format!("Manifest line {} has more fields than the header", index + 1)));
+                }
+                let row = header.iter().cloned().zip(fields).collect();
+                Self::create_entry(base, row, index + 1)
+            })
+            .collect()
+    }
+
+    fn json_value_to_string(value: &Value) -> String {
+        match value {
+            Value::String(value) => value.clone(),
+            Value::Null => String::new(),
+            value => value.to_string(),
+        }
+    }
+
+    fn parse_json(base: &Path, content: &str) -> Result<Vec<ManifestEntry>, Error> {
+        let value: Value = serde_json::from_str(content)
+            .map_err(|e| Error::new(format!("Failed to parse manifest: {}", e)))?;
+
+        let entries = value
+            .as_array()
+            .ok_or_else(|| Error::new("Manifest must be a JSON array of objects"))?;
+
+        entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let object = entry
+                    .as_object()
+                    .ok_or_else(|| Error::new(format!("Manifest entry {} must be an object", index + 1)))?;
+                let row = object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::json_value_to_string(value)))
+                    .collect();
+                Self::create_entry(base, row, index + 1)
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<ManifestEntry>, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::new(format!("Failed to read manifest {}: {}", path.display(), e)))?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let extension = path.extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("json") => Self::parse_json(base, &content),
+            Some("csv") => Self::parse_csv(base, &content),
+            _ => Err(Error::new(format!("Invalid manifest: {}. Must be a .csv or .json file", path.display()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+
+    use std::path::Path;
+
+    #[test]
+    fn quoted_fields_keep_their_commas() {
+        let content = "input,output,model\n\"Show, The/ep1.mkv\",\"out, \"\"final\"\".mkv\",realcugan\n";
+        let entries = Manifest::parse_csv(Path::new("base"), content).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].input, Path::new("base/Show, The/ep1.mkv"));
+        assert_eq!(entries[0].output.as_deref(), Some(Path::new("base/out, \"final\".mkv")));
+        assert_eq!(entries[0].overrides, vec![(String::from("model"), String::from("realcugan"))]);
+    }
+
+    #[test]
+    fn unterminated_quotes_are_rejected() {
+        let content = "input,model\n\"Show, The/ep1.mkv,realcugan\n";
+        assert!(Manifest::parse_csv(Path::new("base"), content).is_err());
+    }
+}
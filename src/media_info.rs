@@ -0,0 +1,262 @@
+use crate::error::Error;
+use crate::binary::Binary;
+use crate::logger::{Capture, Logger};
+use crate::chapters::Chapter;
+
+use std::path::Path;
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec: String,
+    pub pixel_format: String,
+    pub width: usize,
+    pub height: usize,
+    pub frame_rate: Option<f64>,
+    pub frame_count: Option<usize>,
+    pub bit_rate: Option<u64>,
+    pub color_space: String,
+    pub color_range: String,
+    pub color_transfer: String,
+    pub color_primaries: String,
+    pub rotation: u16,
+    pub sample_aspect_ratio: Option<(usize, usize)>,
+    pub language: Option<String>,
+    pub disposition: Vec<String>,
+}
+
+impl StreamInfo {
+    // Dispositions that say which track a player should pick, as named by ffprobe and by ffmpeg's -disposition
+    const DISPOSITIONS: [&'static str; 12] = [
+        "default", "dub", "original", "comment", "lyrics", "karaoke", "forced",
+        "hearing_impaired", "visual_impaired", "clean_effects", "captions", "descriptions",
+    ];
+
+    fn specifier(&self) -> Option<&'static str> {
+        match self.kind {
+            StreamKind::Video => Some("v"),
+            StreamKind::Audio => Some("a"),
+            StreamKind::Subtitle => Some("s"),
+            StreamKind::Other => None,
+        }
+    }
+
+    // Sets the language and disposition of output stream `output_index` of the same kind; an empty disposition is
+    // written as 0 so ffmpeg does not mark the first stream as default on its own
+    pub fn get_tag_args(&self, output_index: usize) -> Vec<String> {
+        let Some(specifier) = self.specifier() else {
+            return Vec::new();
+        };
+        let disposition = match self.disposition.is_empty() {
+            true => String::from("0"),
+            false => self.disposition.join("+"),
+        };
+        let mut args = vec![format!("-disposition:{}:{}", specifier, output_index), disposition];
+        if let Some(language) = &self.language {
+            args.push(format!("-metadata:s:{}:{}", specifier, output_index));
+            args.push(format!("language={}", language));
+        }
+        args
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MediaInfo {
+    pub format: String,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+impl MediaInfo {
+    const TEXT_SUBTITLE_CODECS: [&'static str; 6] = ["subrip", "ass", "ssa", "mov_text", "webvtt", "text"];
+
+    fn run_ffprobe(input: &Path, args: &[&str]) -> Result<Value, Error> {
+        let output = Binary::Ffprobe.command()
+            .args(["-hide_banner", "-v", "error", "-of", "json"])
+            .args(args)
+            .arg(Binary::path_arg(input))
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffprobe: {}", e)))?;
+
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            Logger::debug(format!("[ffprobe] {}", line));
+        }
+
+        if !output.status.success() {
+            return Err(Capture::output_error(format!("ffprobe failed to read {}", input.display()), &output.stderr));
+        }
+
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| Error::new(format!("Failed to parse ffprobe output: {}", e)))
+    }
+
+    fn string(value: &Value, key: &str) -> String {
+        value.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+    }
+
+    // ffprobe reports most numbers as strings
+    fn number<T: std::str::FromStr>(value: &Value, key: &str) -> Option<T> {
+        match value.get(key)? {
+            Value::String(text) => text.parse().ok(),
+            number => number.to_string().parse().ok(),
+        }
+    }
+
+    fn parse_frame_rate(value: &str) -> Option<f64> {
+        let (num, den) = value.split_once('/')?;
+        let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+        (num > 0.0 && den > 0.0).then(|| num / den)
+    }
+
+    fn parse_rotation(stream: &Value) -> u16 {
+        let tag = stream.get("tags").and_then(|tags| Self::number::<f64>(tags, "rotate"));
+        let side_data = stream
+            .get("side_data_list")
+            .and_then(Value::as_array)
+            .and_then(|list| list.iter().find_map(|side_data| Self::number::<f64>(side_data, "rotation")))
+            .map(|degrees| -degrees);
+        let degrees = tag.or(side_data).unwrap_or(0.0);
+        ((degrees.round() as i32).rem_euclid(360) / 90 * 90) as u16
+    }
+
+    fn parse_ratio(value: &str) -> Option<(usize, usize)> {
+        let (num, den) = value.split_once(':')?;
+        let (num, den) = (num.parse::<usize>().ok()?, den.parse::<usize>().ok()?);
+        (num > 0 && den > 0).then_some((num, den))
+    }
+
+    // None for square pixels; falls back to the display aspect ratio when the sample one is not reported
+    fn parse_sample_aspect_ratio(stream: &Value) -> Option<(usize, usize)> {
+        let (width, height) = (Self::number::<usize>(stream, "width")?, Self::number::<usize>(stream, "height")?);
+        let ratio = Self::parse_ratio(&Self::string(stream, "sample_aspect_ratio")).or_else(|| {
+            let (num, den) = Self::parse_ratio(&Self::string(stream, "display_aspect_ratio"))?;
+            Some((num * height, den * width))
+        })?;
+        (ratio.0 != ratio.1).then_some(ratio)
+    }
+
+    fn parse_disposition(stream: &Value) -> Vec<String> {
+        let Some(disposition) = stream.get("disposition") else {
+            return Vec::new();
+        };
+        StreamInfo::DISPOSITIONS
+            .iter()
+            .filter(|name| Self::number::<u8>(disposition, name) == Some(1))
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn parse_stream(stream: &Value) -> StreamInfo {
+        let kind = match stream.get("codec_type").and_then(Value::as_str) {
+            Some("video") => StreamKind::Video,
+            Some("audio") => StreamKind::Audio,
+            Some("subtitle") => StreamKind::Subtitle,
+            _ => StreamKind::Other,
+        };
+
+        StreamInfo {
+            index: Self::number(stream, "index").unwrap_or(0),
+            kind,
+            codec: Self::string(stream, "codec_name"),
+            pixel_format: Self::string(stream, "pix_fmt"),
+            width: Self::number(stream, "width").unwrap_or(0),
+            height: Self::number(stream, "height").unwrap_or(0),
+            frame_rate: Self::parse_frame_rate(&Self::string(stream, "r_frame_rate"))
+                .or_else(|| Self::parse_frame_rate(&Self::string(stream, "avg_frame_rate"))),
+            frame_count: Self::number(stream, "nb_frames").filter(|&count: &usize| count > 0),
+            bit_rate: Self::number(stream, "bit_rate"),
+            color_space: Self::string(stream, "color_space"),
+            color_range: Self::string(stream, "color_range"),
+            color_transfer: Self::string(stream, "color_transfer"),
+            color_primaries: Self::string(stream, "color_primaries"),
+            rotation: Self::parse_rotation(stream),
+            sample_aspect_ratio: Self::parse_sample_aspect_ratio(stream),
+            language: stream.get("tags")
+                .map(|tags| Self::string(tags, "language"))
+                .filter(|language| !language.is_empty() && language != "und"),
+            disposition: Self::parse_disposition(stream),
+        }
+    }
+
+    pub fn probe(input: &Path) -> Result<Self, Error> {
+        let value = Self::run_ffprobe(input, &["-show_streams", "-show_format"])?;
+        let format = value.get("format").cloned().unwrap_or(Value::Null);
+        let streams = value
+            .get("streams")
+            .and_then(Value::as_array)
+            .map(|streams| streams.iter().map(Self::parse_stream).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            format: Self::string(&format, "format_name"),
+            duration: Self::number(&format, "duration"),
+            bit_rate: Self::number(&format, "bit_rate"),
+            streams,
+        })
+    }
+
+    pub fn streams(&self, kind: StreamKind) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(move |stream| stream.kind == kind)
+    }
+
+    pub fn video_stream(&self, index: usize) -> Option<&StreamInfo> {
+        self.streams(StreamKind::Video).nth(index)
+    }
+
+    pub fn is_text_subtitle(stream: &StreamInfo) -> bool {
+        Self::TEXT_SUBTITLE_CODECS.contains(&stream.codec.as_str())
+    }
+
+    // Only keyframes are decoded, so this stays fast on long files
+    pub fn keyframes(input: &Path) -> Result<Vec<f64>, Error> {
+        let value = Self::run_ffprobe(input, &[
+            "-select_streams", "v:0",
+            "-skip_frame", "nokey",
+            "-show_entries", "frame=pts_time",
+        ])?;
+        Ok(value
+            .get("frames")
+            .and_then(Value::as_array)
+            .map(|frames| frames.iter().filter_map(|frame| Self::number(frame, "pts_time")).collect())
+            .unwrap_or_default())
+    }
+
+    // Containers like mkv do not store frame counts, so count packets instead of decoding
+    pub fn count_frames(input: &Path, video_stream: usize) -> Result<usize, Error> {
+        let value = Self::run_ffprobe(input, &[
+            "-select_streams", &format!("v:{}", video_stream),
+            "-count_packets",
+            "-show_entries", "stream=nb_read_packets",
+        ])?;
+        value
+            .get("streams")
+            .and_then(Value::as_array)
+            .and_then(|streams| streams.first())
+            .and_then(|stream| Self::number(stream, "nb_read_packets"))
+            .ok_or_else(|| Error::new(format!("Failed to count frames in {}", input.display())))
+    }
+
+    pub fn chapters(input: &Path) -> Result<Vec<Chapter>, Error> {
+        let value = Self::run_ffprobe(input, &["-show_chapters"])?;
+        Ok(value
+            .get("chapters")
+            .and_then(Value::as_array)
+            .map(|chapters| chapters.iter().enumerate().filter_map(|(index, chapter)| Some(Chapter {
+                number: index + 1,
+                start: Self::number(chapter, "start_time")?,
+                end: Self::number(chapter, "end_time")?,
+            })).collect())
+            .unwrap_or_default())
+    }
+}
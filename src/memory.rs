@@ -0,0 +1,46 @@
+#[cfg(not(target_os = "linux"))]
+use std::process::{self, Command, Stdio};
+
+pub struct Memory;
+
+impl Memory {
+    const BYTES_PER_KIB: u64 = 1024;
+
+    // Resident set size of this process
+    #[cfg(target_os = "linux")]
+    pub fn resident() -> Option<u64> {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|kib| kib * Self::BYTES_PER_KIB)
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    pub fn resident() -> Option<u64> {
+        let output = Command::new("ps")
+            .args(["-o", "rss=", "-p", &process::id().to_string()])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok().map(|kib| kib * Self::BYTES_PER_KIB)
+    }
+
+    #[cfg(windows)]
+    pub fn resident() -> Option<u64> {
+        let script = format!("(Get-Process -Id {}).WorkingSet64", process::id());
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok()
+    }
+
+    pub fn format(bytes: u64) -> String {
+        format!("{:.1} GiB", bytes as f64 / (Self::BYTES_PER_KIB * Self::BYTES_PER_KIB * Self::BYTES_PER_KIB) as f64)
+    }
+}
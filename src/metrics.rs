@@ -0,0 +1,114 @@
+use crate::error::Error;
+use crate::logger::Logger;
+use crate::gpu::Gpu;
+use crate::memory::Memory;
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static FRAMES: AtomicUsize = AtomicUsize::new(0);
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static FAILED: AtomicUsize = AtomicUsize::new(0);
+// Frames and time of the previous scrape, so fps covers the scrape interval
+static LAST_SCRAPE: Mutex<Option<(Instant, usize)>> = Mutex::new(None);
+
+// Prometheus text exposition of the run on --metrics ADDRESS, served at /metrics until the run ends
+pub struct Metrics;
+
+impl Metrics {
+    const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4";
+    const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn count_frame() {
+        FRAMES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_queued(files: usize) {
+        QUEUED.store(files, Ordering::Relaxed);
+    }
+
+    pub fn start_job() {
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finish_job(succeeded: bool) {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+        match succeeded {
+            true => COMPLETED.fetch_add(1, Ordering::Relaxed),
+            false => FAILED.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn fps(frames: usize) -> f64 {
+        let mut last = LAST_SCRAPE.lock().unwrap();
+        let now = Instant::now();
+        let fps = last.map_or(0.0, |(at, previous)| {
+            (frames - previous) as f64 / now.duration_since(at).as_secs_f64().max(f64::EPSILON)
+        });
+        *last = Some((now, frames));
+        fps
+    }
+
+    fn metric(output: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+        let _ = write!(output, "# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value);
+    }
+
+    fn render() -> String {
+        let frames = FRAMES.load(Ordering::Relaxed);
+        let mut output = String::new();
+        Self::metric(&mut output, "upscaler_frames_processed_total", "counter", "Frames upscaled", frames);
+        Self::metric(&mut output, "upscaler_fps", "gauge", "Frames upscaled per second since the previous scrape", format!("{:.3}", Self::fps(frames)));
+        Self::metric(&mut output, "upscaler_queue_depth", "gauge", "Files waiting to be started", QUEUED.load(Ordering::Relaxed));
+        Self::metric(&mut output, "upscaler_jobs_active", "gauge", "Files being processed", ACTIVE.load(Ordering::Relaxed));
+        Self::metric(&mut output, "upscaler_jobs_completed_total", "counter", "Files finished or skipped", COMPLETED.load(Ordering::Relaxed));
+        Self::metric(&mut output, "upscaler_jobs_failed_total", "counter", "Files that failed or were cancelled", FAILED.load(Ordering::Relaxed));
+        if let Some(resident) = Memory::resident() {
+            Self::metric(&mut output, "upscaler_resident_memory_bytes", "gauge", "Resident memory of the upscaler", resident);
+        }
+        // nvidia-smi only, other GPUs leave these out
+        if let Some(used) = Gpu::used_memory() {
+            Self::metric(&mut output, "upscaler_gpu_memory_used_bytes", "gauge", "GPU memory in use", used);
+        }
+        if let Some(total) = Gpu::total_memory() {
+            Self::metric(&mut output, "upscaler_gpu_memory_total_bytes", "gauge", "GPU memory in total", total);
+        }
+        output
+    }
+
+    fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(Self::READ_TIMEOUT))?;
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let (status, body) = match request_line.split_whitespace().take(2).collect::<Vec<_>>().as_slice() {
+            ["GET", "/metrics"] => ("200 OK", Self::render()),
+            _ => ("404 Not Found", String::from("Not found, metrics are at /metrics\n")),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, Self::CONTENT_TYPE, body.len(), body
+        )?;
+        stream.flush()
+    }
+
+    pub fn serve(address: SocketAddr) -> Result<(), Error> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| Error::new(format!("Failed to serve metrics on {}: {}", address, e)))?;
+        Logger::info(format!("Serving metrics on http://{}/metrics", address));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = Self::respond(stream) {
+                    Logger::debug(format!("Metrics request failed: {}", e));
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -1,16 +1,64 @@
+use crate::plugin::Plugin;
+use crate::external_upscaler::ExternalCommand;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Model {
-    RealCugan(u8),
+    RealCugan { scale: u8, pro: bool, syncgap: u8 },
     RealEsrAnime(u8),
-    RealEsrgan,
+    RealEsrgan(u8),
     RealEsrganAnime,
+    Plugin(&'static Plugin, u8),
+    External(&'static ExternalCommand, u8),
 }
 
 impl Model {
+    pub const DEFAULT_SYNCGAP: u8 = 3;
+    pub const MAX_SYNCGAP: u8 = 3;
+
+    pub fn supported_scales(model_name: &str) -> &'static [usize] {
+        if let Some(name) = Plugin::name_of(model_name) {
+            return Plugin::load(name).map_or(&[], Plugin::scales);
+        }
+
+        match model_name {
+            ExternalCommand::MODEL => ExternalCommand::SCALES,
+            "realesrgan" | "realesrgan-anime" => &[4],
+            "realcugan-pro" => &[2, 3],
+            _ => &[2, 3, 4],
+        }
+    }
+
+    // The 2x variant used for low-detail scenes by --adaptive-scale
+    pub fn cheaper(&self) -> Option<Model> {
+        if self.get_scale() <= 2 {
+            return None;
+        }
+        match *self {
+            Model::RealCugan { pro, syncgap, .. } => Some(Model::RealCugan { scale: 2, pro, syncgap }),
+            Model::RealEsrAnime(_) | Model::RealEsrganAnime => Some(Model::RealEsrAnime(2)),
+            Model::Plugin(plugin, _) => plugin.scales().contains(&2).then_some(Model::Plugin(plugin, 2)),
+            // realesrgan-rs only bundles the x4plus weights
+            Model::RealEsrgan(_) | Model::External(..) => None,
+        }
+    }
+
+    // The -m name this model is picked with
+    pub fn name(&self) -> String {
+        match self {
+            Model::RealCugan { pro: false, .. } => String::from("realcugan"),
+            Model::RealCugan { pro: true, .. } => String::from("realcugan-pro"),
+            Model::RealEsrAnime(_) => String::from("realesr-anime"),
+            Model::RealEsrgan(_) => String::from("realesrgan"),
+            Model::RealEsrganAnime => String::from("realesrgan-anime"),
+            Model::Plugin(plugin, _) => format!("{}{}", Plugin::PREFIX, plugin.name()),
+            Model::External(..) => String::from(ExternalCommand::MODEL),
+        }
+    }
+
     pub fn get_scale(&self) -> u8 {
         match self {
-            Model::RealCugan(scale) | Model::RealEsrAnime(scale) => *scale,
-            Model::RealEsrgan | Model::RealEsrganAnime => 4,
+            Model::RealCugan { scale, .. } | Model::RealEsrAnime(scale) | Model::RealEsrgan(scale) | Model::Plugin(_, scale) | Model::External(_, scale) => *scale,
+            Model::RealEsrganAnime => 4,
         }
     }
 }
@@ -18,10 +66,13 @@ impl Model {
 impl std::fmt::Display for Model {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Model::RealCugan(scale) => write!(f, "realcugan-x{}", scale),
+            Model::RealCugan { scale, pro: false, .. } => write!(f, "realcugan-x{}", scale),
+            Model::RealCugan { scale, pro: true, .. } => write!(f, "realcugan-pro-x{}", scale),
             Model::RealEsrAnime(scale) => write!(f, "realesr-anime-x{}", scale),
-            Model::RealEsrgan => write!(f, "realesrgan-x4"),
+            Model::RealEsrgan(scale) => write!(f, "realesrgan-x{}", scale),
             Model::RealEsrganAnime => write!(f, "realesrgan-anime-x4"),
+            Model::Plugin(plugin, scale) => write!(f, "{}-x{}", plugin.name(), scale),
+            Model::External(_, scale) => write!(f, "external-x{}", scale),
         }
     }
 }
\ No newline at end of file
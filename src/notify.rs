@@ -0,0 +1,92 @@
+use crate::error::Error;
+use crate::job::Job;
+use crate::logger::Logger;
+
+use serde_json::json;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub struct Notify {
+    pub url: Option<String>,
+    pub command: Option<String>,
+}
+
+impl Notify {
+    const CURL: &'static str = "curl";
+    const TIMEOUT_SECONDS: u64 = 30;
+
+    fn status(result: &Result<(), Error>) -> &'static str {
+        match result {
+            Ok(()) => "completed",
+            Err(_) => "failed",
+        }
+    }
+
+    // POSTed with curl so https works without a TLS dependency
+    fn post(url: &str, job: &Job, result: &Result<(), Error>, elapsed: Duration) -> Result<(), Error> {
+        let body = json!({
+            "status": Self::status(result),
+            "input": job.input.display().to_string(),
+            "output": job.output.display().to_string(),
+            "error": result.as_ref().err().map(ToString::to_string),
+            "elapsed_seconds": elapsed.as_secs_f64(),
+        }).to_string();
+
+        let mut child = Command::new(Self::CURL)
+            .args(["-fsS", "-X", "POST", "-H", "Content-Type: application/json", "--data-binary", "@-"])
+            .args(["--max-time", &Self::TIMEOUT_SECONDS.to_string(), url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::new(format!("Failed to run {} for --notify-url: {}", Self::CURL, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(body.as_bytes());
+        }
+
+        let output = child.wait_with_output()
+            .map_err(|e| Error::new(format!("Failed to wait for {}: {}", Self::CURL, e)))?;
+        if !output.status.success() {
+            return Err(Error::new(format!("Notification to {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        Ok(())
+    }
+
+    pub fn shell(command: &str) -> Command {
+        let mut shell = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+        shell.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command);
+        shell
+    }
+
+    fn run(command: &str, job: &Job, result: &Result<(), Error>, elapsed: Duration) -> Result<(), Error> {
+        let status = Self::shell(command)
+            .env("UPSCALER_STATUS", Self::status(result))
+            .env("UPSCALER_INPUT", &job.input)
+            .env("UPSCALER_OUTPUT", &job.output)
+            .env("UPSCALER_ERROR", result.as_ref().err().map(ToString::to_string).unwrap_or_default())
+            .env("UPSCALER_ELAPSED", format!("{:.0}", elapsed.as_secs_f64()))
+            .stdin(Stdio::null())
+            .status()
+            .map_err(|e| Error::new(format!("Failed to run --notify-cmd: {}", e)))?;
+        if !status.success() {
+            return Err(Error::new(format!("--notify-cmd exited with {}", status)));
+        }
+        Ok(())
+    }
+
+    // Notification failures are only reported, they never fail the file
+    pub fn send(&self, job: &Job, result: &Result<(), Error>, elapsed: Duration) {
+        if let Some(url) = &self.url {
+            if let Err(e) = Self::post(url, job, result, elapsed) {
+                Logger::warning(e.to_string());
+            }
+        }
+        if let Some(command) = &self.command {
+            if let Err(e) = Self::run(command, job, result, elapsed) {
+                Logger::warning(e.to_string());
+            }
+        }
+    }
+}
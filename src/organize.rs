@@ -0,0 +1,118 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+
+// An episode found in a file name like "Show.Name.S01E02.1080p.mkv"
+#[derive(Clone, Debug, PartialEq)]
+pub struct Episode {
+    pub show: String,
+    pub season: usize,
+    pub episode: usize,
+}
+
+impl Episode {
+    // Returns where the SxxEyy marker starts, with its season and episode
+    fn find_marker(name: &str) -> Option<(usize, usize, usize)> {
+        let bytes = name.as_bytes();
+        let digits = |start: usize, max: usize| {
+            let count = bytes[start..].iter().take(max).take_while(|byte| byte.is_ascii_digit()).count();
+            (count > 0 && bytes.get(start + count).is_none_or(|byte| !byte.is_ascii_digit())).then_some(count)
+        };
+        (0..bytes.len()).find_map(|start| {
+            if !bytes[start].eq_ignore_ascii_case(&b's') || (start > 0 && bytes[start - 1].is_ascii_alphanumeric()) {
+                return None;
+            }
+            let season_digits = digits(start + 1, 2)?;
+            let e = start + 1 + season_digits;
+            if !bytes.get(e)?.eq_ignore_ascii_case(&b'e') {
+                return None;
+            }
+            let episode_digits = digits(e + 1, 3)?;
+            let end = e + 1 + episode_digits;
+            let season = name[start + 1..e].parse().ok()?;
+            let episode = name[e + 1..end].parse().ok()?;
+            Some((start, season, episode))
+        })
+    }
+
+    fn clean(name: &str) -> String {
+        name.replace(['.', '_'], " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_matches(|c: char| c == '-' || c.is_whitespace())
+            .to_string()
+    }
+
+    // Season folders are skipped, so "Show/Season 01/S01E02.mkv" still finds the show
+    fn show_from_folders(input: &Path) -> Option<String> {
+        input.ancestors()
+            .skip(1)
+            .filter_map(|folder| folder.file_name()?.to_str())
+            .find(|name| {
+                let lower = name.to_lowercase();
+                let number = lower.strip_prefix("season").or_else(|| lower.strip_prefix('s')).unwrap_or(&lower);
+                !number.trim().chars().all(|c| c.is_ascii_digit())
+            })
+            .map(Self::clean)
+            .filter(|show| !show.is_empty())
+    }
+
+    pub fn detect(input: &Path) -> Option<Self> {
+        let stem = input.file_stem()?.to_str()?;
+        let (start, season, episode) = Self::find_marker(stem)?;
+        let show = Some(Self::clean(&stem[..start]))
+            .filter(|show| !show.is_empty())
+            .or_else(|| Self::show_from_folders(input))?;
+        Some(Self { show, season, episode })
+    }
+
+    pub fn season(&self) -> String {
+        format!("{:02}", self.season)
+    }
+
+    pub fn episode(&self) -> String {
+        format!("{:02}", self.episode)
+    }
+}
+
+// Media server library layouts: Show/Season 01/<episode file>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Organize {
+    Plex,
+    Jellyfin,
+}
+
+impl Organize {
+    pub fn template(&self) -> &'static str {
+        match self {
+            Organize::Plex => "{show} - s{season}e{episode}.{ext}",
+            Organize::Jellyfin => "{show} S{season}E{episode}.{ext}",
+        }
+    }
+
+    pub fn folder(&self, episode: &Episode) -> PathBuf {
+        PathBuf::from(&episode.show).join(format!("Season {}", episode.season()))
+    }
+}
+
+impl std::str::FromStr for Organize {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "plex" => Ok(Organize::Plex),
+            "jellyfin" => Ok(Organize::Jellyfin),
+            _ => Err(Error::new(format!("Invalid organize layout: {}. Must be plex or jellyfin", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Organize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Organize::Plex => write!(f, "plex"),
+            Organize::Jellyfin => write!(f, "jellyfin"),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Error;
+use crate::segments::Segments;
+
+use std::path::{Path, PathBuf};
+
+// Streaming packages written by ffmpeg's hls and dash muxers: a playlist or manifest with its segments next to it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Package {
+    Hls,
+    Dash,
+}
+
+impl Package {
+    pub const DEFAULT_SEGMENT_TIME: f64 = 6.0;
+    // the one audio codec every hls and dash player decodes
+    pub const AUDIO_CODEC: &'static str = "aac";
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Package::Hls => "m3u8",
+            Package::Dash => "mpd",
+        }
+    }
+
+    // An output already named like a playlist or manifest is packaged without --package
+    pub fn detect(output: &Path) -> Option<Self> {
+        let extension = output.extension()?.to_str()?.to_lowercase();
+        [Package::Hls, Package::Dash].into_iter().find(|package| package.extension() == extension)
+    }
+
+    // Each package gets a folder of its own, so the segments of a batch do not mix: out/movie.mkv -> out/movie/movie.m3u8
+    pub fn output_path(&self, output: &Path) -> PathBuf {
+        if Self::detect(output) == Some(*self) {
+            return output.to_path_buf();
+        }
+        let stem = output.file_stem().unwrap_or_default();
+        let mut file_name = stem.to_os_string();
+        file_name.push(format!(".{}", self.extension()));
+        output.with_file_name(stem).join(file_name)
+    }
+
+    // A live input gets an event playlist that grows as segments are finished
+    pub fn get_args(&self, output: &Path, seconds: f64, name: &str, live: bool) -> Vec<String> {
+        let mut args = Segments::keyframe_args(seconds);
+        match self {
+            Package::Hls => args.extend([
+                String::from("-f"), String::from("hls"),
+                String::from("-hls_time"), seconds.to_string(),
+                String::from("-hls_playlist_type"), String::from(if live { "event" } else { "vod" }),
+                String::from("-hls_segment_filename"), Segments::pattern(output, name, "ts").to_string_lossy().to_string(),
+            ]),
+            // dash segment names are relative to the manifest
+            Package::Dash => args.extend([
+                String::from("-f"), String::from("dash"),
+                String::from("-seg_duration"), seconds.to_string(),
+                String::from("-use_template"), String::from("1"),
+                String::from("-use_timeline"), String::from("1"),
+                String::from("-init_seg_name"), format!("{}-init-$RepresentationID$.m4s", name),
+                String::from("-media_seg_name"), format!("{}-$RepresentationID$-$Number%05d$.m4s", name),
+            ]),
+        }
+        args
+    }
+}
+
+impl std::str::FromStr for Package {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "hls" => Ok(Package::Hls),
+            "dash" => Ok(Package::Dash),
+            _ => Err(Error::new(format!("Invalid package: {}. Must be hls or dash", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Package {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Package::Hls => write!(f, "hls"),
+            Package::Dash => write!(f, "dash"),
+        }
+    }
+}
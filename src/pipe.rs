@@ -0,0 +1,38 @@
+use crate::logger::Logger;
+
+#[cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+
+// Frames go to and from ffmpeg through pipes, which ffmpeg can write without any protocol of ours; shared memory
+// would need one, so the pipes are widened instead. A 4K frame takes 384 writes through a default 64 KiB pipe and
+// 24 through a 1 MiB one, which is what an unprivileged process may ask for by default.
+pub struct Pipe;
+
+impl Pipe {
+    #[cfg(target_os = "linux")]
+    const DEFAULT_MAX_SIZE: usize = 1024 * 1024;
+
+    // Capped at /proc/sys/fs/pipe-max-size, above which only root may go
+    #[cfg(target_os = "linux")]
+    fn max_size() -> usize {
+        std::fs::read_to_string("/proc/sys/fs/pipe-max-size")
+            .ok()
+            .and_then(|size| size.trim().parse().ok())
+            .unwrap_or(Self::DEFAULT_MAX_SIZE)
+    }
+
+    // Sized for a few frames, so ffmpeg can run ahead of the reader without blocking
+    #[cfg(target_os = "linux")]
+    pub fn widen(pipe: &impl AsRawFd, frame_size: usize) {
+        let size = frame_size.saturating_mul(4).min(Self::max_size()).min(libc::c_int::MAX as usize) as libc::c_int;
+        // SAFETY: fcntl only reads its integer arguments, and the descriptor is owned by `pipe` for the whole call
+        let result = unsafe { libc::fcntl(pipe.as_raw_fd(), libc::F_SETPIPE_SZ, size) };
+        match result {
+            -1 => Logger::debug(format!("Failed to widen a frame pipe to {} bytes: {}", size, std::io::Error::last_os_error())),
+            size => Logger::debug(format!("Widened a frame pipe to {} bytes", size)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn widen<T>(_pipe: &T, _frame_size: usize) {}
+}
@@ -0,0 +1,91 @@
+use crate::frame::Frame;
+use crate::error::Error;
+use crate::video::Video;
+use crate::logger::Logger;
+use super::filter_duplicates::FilterDuplicates;
+use super::stats::{MeteredSender, Stats};
+
+use crossbeam_channel::Receiver;
+use std::thread;
+
+// Marks the frames of low-detail scenes for the cheaper 2x model and lanczos; a scene is judged by
+// the edge energy of its first frame, since frames are streamed and the rest of the scene is not known yet
+pub struct AdaptiveScale;
+
+impl AdaptiveScale {
+    const STAGE: &'static str = "adaptive scale";
+    const SCENE_SIMILARITY: f64 = 0.85;
+    // mean luma gradient per pixel, soft animation and out-of-focus shots sit well below it
+    const DETAIL_THRESHOLD: f64 = 6.0;
+    const SAMPLE_STEP: usize = 2;
+
+    fn luma(pixel: &[u8]) -> i32 {
+        (pixel[0] as i32 * 77 + pixel[1] as i32 * 150 + pixel[2] as i32 * 29) >> 8
+    }
+
+    // Horizontal and vertical luma differences, sampled on every other row and column
+    fn edge_energy(frame: &Frame) -> f64 {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        let stride = width * Frame::CHANNELS;
+        let pixel = |x: usize, y: usize| Self::luma(&frame.buffer[y * stride + x * Frame::CHANNELS..][..Frame::CHANNELS]);
+        let (mut energy, mut samples) = (0u64, 0u64);
+        for y in (0..height.saturating_sub(1)).step_by(Self::SAMPLE_STEP) {
+            for x in (0..width.saturating_sub(1)).step_by(Self::SAMPLE_STEP) {
+                let center = pixel(x, y);
+                energy += (center.abs_diff(pixel(x + 1, y)) + center.abs_diff(pixel(x, y + 1))) as u64;
+                samples += 1;
+            }
+        }
+        energy as f64 / samples.max(1) as f64
+    }
+
+    fn process_frames(receiver: Receiver<Result<Frame, Error>>, sender: MeteredSender<Result<Frame, Error>>, input: String, stats: Stats) {
+        let mut previous: Option<Vec<u32>> = None;
+        let mut low_detail = false;
+        let (mut scenes, mut low_detail_scenes, mut marked) = (0, 0, 0);
+
+        while let Ok(frame) = receiver.recv() {
+            let mut frame = match frame {
+                Ok(frame) => frame,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return;
+                },
+            };
+            stats.sample_queue(Self::STAGE, receiver.len());
+            stats.time(Self::STAGE, || {
+                let thumbnail = FilterDuplicates::thumbnail(&frame);
+                let cut = previous.as_deref().is_none_or(|previous| {
+                    FilterDuplicates::thumbnail_similarity(previous, &thumbnail) < Self::SCENE_SIMILARITY
+                });
+                if cut {
+                    low_detail = Self::edge_energy(&frame) < Self::DETAIL_THRESHOLD;
+                    scenes += 1;
+                    low_detail_scenes += low_detail as usize;
+                }
+                previous = Some(thumbnail);
+            });
+            frame.low_detail = low_detail;
+            marked += low_detail as usize * (1 + frame.duplicates);
+            if sender.send(Ok(frame)).is_err() {
+                return;
+            }
+        }
+
+        if marked > 0 {
+            Logger::info(format!(
+                "Upscaled {} frames in {} of {} scenes of {} with the cheaper model", marked, low_detail_scenes, scenes, input
+            ));
+        }
+    }
+
+    pub fn execute(video: &Video, receiver: Receiver<Result<Frame, Error>>, stats: &Stats) -> Receiver<Result<Frame, Error>> {
+        if video.adaptive_model.is_none() {
+            return receiver;
+        }
+        let (sender, adaptive_receiver) = stats.channel("adaptive-scale", video.channel_capacity.get("adaptive-scale", 1));
+        let (input, stats) = (video.input.display().to_string(), stats.clone());
+        thread::spawn(move || Self::process_frames(receiver, sender, input, stats));
+        adaptive_receiver
+    }
+}
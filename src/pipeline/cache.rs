@@ -0,0 +1,83 @@
+use crate::error::Error;
+use crate::video::Video;
+use crate::remote::Remote;
+use crate::grain::Grain;
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone)]
+pub struct FrameCache {
+    directory: PathBuf,
+}
+
+impl FrameCache {
+    const SAMPLE_SIZE: u64 = 1024 * 1024;
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    fn hash(hash: u64, bytes: &[u8]) -> u64 {
+        bytes.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(Self::FNV_PRIME))
+    }
+
+    fn hash_input(input: &Path) -> Result<u64, Error> {
+        let mut file = File::open(input).map_err(|e| Error::new(format!("Failed to open {}: {}", input.display(), e)))?;
+        let size = file.metadata().map_err(|e| Error::new(format!("Failed to read {}: {}", input.display(), e)))?.len();
+        let mut hash = Self::hash(Self::FNV_OFFSET, &size.to_le_bytes());
+
+        for offset in [0, size.saturating_sub(Self::SAMPLE_SIZE)] {
+            let mut sample = Vec::new();
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| (&mut file).take(Self::SAMPLE_SIZE).read_to_end(&mut sample))
+                .map_err(|e| Error::new(format!("Failed to read {}: {}", input.display(), e)))?;
+            hash = Self::hash(hash, &sample);
+        }
+
+        Ok(hash)
+    }
+
+    fn hash_variant(video: &Video) -> u64 {
+        let variant = format!(
+            "{:?}|{:?}|{:?}|{}|{:?}|{:?}|{}|{}|{:?}|{:?}|{:?}|{}|{}|{:?}|{}|{}|{:?}",
+            video.model, video.crop, video.roi, video.detelecine, video.tonemap, video.color, video.stream, video.duplicate_threshold, video.duplicate_mode,
+            video.duplicate_detector, video.chain, video.quality_gate, video.backend, video.chapter, video.skip_static_segments,
+            video.grain == Some(Grain::Preserve), video.adaptive_model
+        );
+        Self::hash(Self::FNV_OFFSET, variant.as_bytes())
+    }
+
+    pub fn new(cache_dir: &str, video: &Video) -> Result<Self, Error> {
+        let input_hash = match &video.sequence {
+            Some(_) => Self::hash(Self::FNV_OFFSET, video.input.as_os_str().as_encoded_bytes()),
+            None if Remote::is_url(video.input) => Self::hash(Self::FNV_OFFSET, video.input.as_os_str().as_encoded_bytes()),
+            None => Self::hash_input(video.input)?,
+        };
+        let directory = PathBuf::from(cache_dir).join(format!("{:016x}-{:016x}", input_hash, Self::hash_variant(video)));
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create cache directory {}: {}", directory.display(), e)))?;
+        Ok(Self { directory })
+    }
+
+    fn frame_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("{:08}.rgb", index))
+    }
+
+    // Reads a cached frame straight into the buffer it will travel in, returning whether there was one of its size
+    pub fn get(&self, index: usize, output: &mut [u8]) -> bool {
+        File::open(self.frame_path(index))
+            .and_then(|mut file| match file.metadata()?.len() == output.len() as u64 {
+                true => file.read_exact(output).map(|_| true),
+                false => Ok(false),
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn put(&self, index: usize, pixels: &[u8]) -> Result<(), Error> {
+        let path = self.frame_path(index);
+        let partial_path = path.with_extension("part");
+        fs::write(&partial_path, pixels)
+            .and_then(|_| fs::rename(&partial_path, &path))
+            .map_err(|e| Error::new(format!("Failed to write cached frame {}: {}", path.display(), e)))
+    }
+}
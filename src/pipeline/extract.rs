@@ -1,106 +1,378 @@
+use crate::buffer::{Buffer, BufferPool};
 use crate::frame::Frame;
 use crate::error::Error;
 use crate::video::Video;
+use crate::logger::{Capture, Logger};
+use crate::telecine::Detelecine;
+use crate::binary::Binary;
+use crate::pipe::Pipe;
+use crate::crop::Crop;
+use crate::capabilities::Capabilities;
+use crate::sequence::Sequence;
+use crate::cancel::Cancel;
+use crate::duplicate_mode::DuplicateMode;
+use super::stats::{MeteredSender, Stats};
+use super::frame_errors::FrameErrors;
+use super::filter_duplicates::{DuplicateFilter, FilterDuplicates};
 
-use std::process::{Child, ChildStdout, Command, Stdio};
-use std::io::{Read, BufReader};
+use std::process::{Child, ChildStdout, Stdio};
+use std::io::{Read, BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::thread;
 
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+#[derive(Clone, Copy)]
+struct FrameTiming {
+    pts: f64,
+    duration: Option<f64>,
+}
+
+struct FrameLayout {
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+    // Applied while reading image sequences; ffmpeg crops video inputs itself
+    crop: Option<Crop>,
+    // End of the input when mpdecimate drops duplicates, so the last kept frame can span to it
+    decimated_end: Option<f64>,
+}
 
 pub struct Extract;
 
 impl Extract {
 
-    const PNG_FOOTER_SIGNATURE: &[u8] = &[0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82];
-    const CHUNK_SIZE: usize = 1024 * 100; // 100KB
-    const MAX_FRAME_BUFFER_SIZE: usize = 1024 * 1024 * 10; // 10MB
+    const POOL_SIZE: usize = 16;
+    const STAGE: &'static str = "extract";
+    const TIMING_FILTER: &'static str = "showinfo";
+    const DECIMATE_FILTER: &'static str = "mpdecimate";
+    const DECODE_ERRORS: [&'static str; 2] = ["error while decoding", "corrupt decoded frame"];
 
-    fn find_png_footer(data: &[u8]) -> Option<usize> {
-        data.windows(12)
-            .position(|window| window == Self::PNG_FOOTER_SIGNATURE)
-            .map(|pos| pos + 12)
-    }
+    fn spawn_ffmpeg_process(video: &Video, frame_errors: &FrameErrors) -> Result<Child, Error> {
+        let mut command = Binary::Ffmpeg.command();
+        if frame_errors.tolerated() {
+            command.args(["-err_detect", "ignore_err"]);
+        }
+        command.args(video.hwdec.input_args());
+        command.args(video.get_seek_args());
+        command.args(["-noautorotate", "-i"])
+            .arg(Binary::path_arg(video.input))
+            .args(["-map", &format!("0:v:{}", video.stream), Capabilities::get().fps_mode_option(), "passthrough"]);
 
-    fn spawn_ffmpeg_process(video: &Video) -> Result<Child, Error> {
-        Command::new("ffmpeg")
+        let filters = [
+            video.get_hwdownload_filter(),
+            video.tonemap.get_filter(),
+            video.detelecine.then(|| String::from(Detelecine::FILTER)),
+            video.get_rotation_filter().map(String::from),
+            video.crop.map(|crop| crop.get_filter()),
+            Some(video.color.get_input_filter()),
+            // max counts the dropped frames, and a run of one drops nothing
+            match (video.duplicate_mode, video.max_duplicate_run) {
+                (DuplicateMode::Decimate, None) => Some(String::from(Self::DECIMATE_FILTER)),
+                (DuplicateMode::Decimate, Some(max_run)) if max_run > 1 => Some(format!("{}=max={}", Self::DECIMATE_FILTER, max_run - 1)),
+                _ => None,
+            },
+            Some(String::from(Self::TIMING_FILTER)),
+        ].into_iter().flatten().collect::<Vec<String>>();
+
+        command.args(["-vf", &filters.join(",")]);
+
+        command
             .args(&[
-                "-r", "1",
-                "-i", &video.input,
-                "-pix_fmt", "rgb8",
-                "-q:v:0", "1",
-                "-vcodec", "png",
-                "-f", "image2pipe",
+                "-pix_fmt", "rgb24",
+                "-f", "rawvideo",
                 "-thread_queue_size", "100",
                 "pipe:1"
             ])
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .stdin(Stdio::null())
             .spawn()
             .map_err(|e| Error::new(format!("Failed to spawn ffmpeg process: {}", e)))
     }
 
-    fn process_chunk(
-        frame_buffer: &mut Vec<u8>,
-        read_chunk: &mut Vec<u8>,
+    fn parse_timing(line: &str) -> Option<FrameTiming> {
+        if !line.contains("Parsed_showinfo") {
+            return None;
+        }
+        let field = |name: &str| line.split_whitespace().find_map(|part| part.strip_prefix(name)?.parse::<f64>().ok());
+        Some(FrameTiming { pts: field("pts_time:")?, duration: field("duration_time:") })
+    }
+
+    fn is_decode_error(line: &str) -> bool {
+        let line = line.to_lowercase();
+        Self::DECODE_ERRORS.iter().any(|error| line.contains(error))
+    }
+
+    // Timing lines are consumed here, everything else is kept for the error if ffmpeg fails
+    fn process_stderr(line: &str, timings: &Sender<FrameTiming>, frame_errors: &FrameErrors) -> bool {
+        match Self::parse_timing(line) {
+            Some(timing) => {
+                let _ = timings.send(timing);
+                false
+            },
+            None if frame_errors.tolerated() && Self::is_decode_error(line) => {
+                frame_errors.record(format!("decode: {}", line));
+                true
+            },
+            None => {
+                Logger::debug(format!("[extract] {}", line));
+                true
+            },
+        }
+    }
+
+    fn frame_timing(timings: &Receiver<FrameTiming>, start: &mut Option<f64>, layout: &FrameLayout, frame_count: usize) -> (f64, f64) {
+        let fallback_duration = 1.0 / layout.frame_rate;
+        match timings.recv() {
+            Ok(timing) => {
+                let start = *start.get_or_insert(timing.pts);
+                ((timing.pts - start).max(0.0), timing.duration.filter(|&duration| duration > 0.0).unwrap_or(fallback_duration))
+            },
+            Err(_) => (frame_count as f64 / layout.frame_rate, fallback_duration),
+        }
+    }
+
+    fn read_frame(
         buff_reader: &mut BufReader<&mut ChildStdout>,
+        pool: &BufferPool,
+        layout: &FrameLayout,
         frame_count: usize
-    ) -> Result<Option<Frame>, Error> {
-        let size = buff_reader
-            .read(read_chunk)
-            .map_err(|e| Error::new(format!("Failed to read chunk: {}", e)))?;
-        if size == 0 {
-            return Ok(None);
+    ) -> Result<Option<Buffer>, Error> {
+        let mut buffer = pool.acquire(Frame::size(layout.width, layout.height));
+        match buff_reader.read_exact(&mut buffer) {
+            Ok(()) => Ok(Some(buffer)),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(Error::new(format!("Failed to read frame {}: {}", frame_count, e))),
         }
-        frame_buffer.extend_from_slice(&read_chunk[..size]);
-        if let Some(index) = Self::find_png_footer(&frame_buffer) {
-            let bytes: Vec<u8> = frame_buffer.drain(..index).collect();
-            let frame = Frame::from_bytes(frame_count, &bytes)?;
-            Ok(Some(frame))
-        } else if frame_buffer.len() > Self::MAX_FRAME_BUFFER_SIZE {
-            Err(Error::new(format!("Frame buffer is too large: {}", frame_buffer.len())))
-        } else {
-            Self::process_chunk(frame_buffer, read_chunk, buff_reader, frame_count)
+    }
+
+    // A kept frame lasts until the next one, and the frames mpdecimate dropped in between become its duplicates.
+    // Indices count those duplicates too, which is what the later stages expect of collapsed frames
+    fn close_run(mut frame: Frame, end: f64, frame_rate: f64, next_index: &mut usize) -> Frame {
+        let duration = end - frame.pts;
+        if duration > frame.duration {
+            frame.duplicates = ((duration * frame_rate).round() as usize).saturating_sub(1);
+            frame.duration = duration;
         }
+        frame.index = *next_index;
+        *next_index += 1 + frame.duplicates;
+        frame
     }
 
-    fn process_stdout(mut stdout: ChildStdout, sender: Sender<Result<Frame, Error>>) {
-        let mut frame_buffer = Vec::new();
-        let mut read_chunk = vec![0u8; Self::CHUNK_SIZE];
+    // Folds the frame in buffer into the previous one when it is a duplicate
+    fn absorb_duplicate(duplicates: &mut Option<DuplicateFilter>, buffer: &[u8], layout: &FrameLayout, duration: f64, stats: &Stats) -> bool {
+        duplicates.as_mut().is_some_and(|filter| {
+            stats.time(FilterDuplicates::STAGE, || filter.absorb(buffer, layout.width, layout.height, duration))
+        })
+    }
+
+    fn distinct_frame(duplicates: &mut Option<DuplicateFilter>, frame: Frame) -> Option<Frame> {
+        match duplicates {
+            Some(filter) => filter.replace(frame),
+            None => Some(frame),
+        }
+    }
+
+    // Returns whether the whole output was read, rather than stopping early on an error or a closed channel
+    fn process_stdout(
+        mut stdout: ChildStdout,
+        timings: Receiver<FrameTiming>,
+        sender: &MeteredSender<Result<Frame, Error>>,
+        layout: FrameLayout,
+        mut duplicates: Option<DuplicateFilter>,
+        stats: Stats
+    ) -> bool {
+        let pool = BufferPool::new(Self::POOL_SIZE);
         let mut buff_reader = BufReader::new(&mut stdout);
         let mut frame_count = 0;
-        loop {
-            match Self::process_chunk(&mut frame_buffer, &mut read_chunk, &mut buff_reader, frame_count) {
+        let mut start = None;
+        let mut pending: Option<Frame> = None;
+        let mut next_index = 0;
+        let complete = loop {
+            let read = stats.time(Self::STAGE, || Self::read_frame(&mut buff_reader, &pool, &layout, frame_count))
+                .map(|buffer| buffer.map(|buffer| {
+                    let (pts, duration) = Self::frame_timing(&timings, &mut start, &layout, frame_count);
+                    (buffer, pts, duration)
+                }));
+            match read {
+                _ if Cancel::requested() => {
+                    break false
+                },
                 Ok(None) => {
-                    break
+                    if let (Some(frame), Some(end)) = (pending.take(), layout.decimated_end) {
+                        let end = end.max(frame.pts + frame.duration);
+                        let _ = sender.send(Ok(Self::close_run(frame, end, layout.frame_rate, &mut next_index)));
+                    }
+                    if let Some(frame) = duplicates.as_mut().and_then(DuplicateFilter::finish) {
+                        let _ = sender.send(Ok(frame));
+                    }
+                    break true
                 },
-                Ok(Some(frame)) => {
+                Ok(Some((buffer, _, duration))) if Self::absorb_duplicate(&mut duplicates, &buffer, &layout, duration, &stats) => {
                     frame_count += 1;
+                },
+                Ok(Some((buffer, pts, duration))) if layout.decimated_end.is_some() => {
+                    let frame = Frame::new(frame_count, pts, duration, layout.width, layout.height, buffer);
+                    frame_count += 1;
+                    let Some(previous) = pending.replace(frame) else {
+                        continue
+                    };
+                    let end = pending.as_ref().map_or(previous.pts + previous.duration, |frame| frame.pts);
+                    if sender.send(Ok(Self::close_run(previous, end, layout.frame_rate, &mut next_index))).is_err() {
+                        break false
+                    }
+                },
+                Ok(Some((buffer, pts, duration))) => {
+                    let frame = Frame::new(frame_count, pts, duration, layout.width, layout.height, buffer);
+                    frame_count += 1;
+                    let Some(frame) = Self::distinct_frame(&mut duplicates, frame) else {
+                        continue
+                    };
                     if sender.send(Ok(frame)).is_err() {
-                        break
+                        break false
                     }
                 }
                 Err(e) => {
                     let _ = sender.send(Err(e));
-                    break
+                    break false
                 }
             }
-        }
+        };
         drop(stdout);
+        complete
+    }
+
+    fn read_image(
+        path: &Path,
+        pool: &BufferPool,
+        layout: &FrameLayout,
+        index: usize,
+        position: usize
+    ) -> Result<Frame, Error> {
+        let image = image::open(path).map_err(|e| Error::new(format!("Failed to read frame {}: {}", path.display(), e)))?;
+        let image = match layout.crop {
+            Some(crop) => image.crop_imm(crop.x as u32, crop.y as u32, crop.width as u32, crop.height as u32),
+            None => image,
+        };
+        let image = image.into_rgb8();
+        if image.dimensions() != (layout.width, layout.height) {
+            return Err(Error::new(format!("Frame {} does not match the sequence size {}x{}", path.display(), layout.width, layout.height)));
+        }
+
+        let mut buffer = pool.acquire(Frame::size(layout.width, layout.height));
+        buffer.copy_from_slice(&image);
+        let pts = position as f64 / layout.frame_rate;
+        Ok(Frame::new(index, pts, 1.0 / layout.frame_rate, layout.width, layout.height, buffer))
     }
 
-    pub fn execute(video: &Video) -> Result<Receiver<Result<Frame, Error>>, Error> {
-        let (sender, receiver) = bounded(1);
-        let mut child = Self::spawn_ffmpeg_process(&video)?;
+    fn read_sequence(
+        pattern: PathBuf,
+        sequence: Sequence,
+        sender: MeteredSender<Result<Frame, Error>>,
+        layout: FrameLayout,
+        mut duplicates: Option<DuplicateFilter>,
+        stats: Stats,
+        frame_errors: FrameErrors
+    ) {
+        let pool = BufferPool::new(Self::POOL_SIZE);
+        let mut index = 0;
+        // Only the position of the last good image is kept, it is read again when a later one fails
+        let mut previous: Option<usize> = None;
+        for position in 0..sequence.count {
+            if Cancel::requested() {
+                break
+            }
+            let path = Sequence::frame_path(&pattern, sequence.start + position);
+            let frame = match stats.time(Self::STAGE, || Self::read_image(&path, &pool, &layout, index, position)) {
+                Ok(frame) => {
+                    if frame_errors.repeat_previous() {
+                        previous = Some(position);
+                    }
+                    frame
+                },
+                Err(e) if frame_errors.tolerated() => {
+                    frame_errors.record(e.to_string());
+                    let repeated = previous.and_then(|previous| {
+                        let path = Sequence::frame_path(&pattern, sequence.start + previous);
+                        Self::read_image(&path, &pool, &layout, index, position).ok()
+                    });
+                    match repeated {
+                        Some(frame) => frame,
+                        None => continue,
+                    }
+                },
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return
+                },
+            };
+            index += 1;
+            if Self::absorb_duplicate(&mut duplicates, &frame.buffer, &layout, frame.duration, &stats) {
+                continue
+            }
+            let Some(frame) = Self::distinct_frame(&mut duplicates, frame) else {
+                continue
+            };
+            if sender.send(Ok(frame)).is_err() {
+                return
+            }
+        }
+        if let Some(frame) = duplicates.as_mut().and_then(DuplicateFilter::finish) {
+            let _ = sender.send(Ok(frame));
+        }
+    }
+
+    pub fn execute(
+        video: &Video,
+        stats: &Stats,
+        frame_errors: &FrameErrors,
+        duplicates: Option<DuplicateFilter>
+    ) -> Result<Receiver<Result<Frame, Error>>, Error> {
+        let (sender, receiver) = stats.channel("extract", video.channel_capacity.get("extract", 1));
+        let layout = FrameLayout {
+            width: video.get_original_width() as u32,
+            height: video.get_original_height() as u32,
+            frame_rate: video.frame_rate,
+            crop: video.crop,
+            decimated_end: (video.duplicate_mode == DuplicateMode::Decimate)
+                .then(|| video.frame_count as f64 / video.frame_rate),
+        };
+
+        if let Some(sequence) = video.sequence.clone() {
+            let (pattern, stats, frame_errors) = (video.input.to_path_buf(), stats.clone(), frame_errors.clone());
+            thread::spawn(move || Self::read_sequence(pattern, sequence, sender, layout, duplicates, stats, frame_errors));
+            return Ok(receiver);
+        }
+
+        let mut child = Self::spawn_ffmpeg_process(video, frame_errors)?;
         let stdout = child.stdout.take().unwrap();
+        Pipe::widen(&stdout, Frame::size(layout.width, layout.height));
+        let (timings_sender, timings) = unbounded();
+        let frame_errors = frame_errors.clone();
+        let capture = Capture::spawn(child.stderr.take(), move |line| Self::process_stderr(line, &timings_sender, &frame_errors));
+        let stats = stats.clone();
+        let input = video.input.display().to_string();
         thread::spawn(move || {
-            Self::process_stdout(stdout, sender);
-            let _ = child.kill();
-            let _ = child.wait();
+            // a decoder that dies mid-file also ends the output, so only the exit status tells it apart from the end
+            if !Self::process_stdout(stdout, timings, &sender, layout, duplicates, stats) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            }
+            match child.wait() {
+                // a signal to the whole process group stops the decoder as well
+                Ok(status) if !status.success() && !Cancel::requested() => {
+                    let _ = sender.send(Err(capture.error(format!("ffmpeg failed to decode {} ({})", input, status))));
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    let _ = sender.send(Err(Error::new(format!("Failed to wait for ffmpeg process: {}", e))));
+                },
+            }
         });
 
         Ok(receiver)
     }
 
-}
\ No newline at end of file
+}
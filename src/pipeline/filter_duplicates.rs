@@ -1,78 +1,222 @@
 use crate::{frame::Frame, video::Video};
+use crate::duplicate_mode::DuplicateMode;
+use crate::duplicate_detector::DuplicateDetector;
+use crate::duplicate_threshold::DuplicateThreshold;
 use crate::error::Error;
+use crate::logger::Logger;
+use super::stats::Stats;
+use super::extract::Extract;
+use super::frame_errors::FrameErrors;
 
-use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError};
-use std::thread;
+use image::RgbImage;
 
 pub struct FilterDuplicates;
 
 impl FilterDuplicates {
 
-    fn frame_is_duplicate(frame1: &Frame, frame2: &Frame, threshold: f64) -> bool {
-        let result = image_compare::rgb_hybrid_compare(
-            &frame1.image.to_rgb8(), 
-            &frame2.image.to_rgb8()
-        );
-        if let Ok(result) = result {
-            if result.score >= threshold {
-                return true
+    pub(super) const STAGE: &'static str = "filter duplicates";
+    const THUMBNAIL_SIZE: usize = 32;
+    const FAST_MARGIN: f64 = 0.05;
+    const SAMPLE_SECONDS: f64 = 10.0;
+    const MIN_SAMPLE_PAIRS: usize = 10;
+    const MIN_AUTO_THRESHOLD: f64 = 0.9;
+    const MIN_GAP: f64 = 0.005;
+
+    pub(super) fn thumbnail(frame: &Frame) -> Vec<u32> {
+        Self::thumbnail_of(&frame.buffer, frame.width, frame.height)
+    }
+
+    fn thumbnail_of(buffer: &[u8], width: u32, height: u32) -> Vec<u32> {
+        let (width, height) = (width as usize, height as usize);
+        let mut cells = vec![(0u32, 0u32); Self::THUMBNAIL_SIZE * Self::THUMBNAIL_SIZE];
+        for (y, row) in buffer.chunks_exact(width * Frame::CHANNELS).enumerate() {
+            let cell_row = y * Self::THUMBNAIL_SIZE / height * Self::THUMBNAIL_SIZE;
+            for (x, pixel) in row.chunks_exact(Frame::CHANNELS).enumerate() {
+                let luma = (pixel[0] as u32 * 77 + pixel[1] as u32 * 150 + pixel[2] as u32 * 29) >> 8;
+                let cell = &mut cells[cell_row + x * Self::THUMBNAIL_SIZE / width];
+                cell.0 += luma;
+                cell.1 += 1;
             }
         }
-        false
-    }
-
-    fn filter_frame(previous_frame: &mut Option<Frame>, frame: Frame, threshold: f64) -> Option<Frame> {
-        if let Some(mut previous) = previous_frame.take() {
-            if Self::frame_is_duplicate(&previous, &frame, threshold) {
-                previous.add_duplicate();
-                *previous_frame = Some(previous);
-                None
-            } else {
-                *previous_frame = Some(frame);
-                Some(previous)
+        cells.into_iter().map(|(sum, count)| sum / count.max(1)).collect()
+    }
+
+    pub(super) fn thumbnail_similarity(thumbnail1: &[u32], thumbnail2: &[u32]) -> f64 {
+        let difference = thumbnail1.iter().zip(thumbnail2).map(|(&a, &b)| a.abs_diff(b) as f64).sum::<f64>();
+        1.0 - difference / (thumbnail1.len() as f64 * 255.0)
+    }
+
+    fn similarity(image1: &RgbImage, image2: &RgbImage) -> Option<f64> {
+        image_compare::rgb_hybrid_compare(image1, image2)
+            .ok()
+            .map(|result| result.score)
+    }
+
+    // Duplicates of noisy sources score just below 1.0, so the threshold goes in the widest gap
+    // between the near-identical pairs and the rest, or stays at exact matches when there is none
+    fn pick_threshold(mut scores: Vec<f64>) -> f64 {
+        scores.retain(|&score| score >= Self::MIN_AUTO_THRESHOLD);
+        scores.sort_by(|a, b| b.total_cmp(a));
+        scores.windows(2)
+            .map(|pair| (pair[0] - pair[1], (pair[0] + pair[1]) / 2.0))
+            .filter(|&(gap, _)| gap >= Self::MIN_GAP)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map_or(1.0, |(_, threshold)| threshold)
+    }
+
+    fn detect_threshold(video: &Video) -> Result<f64, Error> {
+        let frames = Extract::execute(video, &Stats::new(), &FrameErrors::new(video.on_frame_error), None)?;
+        let sample_frames = (Self::SAMPLE_SECONDS * video.frame_rate).ceil() as usize;
+        let (mut previous, mut current) = (ScratchImage::default(), ScratchImage::default());
+        let mut scores = Vec::new();
+        for frame in frames.iter().take(sample_frames) {
+            let frame = frame?;
+            let image = current.fill(&frame.buffer, frame.width, frame.height);
+            if let Some(score) = previous.filled().and_then(|previous| Self::similarity(previous, image)) {
+                scores.push(score);
             }
-        } else {
-            *previous_frame = Some(frame);
-            None
+            std::mem::swap(&mut previous, &mut current);
         }
+
+        let pairs = scores.len();
+        let threshold = if pairs < Self::MIN_SAMPLE_PAIRS { 1.0 } else { Self::pick_threshold(scores) };
+        Logger::info(format!("Duplicate threshold for {}: {:.4} (from {} frame pairs)", video.input.display(), threshold, pairs));
+        Ok(threshold)
     }
 
-    fn process_frames(
-        frames_receiver: Receiver<Result<Frame, Error>>,
-        sender: Sender<Result<Frame, Error>>,
-        threshold: f64
-    ) {
-        let mut previous_frame = None;
-
-        loop {
-            match frames_receiver.try_recv() {
-                Ok(Ok(frame)) => {
-                    if let Some(filtered_frame) = Self::filter_frame(&mut previous_frame, frame, threshold) {
-                        if sender.send(Ok(filtered_frame)).is_err() {
-                            break;
-                        }
-                    }
-                }
-                Ok(Err(e)) => {
-                    let _ = sender.send(Err(e));
-                    break;
-                }
-                Err(TryRecvError::Disconnected) => {
-                    if let Some(previous) = previous_frame.take() {
-                        let _ = sender.send(Ok(previous));
-                    }
-                    break;
-                },
-                Err(TryRecvError::Empty) => { thread::yield_now(); }
+    // Collapse and blend run inside extract; on a video, decimate is left to ffmpeg's mpdecimate
+    pub fn create(video: &Video) -> Result<Option<DuplicateFilter>, Error> {
+        match video.duplicate_mode {
+            DuplicateMode::Keep => return Ok(None),
+            DuplicateMode::Decimate if video.sequence.is_none() => return Ok(None),
+            DuplicateMode::Collapse | DuplicateMode::Blend | DuplicateMode::Decimate => (),
+        }
+        let threshold = match video.duplicate_threshold {
+            DuplicateThreshold::Fixed(threshold) => threshold,
+            DuplicateThreshold::Auto => Self::detect_threshold(video)?,
+        };
+        Ok(Some(DuplicateFilter {
+            threshold,
+            mode: video.duplicate_mode,
+            detector: video.duplicate_detector,
+            max_run: video.max_duplicate_run,
+            previous: None,
+            previous_thumbnail: None,
+            previous_image: ScratchImage::default(),
+            candidate_thumbnail: None,
+            candidate_image: ScratchImage::default(),
+        }))
+    }
+
+}
+
+// image-compare only takes owned images, so one allocation is kept and the pixels of each compared frame copied into it
+#[derive(Default)]
+struct ScratchImage {
+    image: Option<RgbImage>,
+    filled: bool,
+}
+
+impl ScratchImage {
+    fn fill(&mut self, buffer: &[u8], width: u32, height: u32) -> &RgbImage {
+        let image = match self.image.take() {
+            Some(mut image) if image.dimensions() == (width, height) => {
+                image.copy_from_slice(buffer);
+                image
+            },
+            _ => RgbImage::from_raw(width, height, buffer.to_vec()).expect("frame buffer size matches its dimensions"),
+        };
+        self.filled = true;
+        self.image.insert(image)
+    }
+
+    fn get_or_fill(&mut self, buffer: &[u8], width: u32, height: u32) -> &RgbImage {
+        if !self.filled {
+            return self.fill(buffer, width, height);
+        }
+        self.image.as_ref().expect("a filled scratch image has an image")
+    }
+
+    fn filled(&self) -> Option<&RgbImage> {
+        self.image.as_ref().filter(|_| self.filled)
+    }
+
+    fn clear(&mut self) {
+        self.filled = false;
+    }
+}
+
+// Compares each frame as soon as it is read, so a duplicate is folded into the frame before it and its buffer goes
+// straight back to the pool instead of becoming a frame. Thumbnails and images of the previous frame are kept
+// between comparisons rather than rebuilt for every pair, and the images reuse their allocations
+pub struct DuplicateFilter {
+    threshold: f64,
+    mode: DuplicateMode,
+    detector: DuplicateDetector,
+    // a run that reaches it starts over with a frame of its own
+    max_run: Option<usize>,
+    previous: Option<Frame>,
+    previous_thumbnail: Option<Vec<u32>>,
+    previous_image: ScratchImage,
+    candidate_thumbnail: Option<Vec<u32>>,
+    candidate_image: ScratchImage,
+}
+
+impl DuplicateFilter {
+    // The thumbnail check only rules frames out, anything close enough still gets the full comparison
+    fn is_duplicate(&mut self, buffer: &[u8], width: u32, height: u32) -> bool {
+        let Some(previous) = &self.previous else {
+            return false;
+        };
+        if self.detector == DuplicateDetector::Fast {
+            let thumbnail = FilterDuplicates::thumbnail_of(buffer, width, height);
+            let previous_thumbnail = self.previous_thumbnail.get_or_insert_with(|| FilterDuplicates::thumbnail(previous));
+            let different = FilterDuplicates::thumbnail_similarity(previous_thumbnail, &thumbnail) < self.threshold - FilterDuplicates::FAST_MARGIN;
+            self.candidate_thumbnail = Some(thumbnail);
+            if different {
+                return false;
             }
         }
+        let previous_image = self.previous_image.get_or_fill(&previous.buffer, previous.width, previous.height);
+        let image = self.candidate_image.fill(buffer, width, height);
+        FilterDuplicates::similarity(previous_image, image).is_some_and(|score| score >= self.threshold)
     }
 
-    pub fn execute(video: &Video, frames_receiver: Receiver<Result<Frame, Error>>) -> Receiver<Result<Frame, Error>> {
-        let (sender, receiver) = bounded(1);
-        let threshold = video.duplicate_threshold;
-        thread::spawn(move || Self::process_frames(frames_receiver, sender, threshold));
-        receiver
+    fn blend_frame(previous: &mut Frame, buffer: &[u8]) {
+        let weight = previous.duplicates as u32 + 1;
+        for (blended, &pixel) in previous.buffer.iter_mut().zip(buffer.iter()) {
+            *blended = ((*blended as u32 * weight + pixel as u32 + weight.div_ceil(2)) / (weight + 1)) as u8;
+        }
+    }
+
+    // Returns whether the frame in buffer was a duplicate and is now part of the previous frame
+    pub fn absorb(&mut self, buffer: &[u8], width: u32, height: u32, duration: f64) -> bool {
+        self.candidate_thumbnail = None;
+        self.candidate_image.clear();
+        let full = self.previous.as_ref().zip(self.max_run).is_some_and(|(previous, max_run)| previous.duplicates + 1 >= max_run);
+        if full || !self.is_duplicate(buffer, width, height) {
+            return false;
+        }
+        let previous = self.previous.as_mut().expect("a duplicate has a previous frame");
+        if self.mode == DuplicateMode::Blend {
+            Self::blend_frame(previous, buffer);
+            self.previous_thumbnail = None;
+            self.previous_image.clear();
+        }
+        previous.add_duplicate(duration);
+        true
+    }
+
+    // Takes a frame that is not a duplicate and hands back the previous one, now that it has all its duplicates
+    pub fn replace(&mut self, frame: Frame) -> Option<Frame> {
+        self.previous_thumbnail = self.candidate_thumbnail.take();
+        // the old previous image becomes the scratch for the next candidate
+        std::mem::swap(&mut self.previous_image, &mut self.candidate_image);
+        self.candidate_image.clear();
+        self.previous.replace(frame)
     }
 
-}
\ No newline at end of file
+    pub fn finish(&mut self) -> Option<Frame> {
+        self.previous.take()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::frame_error_mode::FrameErrorMode;
+use crate::logger::Logger;
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct FrameErrors {
+    mode: FrameErrorMode,
+    errors: Arc<Mutex<Vec<String>>>,
+}
+
+impl FrameErrors {
+    const LISTED: usize = 10;
+
+    pub fn new(mode: FrameErrorMode) -> Self {
+        Self { mode, errors: Arc::default() }
+    }
+
+    pub fn tolerated(&self) -> bool {
+        self.mode != FrameErrorMode::Abort
+    }
+
+    pub fn repeat_previous(&self) -> bool {
+        self.mode == FrameErrorMode::RepeatPrevious
+    }
+
+    pub fn record(&self, error: String) {
+        Logger::debug(format!("{}, {}", error, self.mode.action()));
+        self.errors.lock().unwrap().push(error);
+    }
+
+    pub fn summary(&self) -> Option<String> {
+        let errors = self.errors.lock().unwrap().len();
+        (errors > 0).then(|| format!("{} frames failed and were {}", errors, self.mode.action()))
+    }
+
+    pub fn report(&self, input: &str) {
+        let errors = self.errors.lock().unwrap();
+        if errors.is_empty() {
+            return;
+        }
+        Logger::warning(format!("{} frames of {} failed and were {}:", errors.len(), input, self.mode.action()));
+        for error in errors.iter().take(Self::LISTED) {
+            Logger::warning(format!("  {}", error));
+        }
+        if errors.len() > Self::LISTED {
+            Logger::warning(format!("  ... and {} more", errors.len() - Self::LISTED));
+        }
+    }
+}
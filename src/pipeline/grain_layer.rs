@@ -0,0 +1,48 @@
+use crate::frame::Frame;
+
+use image::RgbImage;
+use image::imageops::{self, FilterType};
+
+// The difference between a frame and a light blur of it, which is mostly grain and the finest detail
+pub struct GrainLayer {
+    layer: RgbImage,
+}
+
+impl GrainLayer {
+    // Stored offset by 128 so the signed layer fits an image that can be resized
+    const OFFSET: i16 = 128;
+
+    fn blurred(pixels: &[u8], width: usize, height: usize, x: usize, y: usize, channel: usize) -> i16 {
+        let (mut sum, mut count) = (0u32, 0u32);
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                sum += pixels[(ny * width + nx) * Frame::CHANNELS + channel] as u32;
+                count += 1;
+            }
+        }
+        (sum / count) as i16
+    }
+
+    pub fn extract(frame: &Frame) -> Self {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        let mut layer = vec![0u8; frame.buffer.len()];
+        for y in 0..height {
+            for x in 0..width {
+                for channel in 0..Frame::CHANNELS {
+                    let index = (y * width + x) * Frame::CHANNELS + channel;
+                    let grain = frame.buffer[index] as i16 - Self::blurred(&frame.buffer, width, height, x, y, channel);
+                    layer[index] = (grain + Self::OFFSET).clamp(0, 255) as u8;
+                }
+            }
+        }
+        Self { layer: RgbImage::from_raw(frame.width, frame.height, layer).expect("grain layer matches the frame size") }
+    }
+
+    // A bilinear resize keeps the grain the same size relative to the picture without adding ringing of its own
+    pub fn apply(&self, pixels: &mut [u8], width: u32, height: u32) {
+        let layer = imageops::resize(&self.layer, width, height, FilterType::Triangle);
+        for (pixel, grain) in pixels.iter_mut().zip(layer.as_raw()) {
+            *pixel = (*pixel as i16 + *grain as i16 - Self::OFFSET).clamp(0, 255) as u8;
+        }
+    }
+}
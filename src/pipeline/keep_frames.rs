@@ -0,0 +1,118 @@
+use crate::error::Error;
+use crate::frame::Frame;
+use crate::video::Video;
+use crate::frames_format::FramesFormat;
+use super::stats::{MeteredSender, Stats};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+// A frame to write and where to hand it back once written
+type WriteJob = (Frame, Sender<Result<Frame, Error>>);
+
+pub struct KeepFrames;
+
+impl KeepFrames {
+    const STAGE: &'static str = "keep frames";
+    const MAX_WORKERS: usize = 4;
+
+    fn frame_path(directory: &Path, index: usize, format: FramesFormat) -> PathBuf {
+        directory.join(format!("{:08}.{}", index, format.extension()))
+    }
+
+    fn write_frame(frame: &Frame, directory: &Path, format: FramesFormat) -> Result<(), Error> {
+        let path = Self::frame_path(directory, frame.index, format);
+        frame.image()
+            .save_with_format(&path, format.image_format())
+            .map_err(|e| Error::new(format!("Failed to write frame {}: {}", path.display(), e)))?;
+
+        // collapsed duplicates still get their own numbered file
+        for index in frame.index + 1..=frame.index + frame.duplicates {
+            let duplicate = Self::frame_path(directory, index, format);
+            fs::copy(&path, &duplicate)
+                .map_err(|e| Error::new(format!("Failed to write frame {}: {}", duplicate.display(), e)))?;
+        }
+        Ok(())
+    }
+
+    fn spawn_writers(directory: &Path, format: FramesFormat, stats: &Stats) -> Sender<WriteJob> {
+        let workers = thread::available_parallelism().map_or(1, |threads| threads.get()).min(Self::MAX_WORKERS);
+        let (jobs, pending) = bounded::<WriteJob>(workers);
+        for _ in 0..workers {
+            let (pending, directory, stats) = (pending.clone(), directory.to_path_buf(), stats.clone());
+            thread::spawn(move || {
+                while let Ok((frame, result)) = pending.recv() {
+                    let _ = result.send(stats.time(Self::STAGE, || Self::write_frame(&frame, &directory, format)).map(|_| frame));
+                }
+            });
+        }
+        jobs
+    }
+
+    // Image encoding is slow enough to starve the encoder, so frames are written by a few workers
+    // while a forwarding thread passes them on in their original order
+    fn process_frames(
+        receiver: Receiver<Result<Frame, Error>>,
+        sender: MeteredSender<Result<Frame, Error>>,
+        directory: PathBuf,
+        format: FramesFormat,
+        stats: Stats,
+    ) {
+        let jobs = Self::spawn_writers(&directory, format, &stats);
+        let (ordered_sender, ordered) = bounded::<Receiver<Result<Frame, Error>>>(Self::MAX_WORKERS);
+        let forward = thread::spawn(move || {
+            while let Ok(result) = ordered.recv() {
+                let frame = result.recv().unwrap_or_else(|_| Err(Error::new("Frame writer stopped unexpectedly")));
+                let failed = frame.is_err();
+                if sender.send(frame).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        while let Ok(frame) = receiver.recv() {
+            let (result_sender, result) = bounded(1);
+            match frame {
+                Ok(frame) => {
+                    stats.sample_queue(Self::STAGE, receiver.len());
+                    if jobs.send((frame, result_sender)).is_err() {
+                        break;
+                    }
+                },
+                Err(e) => {
+                    let _ = result_sender.send(Err(e));
+                },
+            }
+            if ordered_sender.send(result).is_err() {
+                break;
+            }
+        }
+        drop(jobs);
+        drop(ordered_sender);
+        let _ = forward.join();
+    }
+
+    pub fn execute(
+        video: &Video,
+        frames_receiver: Receiver<Result<Frame, Error>>,
+        stats: &Stats,
+    ) -> Result<Receiver<Result<Frame, Error>>, Error> {
+        let Some(directory) = video.get_frames_directory() else {
+            return Ok(frames_receiver);
+        };
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create frames directory {}: {}", directory.display(), e)))?;
+
+        let (sender, receiver) = stats.channel("keep-frames", video.channel_capacity.get("keep-frames", 1));
+        let (format, stats) = (video.frames_format, stats.clone());
+        thread::spawn(move || Self::process_frames(frames_receiver, sender, directory, format, stats));
+        Ok(receiver)
+    }
+
+    pub fn drain(receiver: Receiver<Result<Frame, Error>>) -> Result<(), Error> {
+        receiver.iter().try_for_each(|frame| frame.map(|_| ()))
+    }
+}
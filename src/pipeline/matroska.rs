@@ -0,0 +1,169 @@
+use crate::error::Error;
+
+use std::io::Write;
+
+pub struct MatroskaWriter<W: Write> {
+    writer: W,
+    frame_rate: f64,
+}
+
+impl<W: Write> MatroskaWriter<W> {
+    const EBML: u32 = 0x1A45DFA3;
+    const EBML_VERSION: u32 = 0x4286;
+    const EBML_READ_VERSION: u32 = 0x42F7;
+    const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+    const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+    const DOC_TYPE: u32 = 0x4282;
+    const DOC_TYPE_VERSION: u32 = 0x4287;
+    const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+    const SEGMENT: u32 = 0x18538067;
+    const INFO: u32 = 0x1549A966;
+    const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+    const MUXING_APP: u32 = 0x4D80;
+    const WRITING_APP: u32 = 0x5741;
+    const TRACKS: u32 = 0x1654AE6B;
+    const TRACK_ENTRY: u32 = 0xAE;
+    const TRACK_NUMBER: u32 = 0xD7;
+    const TRACK_UID: u32 = 0x73C5;
+    const TRACK_TYPE: u32 = 0x83;
+    const FLAG_LACING: u32 = 0x9C;
+    const DEFAULT_DURATION: u32 = 0x23E383;
+    const CODEC_ID: u32 = 0x86;
+    const VIDEO: u32 = 0xE0;
+    const PIXEL_WIDTH: u32 = 0xB0;
+    const PIXEL_HEIGHT: u32 = 0xBA;
+    const COLOUR_SPACE: u32 = 0x2EB524;
+    const CLUSTER: u32 = 0x1F43B675;
+    const TIMESTAMP: u32 = 0xE7;
+    const BLOCK_GROUP: u32 = 0xA0;
+    const BLOCK: u32 = 0xA1;
+    const BLOCK_DURATION: u32 = 0x9B;
+
+    const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+    const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+    const RGB24_FOURCC: &'static [u8] = b"RGB\x18";
+    const BLOCK_HEADER_SIZE: usize = 4;
+
+    pub fn new(writer: W, width: u32, height: u32, frame_rate: f64) -> Result<Self, Error> {
+        let mut matroska = Self { writer, frame_rate };
+        matroska.write_header(width, height)?;
+        Ok(matroska)
+    }
+
+    fn id(id: u32) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let skip = bytes.iter().position(|&byte| byte != 0).unwrap_or(3);
+        bytes[skip..].to_vec()
+    }
+
+    fn size(size: usize) -> [u8; 8] {
+        let mut bytes = (size as u64).to_be_bytes();
+        bytes[0] = 0x01;
+        bytes
+    }
+
+    fn element(id: u32, data: &[u8]) -> Vec<u8> {
+        let mut element = Self::id(id);
+        element.extend_from_slice(&Self::size(data.len()));
+        element.extend_from_slice(data);
+        element
+    }
+
+    fn uint(id: u32, value: u64) -> Vec<u8> {
+        Self::element(id, &value.to_be_bytes())
+    }
+
+    fn master(id: u32, children: &[Vec<u8>]) -> Vec<u8> {
+        Self::element(id, &children.concat())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| Error::new(format!("Failed to write to stdin: {}", e)))
+    }
+
+    fn write_header(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        let ebml = Self::master(Self::EBML, &[
+            Self::uint(Self::EBML_VERSION, 1),
+            Self::uint(Self::EBML_READ_VERSION, 1),
+            Self::uint(Self::EBML_MAX_ID_LENGTH, 4),
+            Self::uint(Self::EBML_MAX_SIZE_LENGTH, 8),
+            Self::element(Self::DOC_TYPE, b"matroska"),
+            Self::uint(Self::DOC_TYPE_VERSION, 4),
+            Self::uint(Self::DOC_TYPE_READ_VERSION, 2),
+        ]);
+
+        let info = Self::master(Self::INFO, &[
+            Self::uint(Self::TIMESTAMP_SCALE, Self::TIMESTAMP_SCALE_NS),
+            Self::element(Self::MUXING_APP, env!("CARGO_PKG_NAME").as_bytes()),
+            Self::element(Self::WRITING_APP, env!("CARGO_PKG_NAME").as_bytes()),
+        ]);
+
+        let tracks = Self::master(Self::TRACKS, &[
+            Self::master(Self::TRACK_ENTRY, &[
+                Self::uint(Self::TRACK_NUMBER, 1),
+                Self::uint(Self::TRACK_UID, 1),
+                Self::uint(Self::TRACK_TYPE, 1),
+                Self::uint(Self::FLAG_LACING, 0),
+                Self::uint(Self::DEFAULT_DURATION, (1_000_000_000.0 / self.frame_rate).round() as u64),
+                Self::element(Self::CODEC_ID, b"V_UNCOMPRESSED"),
+                Self::master(Self::VIDEO, &[
+                    Self::uint(Self::PIXEL_WIDTH, width as u64),
+                    Self::uint(Self::PIXEL_HEIGHT, height as u64),
+                    Self::element(Self::COLOUR_SPACE, Self::RGB24_FOURCC),
+                ]),
+            ]),
+        ]);
+
+        let mut segment = Self::id(Self::SEGMENT);
+        segment.extend_from_slice(&Self::UNKNOWN_SIZE);
+
+        self.write(&ebml)?;
+        self.write(&segment)?;
+        self.write(&info)?;
+        self.write(&tracks)
+    }
+
+    fn timestamp(seconds: f64) -> u64 {
+        (seconds * 1_000_000_000.0 / Self::TIMESTAMP_SCALE_NS as f64).round().max(0.0) as u64
+    }
+
+    pub fn write_frame(&mut self, start: f64, duration: f64, pixels: &[u8]) -> Result<(), Error> {
+        let timestamp = Self::timestamp(start);
+        let duration = Self::timestamp(start + duration).saturating_sub(timestamp);
+
+        let mut block_header = Self::id(Self::BLOCK);
+        block_header.extend_from_slice(&Self::size(Self::BLOCK_HEADER_SIZE + pixels.len()));
+        block_header.extend_from_slice(&[0x81, 0x00, 0x00, 0x00]);
+
+        let block_duration = Self::uint(Self::BLOCK_DURATION, duration);
+        let block_group_size = block_header.len() + pixels.len() + block_duration.len();
+        let mut block_group = Self::id(Self::BLOCK_GROUP);
+        block_group.extend_from_slice(&Self::size(block_group_size));
+
+        let cluster_timestamp = Self::uint(Self::TIMESTAMP, timestamp);
+        let cluster_size = cluster_timestamp.len() + block_group.len() + block_group_size;
+        let mut cluster = Self::id(Self::CLUSTER);
+        cluster.extend_from_slice(&Self::size(cluster_size));
+
+        self.write(&cluster)?;
+        self.write(&cluster_timestamp)?;
+        self.write(&block_group)?;
+        self.write(&block_header)?;
+        self.write(pixels)?;
+        self.write(&block_duration)
+    }
+
+    // Every element size is written in 8 bytes, so the cluster of a frame is its pixels plus a fixed overhead
+    pub fn cluster_size(pixels: usize) -> usize {
+        let element = |id: u32, data: usize| Self::id(id).len() + 8 + data;
+        let block = element(Self::BLOCK, Self::BLOCK_HEADER_SIZE + pixels);
+        let block_group = element(Self::BLOCK_GROUP, block + element(Self::BLOCK_DURATION, 8));
+        element(Self::CLUSTER, element(Self::TIMESTAMP, 8) + block_group)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
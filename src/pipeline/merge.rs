@@ -1,59 +1,149 @@
 use crate::frame::Frame;
 use crate::error::Error;
 use crate::video::Video;
+use crate::logger::Logger;
+use crate::binary::Binary;
+use crate::pipe::Pipe;
+use crate::sequence::Sequence;
+use crate::tonemap::Tonemap;
+use crate::hwdec::HwDecode;
+use crate::aspect_policy::AspectPolicy;
+use super::matroska::MatroskaWriter;
+use super::stats::Stats;
 
 use std::io::Write;
-use std::process::{Child, ChildStdin, Command, Stdio};
+use std::process::{Child, ChildStdin, Stdio};
 use crossbeam_channel::{Receiver, TryRecvError};
 
 pub struct Merge;
 
 impl Merge {
 
+    const STAGE: &'static str = "merge";
+
     fn spawn_ffmpeg_process(video: &Video) -> Result<Child, Error> {
-        Command::new("ffmpeg")
-            .args(&[
-                "-i", &video.input,
-                "-r", &video.frame_rate.to_string(),
-                "-thread_queue_size", "100",
-                "-f", "image2pipe",
-                "-vcodec", "png",
-                "-pix_fmt", "rgb8",
-                "-s", &format!("{}x{}", video.get_scaled_width(), video.get_scaled_height()),
-                "-i", "-",
-                "-map", "0:a",
-                "-map", "0:s?",
+        let (audio_codec, subtitle_codec) = video.get_output_codecs().unwrap_or(("copy", "copy"));
+
+        let sequence_output = Sequence::is_pattern(&video.output);
+        // the image2 muxer only takes the frames, so an image sequence output leaves the input's other streams and metadata out
+        let map_input = video.sequence.is_none() && !sequence_output && !video.live;
+
+        let mut command = Binary::Ffmpeg.command();
+        if map_input {
+            command.args(video.get_seek_args());
+            command.args(video.input_args.get());
+            command.arg("-i").arg(Binary::path_arg(video.input));
+        }
+
+        command.args([
+            "-thread_queue_size", "100",
+            "-f", "matroska",
+            "-i", "-",
+        ]);
+        command.args(video.get_external_inputs());
+
+        if map_input {
+            command.args([
                 "-map", "1:v",
-                "-map_metadata", "0",
-                "-vf", &format!("scale={}x{}:flags=lanczos", &video.width, &video.height),
-                "-pix_fmt", "yuv420p",
-                "-c:v", &video.encoder,
-                "-c:a", "copy",
-                "-c:s", "copy",
-                "-y",
-                &video.output
-            ])
+                "-map", "0:v?",
+                "-map", &format!("-0:v:{}", video.stream),
+            ]);
+            command.args(video.get_stream_args(Some((audio_codec, subtitle_codec)), 2));
+            command.args(video.get_video_tag_args());
+            command.args(["-map_metadata", "0"]);
+            if video.chapter.is_some() {
+                command.args(["-map_chapters", "-1"]);
+            }
+        } else if sequence_output {
+            command.args(["-map", "0:v"]);
+        } else if video.live {
+            // a second session on a live source runs on its own clock and some cameras refuse one, so only the frames are written
+            Logger::warning(format!("Dropping the audio and subtitles of the live input {}", video.input.display()));
+            command.args(["-map", "0:v"]);
+        } else {
+            // the frames are input 0 here, and the sequence itself has nothing else to map
+            command.args(["-map", "0:v"]);
+            command.args(video.get_stream_args(Some((audio_codec, subtitle_codec)), 1));
+        }
+
+        let gpu_pipeline = video.gpu_pipeline && !sequence_output;
+        // subtitles are rendered at the output size, so with them the final scale stays in software
+        let gpu_scale = gpu_pipeline && video.burn_subtitles.is_none() && video.watermark.is_none() && matches!(video.aspect_policy, AspectPolicy::Keep | AspectPolicy::Stretch);
+        // noise works on 8-bit frames, so it runs on the upscaled frames before the 16-bit precision scaling
+        let filters = [
+            video.get_grain_filter(),
+            video.bit_depth.get_precision_filter().map(String::from),
+            if gpu_scale { None } else { video.get_scale_filter() },
+            video.get_subtitle_filter(),
+            video.get_watermark_filter(),
+            (!sequence_output).then(|| video.color.get_output_filter(video.pixel_format)),
+            gpu_pipeline.then(|| HwDecode::get_cuda_upload_filter(video.pixel_format)),
+            if gpu_scale { video.get_cuda_scale_filter() } else { None },
+            video.get_sar_filter(),
+        ].into_iter().flatten().collect::<Vec<String>>();
+
+        if !filters.is_empty() {
+            command.args(["-filter:v:0", &filters.join(",")]);
+        }
+
+        command.args([
+            "-metadata:s:v:0", "rotate=0",
+            "-vsync", "cfr",
+            "-r:v:0", &video.frame_rate.to_string(),
+        ]);
+
+        if sequence_output {
+            let start_number = video.sequence.as_ref().map_or(1, |sequence| sequence.start);
+            command.args(["-f", "image2", "-start_number", &start_number.to_string()]);
+        } else {
+            if video.tonemap.get_filter().is_some() {
+                command.args(Tonemap::SDR_TAGS);
+            }
+
+            command.args(video.color.get_tags());
+            // frames already uploaded keep the surface format they were uploaded in
+            if !gpu_pipeline {
+                command.args(["-pix_fmt:v:0", video.pixel_format]);
+            }
+            command.args([
+                "-c:v", "copy",
+                "-c:v:0", video.encoder,
+            ]);
+            command.args(video.get_encoder_args(0));
+            command.args(video.encoder_args.get());
+
+            command.args(video.get_container_args());
+        }
+
+        command
+            .args(video.output_args.get())
+            .arg("-y")
+            .arg(Binary::path_arg(&video.get_output_target()))
             .stdin(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .stdout(Stdio::null())
             .spawn()
             .map_err(|e| Error::new(format!("Failed to spawn ffmpeg process: {}", e)))
     }
 
-    fn process_stdin(mut stdin: ChildStdin, receiver: Receiver<Result<Frame, Error>>) -> Result<(), Error> {
+    fn process_stdin(video: &Video, stdin: ChildStdin, receiver: Receiver<Result<Frame, Error>>, stats: &Stats) -> Result<(), Error> {
+        let mut matroska = MatroskaWriter::new(
+            stdin,
+            video.get_scaled_width() as u32,
+            video.get_scaled_height() as u32,
+            video.frame_rate
+        )?;
         loop {
             match receiver.try_recv() {
                 Ok(Ok(frame)) => {
-                    let bytes = frame.to_bytes()?;
-                    for _ in 0..(frame.duplicates + 1) {
-                        stdin
-                            .write_all(&bytes)
-                            .map_err(|e| Error::new(format!("Failed to write to stdin: {}", e)))?;
-                    }
-                }
+                    stats.sample_queue(Self::STAGE, receiver.len());
+                    stats.time(Self::STAGE, || matroska.write_frame(frame.pts, frame.duration, &frame.buffer))?;
+                    stats.count_encoded();
+                },
                 Ok(Err(e)) => return Err(e),
                 Err(TryRecvError::Empty) => std::thread::yield_now(),
                 Err(TryRecvError::Disconnected) => {
+                    let mut stdin = matroska.into_inner();
                     let _ = stdin.flush();
                     drop(stdin);
                     return Ok(())
@@ -62,15 +152,25 @@ impl Merge {
         }
     }
 
-    pub fn execute(video: &Video, receiver: Receiver<Result<Frame, Error>>) -> Result<(), Error> {
+    pub fn execute(video: &Video, receiver: Receiver<Result<Frame, Error>>, stats: &Stats) -> Result<(), Error> {
         let mut child = Self::spawn_ffmpeg_process(video)?;
         let stdin = child.stdin.take().unwrap();
-        let result = Self::process_stdin(stdin, receiver);
+        // the frames stay rgb24 at any --bit-depth, the 16-bit precision filter runs in this ffmpeg
+        let pixels = Frame::size(video.get_scaled_width() as u32, video.get_scaled_height() as u32);
+        Pipe::widen(&stdin, MatroskaWriter::<ChildStdin>::cluster_size(pixels));
+        let capture = Logger::capture("merge", child.stderr.take());
+        let result = Self::process_stdin(video, stdin, receiver, stats);
         if result.is_err() {
             let _ = child.kill();
         }
-        let _ = child.wait();
-        result
+        let status = child
+            .wait()
+            .map_err(|e| Error::new(format!("Failed to wait for ffmpeg process: {}", e)))?;
+        result?;
+        if !status.success() {
+            return Err(capture.error(format!("ffmpeg failed to write {} ({})", video.output.display(), status)));
+        }
+        Ok(())
     }
 
 }
\ No newline at end of file
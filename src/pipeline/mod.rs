@@ -3,33 +3,380 @@ mod upscale;
 mod filter_duplicates;
 mod progress;
 mod merge;
+mod matroska;
+mod scheduler;
+mod tiling;
+mod passthrough;
+mod preview;
+mod stats;
+mod cache;
+mod throttle;
+mod keep_frames;
+mod screenshot;
+mod frame_errors;
+mod quality_gate;
+mod verify;
+mod static_segments;
+mod adaptive_scale;
+mod grain_layer;
+mod queue_slot;
+mod reference;
 
 use extract::Extract;
 use upscale::Upscale;
 use filter_duplicates::FilterDuplicates;
 use progress::Progress;
 use merge::Merge;
+use scheduler::Scheduler;
+use passthrough::Passthrough;
+use preview::Preview;
+use stats::Stats;
+use throttle::Throttle;
+use keep_frames::KeepFrames;
+use screenshot::{Screenshot, Still};
+use frame_errors::FrameErrors;
+use quality_gate::QualityGate;
+use verify::Verify;
+use static_segments::StaticSegments;
+use adaptive_scale::AdaptiveScale;
+use reference::Reference;
+use queue_slot::QueueSlot;
 
 use crate::arguments::Arguments;
+use crate::progress_mode::ProgressMode;
+use crate::job::Job;
+use crate::skip_mode::SkipMode;
 use crate::video::Video;
+use crate::sequence::Sequence;
+use crate::chapters::{Chapter, ChapterSelection};
+use crate::chapter_output::ChapterOutput;
+use crate::media_info::MediaInfo;
 use crate::error::Error;
+use crate::cancel::Cancel;
+use crate::backend::Backend;
+use crate::metrics::Metrics;
+use crate::logger::{Level, Logger};
+
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use indicatif::{MultiProgress, ProgressDrawTarget};
 
 pub struct Pipeline;
 
 impl Pipeline {
-    pub fn execute(arguments: Arguments) -> Result<(), Error> {
-        for (input, output) in &arguments.files {
-            let video = Video::new(&arguments, input, output)?;
-            if video.model.is_none() {
-                println!("Skipping {}", input);
-                continue
-            }
-            let extract = Extract::execute(&video)?;
-            let filter_duplicates = FilterDuplicates::execute(&video, extract);
-            let upscale = Upscale::execute(&video, filter_duplicates)?;
-            let progress = Progress::execute(&video, upscale);
-            Merge::execute(&video, progress)?;
+    const STATS_INTERVAL: Duration = Duration::from_secs(10);
+    const MIN_RESUME_SECONDS: f64 = 1.0;
+
+    fn upscale_file(
+        video: &Video,
+        arguments: &Arguments,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        // held until the file is merged, so waiting processes start with the GPU to themselves
+        let _slot = match arguments.queue_slots {
+            Some(slots) if video.backend != Backend::Cpu => Some(QueueSlot::acquire(slots, &video.input.display().to_string())?),
+            _ => None,
+        };
+        let preflight = Upscale::preflight(video, scheduler)?;
+        let throttle = Throttle::new(arguments.gpu_limit, arguments.max_fps.clone());
+        let stats = Stats::new();
+        if arguments.stats {
+            stats.watch(video.input.display().to_string(), Self::STATS_INTERVAL);
+        }
+        let frame_errors = FrameErrors::new(video.on_frame_error);
+        let quality_gate = video.quality_gate.then(QualityGate::default);
+        let duplicates = FilterDuplicates::create(video)?;
+        let frames = Extract::execute(video, &stats, &frame_errors, duplicates)?;
+        let frames = StaticSegments::execute(video, frames, &stats);
+        let frames = AdaptiveScale::execute(video, frames, &stats);
+        let frames = Screenshot::execute(video, frames, Still::Original, &stats)?;
+        let upscale = Upscale::execute(video, frames, preflight, throttle, &stats, &frame_errors, quality_gate.as_ref())?;
+        let upscale = Screenshot::execute(video, upscale, Still::Upscaled, &stats)?;
+        let progress = Progress::execute(video, upscale, multi_progress, arguments.progress, preview.cloned(), &stats);
+        let frames = KeepFrames::execute(video, progress, &stats)?;
+        if video.frames_only {
+            KeepFrames::drain(frames)?;
+        } else {
+            Merge::execute(video, frames, &stats)?;
+            // segments have no single file to check
+            let single_output = !Sequence::is_pattern(&video.output) && !video.is_segmented();
+            if video.verify && single_output && !Cancel::requested() {
+                Verify::execute(video)?;
+            }
+            if single_output && !Cancel::requested() {
+                Reference::execute(video)?;
+            }
+        }
+        stats.report(&video.input.display().to_string());
+        frame_errors.report(&video.input.display().to_string());
+        if let Some(quality_gate) = &quality_gate {
+            quality_gate.report(&video.input.display().to_string());
+        }
+        arguments.report.count_frames(video.input, stats.encoded());
+        let warnings = [frame_errors.summary(), quality_gate.as_ref().and_then(QualityGate::summary)];
+        for warning in warnings.into_iter().flatten() {
+            arguments.report.warn(video.input, warning);
+        }
+        Ok(())
+    }
+
+    fn finalize_output(video: &Video, replace_output: bool, result: Result<(), Error>) -> Result<(), Error> {
+        let partial_output = video.get_partial_output();
+        if partial_output == video.output || (video.frames_only && video.model.is_some()) {
+            return result;
+        }
+
+        if let Err(e) = result {
+            if video.fragmented && partial_output.is_file() {
+                Logger::warning(format!("Kept the playable part of {} at {}", video.output.display(), partial_output.display()));
+            } else {
+                let _ = std::fs::remove_file(&partial_output);
+            }
+            return Err(e);
+        }
+
+        if video.output.exists() && !replace_output {
+            return Err(Error::new(format!(
+                "Output file {} already exists, result kept at {}", video.output.display(), partial_output.display()
+            )));
+        }
+
+        std::fs::rename(&partial_output, &video.output)
+            .map_err(|e| Error::new(format!("Failed to move {} to {}: {}", partial_output.display(), video.output.display(), e)))
+    }
+
+    // Only a fragmented partial output from a run the batch state recorded as failed or cancelled is trusted
+    fn resumable_seconds(job: &Job, video: &Video, arguments: &Arguments) -> Option<f64> {
+        let interrupted = arguments.batch.as_ref().is_some_and(|batch| batch.was_interrupted(job));
+        if !video.fragmented || !interrupted || job.chapter.is_some() || Sequence::is_pattern(&video.output) || video.is_segmented() {
+            return None;
+        }
+        MediaInfo::probe(&video.get_partial_output()).ok()?.duration.filter(|&seconds| seconds >= Self::MIN_RESUME_SECONDS)
+    }
+
+    // The rest of the input is upscaled like a chapter and joined to what the interrupted run already wrote
+    fn resume_file(
+        job: &Job,
+        video: &Video,
+        done: f64,
+        arguments: &Arguments,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        let end = MediaInfo::probe(job.input.as_path())?.duration
+            .ok_or_else(|| Error::new(format!("Cannot resume {}: its duration is unknown", job.input.display())))?;
+        Logger::info(format!("Resuming {} at {:.1}s of {:.1}s", job.input.display(), done, end));
+
+        let partial_output = video.get_partial_output();
+        let extension = video.output.extension().unwrap_or_default().to_string_lossy();
+        let stem = video.output.file_stem().unwrap_or_default().to_string_lossy();
+        let directory = video.output.with_file_name(format!(".resume-{}-{}", process::id(), stem));
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create {}: {}", directory.display(), e)))?;
+        let done_part = directory.join(format!("part01.{}", extension));
+        let rest_part = directory.join(format!("part02.{}", extension));
+        fs::rename(&partial_output, &done_part)
+            .map_err(|e| Error::new(format!("Failed to move {} to {}: {}", partial_output.display(), done_part.display(), e)))?;
+
+        let rest_job = Job { output: rest_part.clone(), chapter: Some(Chapter { number: 0, start: done, end }), ..job.clone() };
+        let result = Self::process_file(&rest_job, arguments, scheduler, multi_progress, preview)
+            .and_then(|_| Chapter::concat(&[done_part.clone(), rest_part], &directory, &partial_output));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_output);
+            let _ = fs::rename(&done_part, &partial_output);
+        }
+        let _ = fs::remove_dir_all(&directory);
+        Self::finalize_output(video, arguments.replaces_output(), result)
+    }
+
+    fn join_chapters(
+        job: &Job,
+        chapters: Vec<Chapter>,
+        directory: &Path,
+        arguments: &Arguments,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        let extension = job.output.extension().unwrap_or_default().to_string_lossy();
+        let mut parts = Vec::new();
+        for chapter in chapters {
+            let part = directory.join(format!("chapter{:02}.{}", chapter.number, extension));
+            let chapter_job = Job { output: part.clone(), chapter: Some(chapter), ..job.clone() };
+            Self::process_file(&chapter_job, arguments, scheduler, multi_progress, preview)?;
+            parts.push(part);
+        }
+
+        let joined = directory.join(format!("joined.{}", extension));
+        Chapter::concat(&parts, directory, &joined)?;
+        fs::rename(&joined, &job.output)
+            .map_err(|e| Error::new(format!("Failed to move {} to {}: {}", joined.display(), job.output.display(), e)))
+    }
+
+    // Each selected chapter is upscaled as its own job, then written next to the output or joined into it
+    fn process_chapters(
+        job: &Job,
+        selection: &ChapterSelection,
+        arguments: &Arguments,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        if Sequence::is_pattern(&job.input) || Sequence::is_pattern(&job.output) {
+            return Err(Error::new(format!("--chapters does not work with image sequences: {}", job.input.display())));
+        }
+        let chapters = selection.select(&MediaInfo::chapters(&job.input)?, &job.input)?;
+
+        if job.settings.chapter_output == ChapterOutput::Split {
+            for chapter in chapters {
+                let output = chapter.output_path(&job.output);
+                if output.exists() && !arguments.replace_output && arguments.skip_policy.skips(&output, || Some(chapter.duration())) {
+                    Logger::info(format!("Skipping {} output file already exists", output.display()));
+                    continue;
+                }
+                let chapter_job = Job { output, chapter: Some(chapter), ..job.clone() };
+                Self::process_file(&chapter_job, arguments, scheduler, multi_progress, preview)?;
+            }
+            return Ok(());
+        }
+
+        // Next to the output so the joined file can be renamed into place
+        let stem = job.output.file_stem().unwrap_or_default().to_string_lossy();
+        let directory = job.output.with_file_name(format!(".chapters-{}-{}", process::id(), stem));
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create {}: {}", directory.display(), e)))?;
+        let result = Self::join_chapters(job, chapters, &directory, arguments, scheduler, multi_progress, preview);
+        let _ = fs::remove_dir_all(&directory);
+        result
+    }
+
+    fn process_file(
+        job: &Job,
+        arguments: &Arguments,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        if let (Some(selection), None) = (&job.settings.chapters, job.chapter) {
+            return Self::process_chapters(job, selection, arguments, scheduler, multi_progress, preview);
+        }
+        let video = Video::new(job)?;
+        arguments.report.describe(&video);
+        let existing = !video.frames_only && video.output != job.output && video.output.exists() && !arguments.replace_output;
+        if existing && arguments.skip_policy.skips(&video.output, || video.media.as_ref()?.duration) {
+            Logger::info(format!("Skipping {} output file already exists", video.output.display()));
+            arguments.report.skip(job);
+            return Ok(())
+        }
+        if let Some(done) = Self::resumable_seconds(job, &video, arguments) {
+            return Self::resume_file(job, &video, done, arguments, scheduler, multi_progress, preview);
+        }
+        let result = if video.model.is_none() {
+            if video.on_skip == SkipMode::Skip {
+                Logger::info(format!("Skipping {}", job.input.display()));
+                arguments.report.skip(job);
+                return Ok(())
+            }
+            Logger::info(format!("No upscaling needed, using {} for {}", video.on_skip, job.input.display()));
+            Passthrough::execute(&video)
+        } else {
+            Self::upscale_file(&video, arguments, scheduler, multi_progress, preview)
+        };
+        // a cancelled file ends early but cleanly, so it has to be treated as failed to drop the partial output;
+        // a live input only ends when cancelled, so there it is the normal way to stop
+        let result = if Cancel::requested() && !video.live { Err(Cancel::error()) } else { result };
+        Self::finalize_output(&video, arguments.replaces_output(), result)
+    }
+
+    fn process_files(
+        arguments: &Arguments,
+        next_file: &AtomicUsize,
+        failed: &AtomicBool,
+        failures: &AtomicUsize,
+        scheduler: &Scheduler,
+        multi_progress: &MultiProgress,
+        preview: Option<&Preview>,
+    ) -> Result<(), Error> {
+        while !failed.load(Ordering::SeqCst) && !Cancel::requested() {
+            let index = next_file.fetch_add(1, Ordering::SeqCst);
+            let Some(job) = arguments.files.get(index) else {
+                break
+            };
+            Metrics::set_queued(arguments.files.len() - index - 1);
+            Metrics::start_job();
+            let started = Instant::now();
+            let result = Self::process_file(job, arguments, scheduler, multi_progress, preview);
+            // files after this one may have started on other workers, so they count as still needing their models
+            let pending = arguments.files.get(index + 1..).unwrap_or_default();
+            Upscale::release_upscalers(|name| pending.iter().any(|job| job.uses_model(name)));
+            Metrics::finish_job(result.is_ok());
+            if let Some(batch) = &arguments.batch {
+                batch.update(job, &result);
+            }
+            arguments.notify.send(job, &result, started.elapsed());
+            arguments.report.finish(job, &result, started.elapsed());
+            match result {
+                // with --keep-going a failed file is only reported, a cancel still stops the batch
+                Err(e) if arguments.keep_going && !Cancel::requested() => {
+                    Logger::error(format!("{}: {}", job.input.display(), e));
+                    failures.fetch_add(1, Ordering::SeqCst);
+                },
+                Err(e) => {
+                    failed.store(true, Ordering::SeqCst);
+                    return Err(e);
+                },
+                Ok(()) => (),
+            }
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn execute(arguments: Arguments) -> Result<(), Error> {
+        let next_file = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let failures = AtomicUsize::new(0);
+        let scheduler = Scheduler::new(if arguments.background { Upscale::BACKGROUND_JOBS } else { Upscale::MAX_JOBS });
+        let multi_progress = MultiProgress::new();
+        if !Logger::enabled(Level::Info) || arguments.progress != ProgressMode::Bar {
+            multi_progress.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        let workers = arguments.parallel_files.min(arguments.files.len()).max(1);
+        if let Some(address) = arguments.metrics {
+            Metrics::set_queued(arguments.files.len());
+            Metrics::serve(address)?;
+        }
+        let preview = arguments.preview_window.then(Preview::default);
+
+        let result = thread::scope(|scope| {
+            let handles = (0..workers)
+                .map(|_| scope.spawn(|| Self::process_files(&arguments, &next_file, &failed, &failures, &scheduler, &multi_progress, preview.as_ref())))
+                .collect::<Vec<_>>();
+            if let Some(preview) = &preview {
+                preview.run_window(|| handles.iter().all(|handle| handle.is_finished()));
+            }
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(Error::new("File pipeline thread panicked"))))
+                .collect::<Result<Vec<()>, Error>>()
+                .map(|_| ())
+        });
+
+        // dropping the cached models closes the processes of external upscalers
+        Upscale::release_upscalers(|_| false);
+        if let Some(preview) = preview {
+            preview.close();
+        }
+        arguments.report.complete();
+        match failures.load(Ordering::SeqCst) {
+            0 => result,
+            failures => result.and(Err(Error::new(format!("{} of {} files failed", failures, arguments.files.len())))),
+        }
+    }
+}
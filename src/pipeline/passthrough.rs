@@ -0,0 +1,69 @@
+use crate::error::Error;
+use crate::video::Video;
+use crate::logger::Logger;
+use crate::binary::Binary;
+use crate::skip_mode::SkipMode;
+
+use std::process::Stdio;
+
+pub struct Passthrough;
+
+impl Passthrough {
+    pub fn execute(video: &Video) -> Result<(), Error> {
+        let mut command = Binary::Ffmpeg.command();
+        if let Some(sequence) = &video.sequence {
+            command.args([
+                "-framerate", &video.frame_rate.to_string(),
+                "-start_number", &sequence.start.to_string(),
+            ]);
+        }
+
+        // transcoding with --gpu-pipeline never leaves the GPU, nvdec frames go straight to nvenc
+        let gpu_pipeline = video.gpu_pipeline && video.on_skip == SkipMode::Transcode && video.sequence.is_none();
+        if gpu_pipeline {
+            command.args(video.hwdec.input_args());
+        }
+        command.args(video.get_seek_args());
+        command.arg("-i").arg(Binary::path_arg(video.input));
+        command.args(video.get_external_inputs());
+        command.args(["-map", "0:v"]);
+        command.args(video.get_stream_args(video.get_output_codecs(), 1));
+        command.args([
+            "-map_metadata", "0",
+            "-c", "copy",
+        ]);
+        if video.chapter.is_some() {
+            command.args(["-map_chapters", "-1"]);
+        }
+
+        if video.on_skip == SkipMode::Transcode {
+            let stream = video.stream;
+            command.args([&format!("-c:v:{}", stream), video.encoder]);
+            if !gpu_pipeline {
+                command.args([&format!("-pix_fmt:v:{}", stream), video.pixel_format]);
+            }
+            command.args(video.get_encoder_args(stream));
+        }
+
+        command.args(video.get_container_args());
+
+        let mut child = command
+            .arg("-y")
+            .arg(Binary::path_arg(&video.get_output_target()))
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::new(format!("Failed to spawn ffmpeg process: {}", e)))?;
+        let capture = Logger::capture("passthrough", child.stderr.take());
+
+        let status = child
+            .wait()
+            .map_err(|e| Error::new(format!("Failed to wait for ffmpeg process: {}", e)))?;
+        if !status.success() {
+            return Err(capture.error(format!("Failed to {} {} to {}", video.on_skip, video.input.display(), video.output.display())));
+        }
+
+        Ok(())
+    }
+}
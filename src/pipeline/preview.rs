@@ -0,0 +1,110 @@
+use crate::frame::Frame;
+use crate::logger::Logger;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use minifb::{ScaleMode, Window, WindowOptions};
+
+struct Snapshot {
+    title: String,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Shared {
+    snapshot: Mutex<Option<Snapshot>>,
+    last_capture: Mutex<Option<Instant>>,
+    closed: AtomicBool,
+}
+
+#[derive(Clone, Default)]
+pub struct Preview {
+    shared: Arc<Shared>,
+}
+
+impl Preview {
+    const CAPTURE_INTERVAL: Duration = Duration::from_secs(1);
+    const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+    const MAX_WIDTH: u32 = 960;
+
+    pub fn close(&self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+    }
+
+    fn capture_due(&self) -> bool {
+        let mut last_capture = self.shared.last_capture.lock().unwrap();
+        if last_capture.is_some_and(|last_capture| last_capture.elapsed() < Self::CAPTURE_INTERVAL) {
+            return false;
+        }
+        *last_capture = Some(Instant::now());
+        true
+    }
+
+    pub fn offer(&self, title: &str, frame: &Frame) {
+        if self.shared.closed.load(Ordering::SeqCst) || !self.capture_due() {
+            return;
+        }
+
+        let step = frame.width.div_ceil(Self::MAX_WIDTH).max(1);
+        let (width, height) = (frame.width / step, frame.height / step);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| ((y * step * frame.width + x * step) as usize) * Frame::CHANNELS))
+            .map(|offset| {
+                let [red, green, blue] = [frame.buffer[offset], frame.buffer[offset + 1], frame.buffer[offset + 2]];
+                (red as u32) << 16 | (green as u32) << 8 | blue as u32
+            })
+            .collect();
+
+        *self.shared.snapshot.lock().unwrap() = Some(Snapshot {
+            title: format!("Preview: {} (frame {})", title, frame.index),
+            width: width as usize,
+            height: height as usize,
+            pixels,
+        });
+    }
+
+    // AppKit only allows windows on the main thread, so the caller runs this there while the files are processed
+    // elsewhere, until done returns true or the window is closed
+    pub fn run_window(&self, done: impl Fn() -> bool) {
+        let shared = &self.shared;
+        let mut window: Option<(Window, usize, usize)> = None;
+
+        while !shared.closed.load(Ordering::SeqCst) && !done() {
+            let snapshot = shared.snapshot.lock().unwrap().take();
+            match (snapshot, &mut window) {
+                (Some(snapshot), _) => {
+                    if !matches!(&window, Some((_, width, height)) if *width == snapshot.width && *height == snapshot.height) {
+                        let options = WindowOptions { resize: true, scale_mode: ScaleMode::AspectRatioStretch, ..WindowOptions::default() };
+                        match Window::new(&snapshot.title, snapshot.width, snapshot.height, options) {
+                            Ok(created) => window = Some((created, snapshot.width, snapshot.height)),
+                            Err(e) => {
+                                Logger::warning(format!("Failed to open preview window: {}", e));
+                                break;
+                            }
+                        }
+                    }
+                    if let Some((window, width, height)) = &mut window {
+                        window.set_title(&snapshot.title);
+                        if let Err(e) = window.update_with_buffer(&snapshot.pixels, *width, *height) {
+                            Logger::warning(format!("Failed to update preview window: {}", e));
+                            break;
+                        }
+                    }
+                },
+                (None, Some((window, _, _))) => window.update(),
+                (None, None) => {},
+            }
+
+            if window.as_ref().is_some_and(|(window, _, _)| !window.is_open()) {
+                break;
+            }
+            thread::sleep(Self::REFRESH_INTERVAL);
+        }
+
+        shared.closed.store(true, Ordering::SeqCst);
+    }
+}
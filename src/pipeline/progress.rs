@@ -1,20 +1,213 @@
 use crate::error::Error;
 use crate::frame::Frame;
 use crate::video::Video;
+use super::preview::Preview;
+use crate::progress_mode::ProgressMode;
+use crate::logger::Logger;
+use crate::gpu::Gpu;
+use crate::memory::Memory;
+use crate::metrics::Metrics;
+use super::stats::{MeteredSender, Stats};
 
+use std::collections::VecDeque;
 use std::fmt::Write;
-use std::time::Instant;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
-use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use crossbeam_channel::{Receiver, TryRecvError};
+
+// Sampled on its own thread since nvidia-smi is too slow to run for every frame
+#[derive(Clone, Default)]
+struct MemoryUsage {
+    label: Arc<Mutex<String>>,
+}
+
+impl MemoryUsage {
+    const INTERVAL: Duration = Duration::from_secs(2);
+
+    fn sample(gpu_total: Option<u64>) -> String {
+        let vram = match (Gpu::used_memory(), gpu_total) {
+            (Some(used), Some(total)) => Some(format!("[vram: {} / {}]", Memory::format(used), Memory::format(total))),
+            (Some(used), None) => Some(format!("[vram: {}]", Memory::format(used))),
+            (None, _) => None,
+        };
+        let rss = Memory::resident().map(|rss| format!("[rss: {}]", Memory::format(rss)));
+        vram.into_iter().chain(rss).collect::<Vec<String>>().join(" ")
+    }
+
+    // Stops once every other handle is dropped, i.e. when the file's progress ends
+    fn watch() -> Self {
+        let usage = Self::default();
+        let label = Arc::downgrade(&usage.label);
+        thread::spawn(move || {
+            let gpu_total = Gpu::total_memory();
+            while let Some(shared) = label.upgrade() {
+                let sample = Self::sample(gpu_total);
+                *shared.lock().unwrap() = sample;
+                drop(shared);
+                thread::sleep(Self::INTERVAL);
+            }
+        });
+        usage
+    }
+
+    fn label(&self) -> String {
+        self.label.lock().unwrap().clone()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    time: Instant,
+    upscaled: usize,
+    emitted: usize,
+    encoded: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Rates {
+    fps: f64,
+    encode_fps: f64,
+    eta: Option<f64>,
+}
+
+// Rates over the most recent frames only; the window starts at the first frame so model initialization is excluded
+struct RollingRate {
+    samples: VecDeque<Sample>,
+    first: Option<Sample>,
+    total: usize,
+    // shared with the progress bar's eta key, NaN while unknown
+    eta: Arc<AtomicU64>,
+    stats: Stats,
+}
+
+impl RollingRate {
+    const WINDOW: usize = 120;
+
+    fn new(total: usize, stats: Stats) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(Self::WINDOW),
+            first: None,
+            total,
+            eta: Arc::new(AtomicU64::new(f64::NAN.to_bits())),
+            stats,
+        }
+    }
+
+    fn record(&mut self, upscaled: usize, emitted: usize) -> Rates {
+        let sample = Sample { time: Instant::now(), upscaled, emitted, encoded: self.stats.encoded() };
+        self.first.get_or_insert(sample);
+        if self.samples.len() == Self::WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        let rates = self.rates();
+        self.eta.store(rates.eta.unwrap_or(f64::NAN).to_bits(), Ordering::Relaxed);
+        rates
+    }
+
+    // Averaged from the first frame for the final summary
+    fn overall(&self, upscaled: usize) -> Rates {
+        let Some(first) = self.first else {
+            return Rates::default();
+        };
+        let elapsed = first.time.elapsed().as_secs_f64().max(f64::EPSILON);
+        Rates {
+            fps: upscaled.saturating_sub(first.upscaled) as f64 / elapsed,
+            encode_fps: self.stats.encoded().saturating_sub(first.encoded) as f64 / elapsed,
+            eta: None,
+        }
+    }
+
+    fn rate(&self, count: impl Fn(&Sample) -> usize) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if last.time > first.time => {
+                count(last).saturating_sub(count(first)) as f64 / (last.time - first.time).as_secs_f64()
+            },
+            _ => 0.0,
+        }
+    }
+
+    fn rates(&self) -> Rates {
+        let emitted_fps = self.rate(|sample| sample.emitted);
+        let remaining = self.samples.back().map_or(self.total, |sample| self.total.saturating_sub(sample.emitted));
+        Rates {
+            fps: self.rate(|sample| sample.upscaled),
+            encode_fps: self.rate(|sample| sample.encoded),
+            // a total of 0 is a live input, which has no end to estimate
+            eta: (emitted_fps > 0.0 && self.total > 0).then(|| remaining as f64 / emitted_fps),
+        }
+    }
+}
+
+// Periodic summaries for when no progress bar is drawn
+struct LineReporter {
+    mode: ProgressMode,
+    name: String,
+    total: usize,
+    last_report: Instant,
+}
+
+impl LineReporter {
+    const INTERVAL: Duration = Duration::from_secs(30);
+
+    fn report(&mut self, emitted: usize, rates: Rates, memory: &str, finished: bool) {
+        if !finished && self.last_report.elapsed() < Self::INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+
+        // a live input has no total until it ends
+        let total = match (finished, self.total) {
+            (true, _) => Some(emitted),
+            (false, 0) => None,
+            (false, total) => Some(total.max(emitted)),
+        };
+        let eta = if finished { Some(0.0) } else { rates.eta };
+        let percent = total.map(|total| emitted as f64 / total.max(1) as f64 * 100.0);
+        let memory = if memory.is_empty() { String::new() } else { format!(" {}", memory) };
+        match (self.mode, total) {
+            (ProgressMode::Plain, None) => Logger::info(format!(
+                "{}: {} frames at {:.1} fps, encoding at {:.1} fps{}",
+                self.name, emitted, rates.fps, rates.encode_fps, memory
+            )),
+            (ProgressMode::Plain, Some(total)) => Logger::info(format!(
+                "{}: {}/{} frames ({:.0}%) at {:.1} fps, encoding at {:.1} fps, ETA {}{}{}",
+                self.name, emitted, total, percent.unwrap_or_default(), rates.fps, rates.encode_fps,
+                eta.map_or(String::from("unknown"), |eta| format!("{:.0}s", eta)),
+                memory,
+                if finished { ", done" } else { "" }
+            )),
+            (ProgressMode::Json, _) => println!("{}", serde_json::json!({
+                "input": self.name,
+                "frames": emitted,
+                "total": total,
+                "percent": percent,
+                "fps": rates.fps,
+                "encode_fps": rates.encode_fps,
+                "eta_seconds": eta,
+                "finished": finished,
+            })),
+            (ProgressMode::Bar | ProgressMode::None, _) => (),
+        }
+    }
+}
 
 pub struct Progress;
 
 impl Progress {
 
-    pub fn create_progress_bar(video: &Video) -> ProgressBar {
-        let progress_bar = ProgressBar::new(video.frame_count as u64);
-        let progress_template = "[{elapsed_precise}] [{eta_precise}] [{wide_bar:.white/green}] {pos}/{len} {percent} {msg}";
-        let file_template = format!("{} -> {}", video.input, video.output);
+    // indicatif's own ETA averages over the whole run, so the bar shows the rolling estimate stored in `eta` instead
+    pub fn create_progress_bar(video: &Video, multi_progress: &MultiProgress, eta: Arc<AtomicU64>) -> ProgressBar {
+        // a live input has no length, so only the frames done and the rates are shown
+        let (progress_bar, progress_template) = match video.frame_count {
+            0 => (ProgressBar::no_length(), "[{elapsed_precise}] {pos} frames {msg}"),
+            frame_count => (ProgressBar::new(frame_count as u64), "[{elapsed_precise}] [{eta}] [{wide_bar:.white/green}] {pos}/{len} {percent} {msg}"),
+        };
+        let progress_bar = multi_progress.add(progress_bar);
+        let file_template = format!("{} -> {}", video.input.display(), video.output.display());
         let options_template = format!(
             "[resolutin: {}x{}] [model: {}] [encoder: {}]", 
             video.width,
@@ -26,52 +219,96 @@ impl Progress {
             .template(&format!("{}\n{}\n{}", file_template, options_template, progress_template))
             .unwrap()
             .progress_chars("█▓▒░-")
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+            .with_key("eta", move |_: &ProgressState, w: &mut dyn Write| match f64::from_bits(eta.load(Ordering::Relaxed)) {
+                eta if eta.is_finite() => write!(w, "{:.1}s", eta).unwrap(),
+                _ => write!(w, "--").unwrap(),
+            })
             .with_key("percent", |state: &ProgressState, w: &mut dyn Write| write!(w, "({:.0}%)", state.fraction() * 100.0).unwrap());
         progress_bar.set_style(progress_style);
         progress_bar
     }
 
-    fn update_progress(progress_bar: &ProgressBar, position: usize, duplicates: usize, frame_rate: f64) {
-        progress_bar.set_position(position as u64);
-        progress_bar.set_message(format!("[duplicates: {}] [fps: {:.0}]", duplicates, frame_rate));
+    fn update_progress(progress_bar: &ProgressBar, upscaled: usize, duplicates: usize, rates: Rates, memory: &str) {
+        let emitted = (upscaled + duplicates) as u64;
+        progress_bar.set_position(emitted.min(progress_bar.length().unwrap_or(emitted)));
+        progress_bar.set_message(format!(
+            "[upscaled: {}] [duplicates: {}] [fps: {:.0}] [encode fps: {:.0}] {}", upscaled, duplicates, rates.fps, rates.encode_fps, memory
+        ));
+    }
+
+    fn finish_progress(progress_bar: &ProgressBar, upscaled: usize, duplicates: usize) {
+        let emitted = (upscaled + duplicates) as u64;
+        progress_bar.set_length(emitted);
+        progress_bar.set_position(emitted);
+        progress_bar.finish();
     }
 
     fn process_incoming_frames(
         receiver: Receiver<Result<Frame, Error>>,
-        sender: Sender<Result<Frame, Error>>,
+        sender: MeteredSender<Result<Frame, Error>>,
         progress_bar: ProgressBar,
+        mut reporter: LineReporter,
+        preview: Option<(Preview, String)>,
+        memory: MemoryUsage,
+        mut window: RollingRate,
     ) {
-        let start_time = Instant::now();
-        let mut duplicates = 0;
+        let (mut upscaled, mut duplicates) = (0, 0);
         loop {
             match receiver.try_recv() {
                 Ok(Ok(frame)) => {
-                    let position = frame.index + frame.duplicates;
+                    upscaled += 1;
+                    Metrics::count_frame();
                     duplicates += frame.duplicates;
+                    if let Some((preview, title)) = &preview {
+                        preview.offer(title, &frame);
+                    }
                     if sender.send(Ok(frame)).is_err() {
                         break;
                     }
-                    let total_elapsed = start_time.elapsed();
-                    let frame_rate = position as f64 / total_elapsed.as_secs_f64();
-                    Self::update_progress(&progress_bar, position, duplicates, frame_rate);
+                    let rates = window.record(upscaled, upscaled + duplicates);
+                    let memory = memory.label();
+                    Self::update_progress(&progress_bar, upscaled, duplicates, rates, &memory);
+                    reporter.report(upscaled + duplicates, rates, &memory, false);
                 }
                 Ok(Err(e)) => {
                     let _ = sender.send(Err(e));
                     break;
                 }
-                Err(TryRecvError::Disconnected) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // frame_count is an estimate (detelecine, variable frame rate), so end on what was actually emitted
+                    reporter.report(upscaled + duplicates, window.overall(upscaled), &memory.label(), true);
+                    return Self::finish_progress(&progress_bar, upscaled, duplicates);
+                },
                 Err(TryRecvError::Empty) => std::thread::yield_now(),
             }
         }
-        progress_bar.finish();
+        progress_bar.abandon();
     }
 
-    pub fn execute(video: &Video, frames_receiver: Receiver<Result<Frame, Error>>) -> Receiver<Result<Frame, Error>> {
-        let (sender, receiver) = bounded(1);
-        let progress_bar = Self::create_progress_bar(video);
-        Self::update_progress(&progress_bar, 0, 0, 0.0);
-        std::thread::spawn(move || Self::process_incoming_frames(frames_receiver, sender, progress_bar));
+    pub fn execute(
+        video: &Video,
+        frames_receiver: Receiver<Result<Frame, Error>>,
+        multi_progress: &MultiProgress,
+        mode: ProgressMode,
+        preview: Option<Preview>,
+        stats: &Stats,
+    ) -> Receiver<Result<Frame, Error>> {
+        let (sender, receiver) = stats.channel("progress", video.channel_capacity.get("progress", 1));
+        let window = RollingRate::new(video.frame_count, stats.clone());
+        let progress_bar = Self::create_progress_bar(video, multi_progress, window.eta.clone());
+        Self::update_progress(&progress_bar, 0, 0, Rates::default(), "");
+        let reporter = LineReporter {
+            mode,
+            name: video.input.display().to_string(),
+            total: video.frame_count,
+            last_report: Instant::now(),
+        };
+        let preview = preview.map(|preview| (preview, video.input.display().to_string()));
+        let memory = match mode {
+            ProgressMode::Bar | ProgressMode::Plain => MemoryUsage::watch(),
+            ProgressMode::Json | ProgressMode::None => MemoryUsage::default(),
+        };
+        std::thread::spawn(move || Self::process_incoming_frames(frames_receiver, sender, progress_bar, reporter, preview, memory, window));
         receiver
     }
 
@@ -0,0 +1,71 @@
+use crate::frame::Frame;
+use crate::logger::Logger;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use image::imageops::{self, FilterType};
+
+// Falls back to a lanczos resize when the model output is noisier than it, which catches
+// hallucinated grain and confetti since the model otherwise cleans up its input
+#[derive(Clone, Default)]
+pub struct QualityGate {
+    fallbacks: Arc<AtomicUsize>,
+}
+
+impl QualityGate {
+    const NOISE_MARGIN: f64 = 1.25;
+    const MIN_NOISE: f64 = 0.5;
+
+    fn luma(pixel: &[u8]) -> i32 {
+        (pixel[0] as i32 * 77 + pixel[1] as i32 * 150 + pixel[2] as i32 * 29) >> 8
+    }
+
+    // Immerkaer's fast noise estimate: the mean response to a Laplacian difference kernel
+    fn noise(pixels: &[u8], width: u32, height: u32) -> f64 {
+        let (width, height) = (width as usize, height as usize);
+        if width < 3 || height < 3 {
+            return 0.0;
+        }
+        let luma = pixels.chunks_exact(Frame::CHANNELS).map(Self::luma).collect::<Vec<i32>>();
+        let at = |x: usize, y: usize| luma[y * width + x];
+        let mut sum = 0u64;
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let response = at(x - 1, y - 1) - 2 * at(x, y - 1) + at(x + 1, y - 1)
+                    - 2 * at(x - 1, y) + 4 * at(x, y) - 2 * at(x + 1, y)
+                    + at(x - 1, y + 1) - 2 * at(x, y + 1) + at(x + 1, y + 1);
+                sum += response.unsigned_abs() as u64;
+            }
+        }
+        (std::f64::consts::PI / 2.0).sqrt() * sum as f64 / (6.0 * ((width - 2) * (height - 2)) as f64)
+    }
+
+    // Overwrites the model output in place when it falls back
+    pub fn check(&self, frame: &Frame, upscaled: &mut [u8], width: u32, height: u32) {
+        let baseline = imageops::resize(&frame.image(), width, height, FilterType::Lanczos3);
+        let model_noise = Self::noise(upscaled, width, height);
+        let baseline_noise = Self::noise(&baseline, width, height);
+        if model_noise <= baseline_noise.max(Self::MIN_NOISE) * Self::NOISE_MARGIN {
+            return;
+        }
+
+        Logger::debug(format!(
+            "Frame {} failed the quality gate (noise {:.2} against {:.2} for lanczos), using lanczos", frame.index, model_noise, baseline_noise
+        ));
+        self.fallbacks.fetch_add(1, Ordering::Relaxed);
+        upscaled.copy_from_slice(&baseline);
+    }
+
+    pub fn summary(&self) -> Option<String> {
+        let fallbacks = self.fallbacks.load(Ordering::Relaxed);
+        (fallbacks > 0).then(|| format!("{} frames used lanczos instead of the model", fallbacks))
+    }
+
+    pub fn report(&self, input: &str) {
+        let fallbacks = self.fallbacks.load(Ordering::Relaxed);
+        if fallbacks > 0 {
+            Logger::info(format!("Quality gate used lanczos instead of the model for {} frames of {}", fallbacks, input));
+        }
+    }
+}
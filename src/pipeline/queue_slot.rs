@@ -0,0 +1,60 @@
+use crate::error::Error;
+use crate::logger::Logger;
+use crate::cancel::Cancel;
+
+use std::env;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+// A machine-wide GPU slot shared by every running upscaler, held as an exclusive lock on one of N files in the
+// temp directory; the operating system releases it when the process exits, even if it crashes
+pub struct QueueSlot {
+    _file: File,
+}
+
+impl QueueSlot {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    fn path(slot: usize) -> PathBuf {
+        env::temp_dir().join(format!("simple-video-upscaler-gpu-{}.lock", slot))
+    }
+
+    fn try_lock(slot: usize) -> Result<Option<File>, Error> {
+        let path = Self::path(slot);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| Error::new(format!("Failed to open queue slot {}: {}", path.display(), e)))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(file)),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(Error::new(format!("Failed to lock queue slot {}: {}", path.display(), e))),
+        }
+    }
+
+    pub fn acquire(slots: usize, input: &str) -> Result<Self, Error> {
+        let mut waiting = false;
+        loop {
+            for slot in 0..slots {
+                if let Some(file) = Self::try_lock(slot)? {
+                    if waiting {
+                        Logger::info(format!("Got GPU slot {} for {}", slot + 1, input));
+                    }
+                    return Ok(Self { _file: file });
+                }
+            }
+            if Cancel::requested() {
+                return Err(Cancel::error());
+            }
+            if !waiting {
+                Logger::info(format!("All {} GPU slots are taken by other upscalers, {} is queued", slots, input));
+                waiting = true;
+            }
+            thread::sleep(Self::POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::error::Error;
+use crate::video::Video;
+use crate::logger::{Capture, Logger};
+use crate::binary::Binary;
+use crate::capabilities::Capabilities;
+
+use serde_json::json;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+#[derive(Default)]
+struct Metrics {
+    psnr: Option<f64>,
+    ssim: Option<f64>,
+    vmaf: Option<f64>,
+}
+
+// Full-reference metrics of the output against a master of the same content, written next to the output as JSON
+pub struct Reference;
+
+impl Reference {
+    const REPORT_SUFFIX: &'static str = ".metrics.json";
+
+    pub fn report_path(video: &Video) -> PathBuf {
+        let mut path = OsString::from(video.output.as_os_str());
+        path.push(Self::REPORT_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    // The reference is scaled to the output size, and both sides are compared from their first frame in one pixel format
+    fn get_filter(video: &Video, vmaf: bool) -> String {
+        let count = if vmaf { 3 } else { 2 };
+        let labels = |prefix: &str| (0..count).map(|index| format!("[{}{}]", prefix, index)).collect::<String>();
+        let normalize = format!("format={},setpts=PTS-STARTPTS", video.pixel_format);
+        let mut filter = format!(
+            "[0:v:0]{normalize},split={count}{distorted};[1:v:0]scale={}:{}:flags=lanczos,{normalize},split={count}{reference};[d0][r0]psnr;[d1][r1]ssim",
+            video.width, video.height, normalize = normalize, count = count, distorted = labels("d"), reference = labels("r")
+        );
+        if vmaf {
+            filter.push_str(";[d2][r2]libvmaf");
+        }
+        filter
+    }
+
+    fn parse_value(line: &str, key: &str) -> Option<f64> {
+        line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+    }
+
+    fn parse_line(metrics: &mut Metrics, line: &str) -> bool {
+        if line.contains("Parsed_psnr") {
+            metrics.psnr = metrics.psnr.or(Self::parse_value(line, "average:"));
+        } else if line.contains("Parsed_ssim") {
+            metrics.ssim = metrics.ssim.or(Self::parse_value(line, "All:"));
+        } else if line.contains("Parsed_libvmaf") {
+            metrics.vmaf = metrics.vmaf.or(Self::parse_value(line, "VMAF score:"));
+        } else {
+            Logger::debug(format!("[reference] {}", line));
+        }
+        true
+    }
+
+    fn measure(video: &Video, reference: &std::path::Path, vmaf: bool) -> Result<Metrics, Error> {
+        let mut child = Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-nostats"])
+            .arg("-i").arg(Binary::path_arg(&video.get_partial_output()))
+            .args(video.get_seek_args())
+            .arg("-i").arg(Binary::path_arg(reference))
+            .args(["-filter_complex", &Self::get_filter(video, vmaf), "-f", "null", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::new(format!("Failed to spawn ffmpeg process: {}", e)))?;
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let capture = Capture::spawn(child.stderr.take(), move |line| {
+            let mut metrics = Metrics::default();
+            Self::parse_line(&mut metrics, line);
+            let _ = sender.send(metrics);
+            true
+        });
+        let status = child.wait().map_err(|e| Error::new(format!("Failed to wait for ffmpeg process: {}", e)))?;
+        if !status.success() {
+            return Err(capture.error(format!("ffmpeg failed to compare {} with {} ({})", video.output.display(), reference.display(), status)));
+        }
+        drop(capture);
+        Ok(receiver.into_iter().fold(Metrics::default(), |metrics, line| Metrics {
+            psnr: metrics.psnr.or(line.psnr),
+            ssim: metrics.ssim.or(line.ssim),
+            vmaf: metrics.vmaf.or(line.vmaf),
+        }))
+    }
+
+    pub fn execute(video: &Video) -> Result<(), Error> {
+        let Some(reference) = video.reference else {
+            return Ok(());
+        };
+        Logger::info(format!("Comparing {} with {}", video.output.display(), reference.display()));
+        let vmaf = Capabilities::get().has_filter("libvmaf");
+        if !vmaf {
+            Logger::warning("This ffmpeg has no libvmaf filter, only PSNR and SSIM are measured");
+        }
+        let metrics = Self::measure(video, reference, vmaf)?;
+
+        let format = |value: Option<f64>, precision: usize| value.map_or(String::from("-"), |value| format!("{:.*}", precision, value));
+        Logger::info(format!(
+            "{}: PSNR {} dB, SSIM {}, VMAF {}", video.output.display(), format(metrics.psnr, 2), format(metrics.ssim, 4), format(metrics.vmaf, 2)
+        ));
+
+        let path = Self::report_path(video);
+        let report = json!({
+            "output": video.output.display().to_string(),
+            "reference": reference.display().to_string(),
+            "psnr": metrics.psnr,
+            "ssim": metrics.ssim,
+            "vmaf": metrics.vmaf,
+        });
+        let content = serde_json::to_string_pretty(&report)
+            .map_err(|e| Error::new(format!("Failed to serialize metrics: {}", e)))?;
+        fs::write(&path, content).map_err(|e| Error::new(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
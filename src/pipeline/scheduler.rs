@@ -0,0 +1,39 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Clone)]
+pub struct Scheduler {
+    slots: Arc<(Mutex<usize>, Condvar)>,
+}
+
+pub struct SchedulerSlot {
+    scheduler: Scheduler,
+}
+
+impl Scheduler {
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: Arc::new((Mutex::new(slots), Condvar::new())),
+        }
+    }
+
+    pub fn acquire(&self) -> SchedulerSlot {
+        let (available, condvar) = &*self.slots;
+        let mut available = condvar
+            .wait_while(available.lock().unwrap(), |available| *available == 0)
+            .unwrap();
+        *available -= 1;
+        SchedulerSlot { scheduler: self.clone() }
+    }
+
+    fn release(&self) {
+        let (available, condvar) = &*self.slots;
+        *available.lock().unwrap() += 1;
+        condvar.notify_one();
+    }
+}
+
+impl Drop for SchedulerSlot {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Error;
+use crate::frame::Frame;
+use crate::video::Video;
+use crate::screenshots::Screenshots;
+use super::stats::{MeteredSender, Stats};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crossbeam_channel::Receiver;
+
+#[derive(Clone, Copy)]
+pub enum Still {
+    Original,
+    Upscaled,
+}
+
+impl Still {
+    fn name(&self) -> &'static str {
+        match self {
+            Still::Original => "original",
+            Still::Upscaled => "upscaled",
+        }
+    }
+}
+
+pub struct Screenshot;
+
+impl Screenshot {
+    const STAGE: &'static str = "screenshots";
+
+    fn write_still(frame: &Frame, directory: &Path, still: Still) -> Result<(), Error> {
+        let path = directory.join(Screenshots::file_name(frame.pts, still.name()));
+        frame.image()
+            .save(&path)
+            .map_err(|e| Error::new(format!("Failed to write screenshot {}: {}", path.display(), e)))
+    }
+
+    // Both stills are picked by the same pts rule on the same frame sequence, so they pair up
+    fn process_frames(
+        receiver: Receiver<Result<Frame, Error>>,
+        sender: MeteredSender<Result<Frame, Error>>,
+        directory: PathBuf,
+        every: f64,
+        still: Still,
+        stats: Stats,
+    ) {
+        let mut next_pts = 0.0;
+        while let Ok(frame) = receiver.recv() {
+            let frame = frame.and_then(|frame| {
+                if frame.pts < next_pts {
+                    return Ok(frame);
+                }
+                while next_pts <= frame.pts {
+                    next_pts += every;
+                }
+                stats.time(Self::STAGE, || Self::write_still(&frame, &directory, still)).map(|_| frame)
+            });
+            let failed = frame.is_err();
+            if sender.send(frame).is_err() || failed {
+                break;
+            }
+        }
+    }
+
+    pub fn execute(
+        video: &Video,
+        frames_receiver: Receiver<Result<Frame, Error>>,
+        still: Still,
+        stats: &Stats,
+    ) -> Result<Receiver<Result<Frame, Error>>, Error> {
+        let (Some(screenshots), Some(directory)) = (video.screenshots, video.get_screenshots_directory()) else {
+            return Ok(frames_receiver);
+        };
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create screenshots directory {}: {}", directory.display(), e)))?;
+
+        let channel = format!("{} {}", Self::STAGE, still.name());
+        let (sender, receiver) = stats.channel(&channel, video.channel_capacity.get(Self::STAGE, 1));
+        let (every, stats) = (screenshots.every, stats.clone());
+        thread::spawn(move || Self::process_frames(frames_receiver, sender, directory, every, still, stats));
+        Ok(receiver)
+    }
+}
@@ -0,0 +1,102 @@
+use crate::frame::Frame;
+use crate::error::Error;
+use crate::video::Video;
+use crate::logger::Logger;
+use super::filter_duplicates::FilterDuplicates;
+use super::stats::{MeteredSender, Stats};
+
+use crossbeam_channel::Receiver;
+use std::thread;
+
+// Marks runs of black or unchanging frames (credits over black, title cards) so upscaling
+// scales them with lanczos instead of running the model; short runs are left alone
+pub struct StaticSegments;
+
+impl StaticSegments {
+    const STAGE: &'static str = "static segments";
+    const MIN_SECONDS: f64 = 2.0;
+    const BLACK_LEVEL: u32 = 24;
+    const STATIC_SIMILARITY: f64 = 0.995;
+
+    fn is_black(thumbnail: &[u32]) -> bool {
+        thumbnail.iter().all(|&luma| luma < Self::BLACK_LEVEL)
+    }
+
+    fn continues_run(previous: &[u32], thumbnail: &[u32]) -> bool {
+        if Self::is_black(previous) && Self::is_black(thumbnail) {
+            return true;
+        }
+        let difference = previous.iter().zip(thumbnail).map(|(&a, &b)| a.abs_diff(b) as f64).sum::<f64>();
+        1.0 - difference / (thumbnail.len() as f64 * 255.0) >= Self::STATIC_SIMILARITY
+    }
+
+    fn send_run(run: &mut Vec<Frame>, sender: &MeteredSender<Result<Frame, Error>>) -> bool {
+        run.drain(..).all(|frame| sender.send(Ok(frame)).is_ok())
+    }
+
+    // Frames are held back only until the run is long enough, after that they pass through marked
+    fn process_frames(receiver: Receiver<Result<Frame, Error>>, sender: MeteredSender<Result<Frame, Error>>, input: String, stats: Stats) {
+        let mut run: Vec<Frame> = Vec::new();
+        let mut run_seconds = 0.0;
+        let mut marking = false;
+        let mut previous: Option<Vec<u32>> = None;
+        let mut marked = 0;
+
+        while let Ok(frame) = receiver.recv() {
+            let mut frame = match frame {
+                Ok(frame) => frame,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return;
+                },
+            };
+            stats.sample_queue(Self::STAGE, receiver.len());
+            let thumbnail = stats.time(Self::STAGE, || FilterDuplicates::thumbnail(&frame));
+            let continues = previous.as_deref().is_some_and(|previous| Self::continues_run(previous, &thumbnail));
+            previous = Some(thumbnail);
+
+            if !continues {
+                marking = false;
+                run_seconds = 0.0;
+                if !Self::send_run(&mut run, &sender) {
+                    return;
+                }
+            }
+            run_seconds += frame.duration;
+            if marking {
+                frame.static_segment = true;
+                marked += 1 + frame.duplicates;
+                if sender.send(Ok(frame)).is_err() {
+                    return;
+                }
+                continue;
+            }
+
+            run.push(frame);
+            if run_seconds >= Self::MIN_SECONDS {
+                marking = true;
+                for frame in &mut run {
+                    frame.static_segment = true;
+                    marked += 1 + frame.duplicates;
+                }
+                if !Self::send_run(&mut run, &sender) {
+                    return;
+                }
+            }
+        }
+
+        if Self::send_run(&mut run, &sender) && marked > 0 {
+            Logger::info(format!("Scaled {} frames of black or static segments in {} with lanczos", marked, input));
+        }
+    }
+
+    pub fn execute(video: &Video, receiver: Receiver<Result<Frame, Error>>, stats: &Stats) -> Receiver<Result<Frame, Error>> {
+        if !video.skip_static_segments {
+            return receiver;
+        }
+        let (sender, static_receiver) = stats.channel("static-segments", video.channel_capacity.get("static-segments", 1));
+        let (input, stats) = (video.input.display().to_string(), stats.clone());
+        thread::spawn(move || Self::process_frames(receiver, sender, input, stats));
+        static_receiver
+    }
+}
@@ -0,0 +1,188 @@
+use crate::logger::Logger;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, Receiver, SendError, Sender, TrySendError};
+
+#[derive(Default)]
+struct StageStats {
+    busy: Duration,
+    frames: usize,
+    queued: usize,
+    samples: usize,
+}
+
+struct ChannelStats {
+    name: String,
+    capacity: usize,
+    sends: AtomicUsize,
+    blocked: AtomicUsize,
+    occupied: AtomicUsize,
+}
+
+impl ChannelStats {
+    fn report(&self) -> String {
+        let sends = self.sends.load(Ordering::Relaxed);
+        let blocked = self.blocked.load(Ordering::Relaxed);
+        let occupancy = self.occupied.load(Ordering::Relaxed) as f64 / sends.max(1) as f64;
+        format!(
+            "  {:<20} {:>3.0}% full ({:.1}/{})  {:>7} sends  {:>7} blocked ({:.0}%)",
+            self.name, occupancy / self.capacity as f64 * 100.0, occupancy, self.capacity,
+            sends, blocked, blocked as f64 / sends.max(1) as f64 * 100.0
+        )
+    }
+}
+
+// A bounded sender that counts how full its channel is and how often a send had to wait
+pub struct MeteredSender<T> {
+    sender: Sender<T>,
+    channel: Arc<ChannelStats>,
+}
+
+impl<T> Clone for MeteredSender<T> {
+    fn clone(&self) -> Self {
+        Self { sender: self.sender.clone(), channel: self.channel.clone() }
+    }
+}
+
+impl<T> MeteredSender<T> {
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self.sender.try_send(value) {
+            Ok(()) => (),
+            Err(TrySendError::Full(value)) => {
+                self.channel.blocked.fetch_add(1, Ordering::Relaxed);
+                self.sender.send(value)?;
+            },
+            Err(TrySendError::Disconnected(value)) => return Err(SendError(value)),
+        }
+        self.channel.sends.fetch_add(1, Ordering::Relaxed);
+        self.channel.occupied.fetch_add(self.sender.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sender.is_full()
+    }
+}
+
+#[derive(Clone)]
+pub struct Stats {
+    started: Instant,
+    stages: Arc<Mutex<Vec<(String, StageStats)>>>,
+    channels: Arc<Mutex<Vec<Arc<ChannelStats>>>>,
+    encoded: Arc<AtomicUsize>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            stages: Arc::new(Mutex::new(Vec::new())),
+            channels: Arc::new(Mutex::new(Vec::new())),
+            encoded: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // Frames handed to the encoder, counted by merge so progress can show the encode rate
+    pub fn count_encoded(&self) {
+        self.encoded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn encoded(&self) -> usize {
+        self.encoded.load(Ordering::Relaxed)
+    }
+
+    pub fn channel<T>(&self, name: &str, capacity: usize) -> (MeteredSender<T>, Receiver<T>) {
+        let (sender, receiver) = bounded(capacity);
+        let channel = Arc::new(ChannelStats {
+            name: name.to_string(),
+            capacity,
+            sends: AtomicUsize::new(0),
+            blocked: AtomicUsize::new(0),
+            occupied: AtomicUsize::new(0),
+        });
+        self.channels.lock().unwrap().push(channel.clone());
+        (MeteredSender { sender, channel }, receiver)
+    }
+
+    fn report_channels(channels: &[Arc<ChannelStats>]) {
+        for channel in channels {
+            Logger::info(channel.report());
+        }
+    }
+
+    // Stops once every clone of these stats is dropped at the end of the file
+    pub fn watch(&self, name: String, interval: Duration) {
+        let channels = Arc::downgrade(&self.channels);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let Some(channels) = Weak::upgrade(&channels) else {
+                break
+            };
+            Logger::info(format!("Channels for {}:", name));
+            Self::report_channels(&channels.lock().unwrap());
+        });
+    }
+
+    fn update(&self, stage: &str, update: impl FnOnce(&mut StageStats)) {
+        let mut stages = self.stages.lock().unwrap();
+        match stages.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, stats)) => update(stats),
+            None => {
+                let mut stats = StageStats::default();
+                update(&mut stats);
+                stages.push((stage.to_string(), stats));
+            }
+        }
+    }
+
+    pub fn time<T>(&self, stage: &str, task: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = task();
+        let busy = started.elapsed();
+        self.update(stage, |stats| {
+            stats.busy += busy;
+            stats.frames += 1;
+        });
+        result
+    }
+
+    pub fn sample_queue(&self, stage: &str, queued: usize) {
+        self.update(stage, |stats| {
+            stats.queued += queued;
+            stats.samples += 1;
+        });
+    }
+
+    pub fn report(&self, name: &str) {
+        let elapsed = self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let stages = self.stages.lock().unwrap();
+        Logger::info(format!("Timing for {} ({:.1}s):", name, elapsed));
+
+        for (stage, stats) in stages.iter() {
+            let busy = stats.busy.as_secs_f64();
+            let per_frame = busy * 1000.0 / stats.frames.max(1) as f64;
+            let queue = match stats.samples {
+                0 => String::from("-"),
+                samples => format!("{:.1}", stats.queued as f64 / samples as f64),
+            };
+            Logger::info(format!(
+                "  {:<20} {:>8.1}s busy ({:>3.0}%)  {:>7} frames  {:>8.1}ms/frame  queue {}",
+                stage, busy, busy / elapsed * 100.0, stats.frames, per_frame, queue
+            ));
+        }
+
+        if let Some((stage, _)) = stages.iter().max_by(|(_, a), (_, b)| a.busy.cmp(&b.busy)) {
+            Logger::info(format!("  Bottleneck: {}", stage));
+        }
+
+        let channels = self.channels.lock().unwrap();
+        if !channels.is_empty() {
+            Logger::info("Channels:");
+            Self::report_channels(&channels);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::rate_limit::RateLimit;
+
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Throttle {
+    limit: u8,
+    rate_limit: Option<RateLimit>,
+}
+
+impl Throttle {
+    pub fn new(limit: u8, rate_limit: Option<RateLimit>) -> Option<Self> {
+        (limit < 100 || rate_limit.is_some()).then_some(Self { limit, rate_limit })
+    }
+
+    // CPU workers share the frame rate limit but not the GPU utilization target
+    pub fn for_cpu(&self) -> Option<Self> {
+        Self::new(100, self.rate_limit.clone())
+    }
+
+    pub fn wait(&self) {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.acquire();
+        }
+    }
+
+    pub fn pause(&self, busy: Duration) {
+        if self.limit < 100 {
+            let idle_ratio = (100 - self.limit) as f64 / self.limit as f64;
+            thread::sleep(busy.mul_f64(idle_ratio));
+        }
+    }
+}
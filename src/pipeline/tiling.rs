@@ -0,0 +1,121 @@
+use crate::error::Error;
+use crate::frame::Frame;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tiler {
+    tile_size: u32,
+    overlap: u32,
+}
+
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Tiler {
+    pub const MIN_TILE_SIZE: u32 = 64;
+    const OVERLAP: u32 = 16;
+
+    pub fn new(tile_size: u32) -> Self {
+        let tile_size = tile_size.max(Self::MIN_TILE_SIZE);
+        Self {
+            tile_size,
+            overlap: Self::OVERLAP.min(tile_size / 4),
+        }
+    }
+
+    pub fn get_tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    fn positions(&self, length: u32) -> Vec<u32> {
+        if length <= self.tile_size {
+            return vec![0];
+        }
+
+        let step = self.tile_size - self.overlap;
+        let mut positions = (0..)
+            .map(|index| index * step)
+            .take_while(|&position| position + self.tile_size < length)
+            .collect::<Vec<u32>>();
+        positions.push(length - self.tile_size);
+        positions
+    }
+
+    fn tiles(&self, width: u32, height: u32) -> Vec<Tile> {
+        let columns = self.positions(width);
+        self.positions(height)
+            .into_iter()
+            .flat_map(|y| columns.iter().map(move |&x| Tile {
+                x,
+                y,
+                width: self.tile_size.min(width),
+                height: self.tile_size.min(height),
+            }))
+            .collect()
+    }
+
+    fn copy_tile(pixels: &[u8], width: u32, tile: &Tile) -> Vec<u8> {
+        let row_size = tile.width as usize * Frame::CHANNELS;
+        let mut tile_pixels = Vec::with_capacity(row_size * tile.height as usize);
+        for row in tile.y..tile.y + tile.height {
+            let start = (row as usize * width as usize + tile.x as usize) * Frame::CHANNELS;
+            tile_pixels.extend_from_slice(&pixels[start..start + row_size]);
+        }
+        tile_pixels
+    }
+
+    fn feather(&self, position: u32, length: u32, has_before: bool, has_after: bool, scale: u32) -> f32 {
+        let ramp = (self.overlap * scale).max(1) as f32;
+        let before = if has_before { ((position as f32 + 0.5) / ramp).min(1.0) } else { 1.0 };
+        let after = if has_after { ((length - position) as f32 - 0.5) / ramp } else { 1.0 };
+        before.min(after.min(1.0))
+    }
+
+    // Every tile has the same size, so one buffer takes the upscaled pixels of each in turn
+    pub fn upscale<F>(&self, pixels: &[u8], width: u32, height: u32, scale: u32, output: &mut [u8], upscale: F) -> Result<(), Error>
+    where
+        F: Fn(&[u8], usize, usize, &mut [u8]) -> Result<(), Error>,
+    {
+        let output_width = (width * scale) as usize;
+        let output_height = (height * scale) as usize;
+        if output.len() != output_width * output_height * Frame::CHANNELS {
+            return Err(Error::new(format!("Tiled upscale got an output buffer of unexpected size: {}", output.len())));
+        }
+        let mut sums = vec![0f32; output.len()];
+        let mut weights = vec![0f32; output_width * output_height];
+        let (tile_width, tile_height) = (self.tile_size.min(width) * scale, self.tile_size.min(height) * scale);
+        let mut upscaled = vec![0u8; Frame::size(tile_width, tile_height)];
+
+        for tile in self.tiles(width, height) {
+            let tile_pixels = Self::copy_tile(pixels, width, &tile);
+            upscale(&tile_pixels, tile.width as usize, tile.height as usize, &mut upscaled)?;
+
+            let (has_left, has_right) = (tile.x > 0, tile.x + tile.width < width);
+            let (has_top, has_bottom) = (tile.y > 0, tile.y + tile.height < height);
+            for ty in 0..tile_height {
+                let weight_y = self.feather(ty, tile_height, has_top, has_bottom, scale);
+                let output_y = (tile.y * scale + ty) as usize;
+                for tx in 0..tile_width {
+                    let weight = weight_y * self.feather(tx, tile_width, has_left, has_right, scale);
+                    let output_index = output_y * output_width + (tile.x * scale + tx) as usize;
+                    let tile_index = (ty * tile_width + tx) as usize * Frame::CHANNELS;
+                    weights[output_index] += weight;
+                    for channel in 0..Frame::CHANNELS {
+                        sums[output_index * Frame::CHANNELS + channel] += upscaled[tile_index + channel] as f32 * weight;
+                    }
+                }
+            }
+        }
+
+        for (index, weight) in weights.into_iter().enumerate() {
+            for channel in 0..Frame::CHANNELS {
+                let position = index * Frame::CHANNELS + channel;
+                output[position] = (sums[position] / weight).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,53 +1,310 @@
+use crate::buffer::{Buffer, BufferPool};
 use crate::frame::Frame;
 use crate::error::Error;
 use crate::video::Video;
 use crate::model::Model;
+use crate::gpu::Gpu;
+use crate::plugin::PluginUpscaler;
+use crate::external_upscaler::ExternalUpscaler;
+use crate::logger::Logger;
+use crate::roi::Roi;
+use crate::backend::Backend;
+#[cfg(all(feature = "coreml", target_os = "macos"))]
+use crate::coreml::CoreMlUpscaler;
+use super::scheduler::Scheduler;
+use super::tiling::Tiler;
+use super::stats::{MeteredSender, Stats};
+use super::cache::FrameCache;
+use super::throttle::Throttle;
+use super::frame_errors::FrameErrors;
+use super::quality_gate::QualityGate;
+use super::grain_layer::GrainLayer;
+use crate::grain::Grain;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 use std::collections::BTreeMap;
 
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::Receiver;
+use image::RgbImage;
+use image::imageops::{self, FilterType};
+#[cfg(feature = "ncnn")]
 use realcugan_rs::{RealCugan, Options as RealCuganOptions, OptionsModel as RealCuganOptionsModel};
+#[cfg(feature = "ncnn")]
 use realesrgan_rs::{RealEsrgan, Options as RealEsrganOptions, OptionsModel as RealEsrganOptionsModel};
 
+// Upscalers write into the output buffer they are given, which is the pooled buffer of the frame on the last pass
 trait Upscaler: Sync + Send {
-    fn upscale(&self, input: &[u8], width: usize, height: usize) -> Result<Vec<u8>, Error>;
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error>;
 }
 
+// The ncnn bindings only return a Vec of their own, so their pixels are copied into the output
+#[cfg(feature = "ncnn")]
+fn copy_output(pixels: Vec<u8>, output: &mut [u8]) -> Result<(), Error> {
+    if pixels.len() != output.len() {
+        return Err(Error::new(format!("Upscaled frame has unexpected size: {}", pixels.len())));
+    }
+    output.copy_from_slice(&pixels);
+    Ok(())
+}
+
+#[cfg(feature = "ncnn")]
 impl Upscaler for RealCugan {
-    fn upscale(&self, input: &[u8], width: usize, height: usize) -> Result<Vec<u8>, Error> {
-        self.process(input, width, height).map_err(|e| Error::new(format!("RealCugan upscale failed: {}", e)))
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        let pixels = self.process(input, width, height).map_err(|e| Error::new(format!("RealCugan upscale failed: {}", e)))?;
+        copy_output(pixels, output)
     }
 }
 
+#[cfg(feature = "ncnn")]
 impl Upscaler for RealEsrgan {
-    fn upscale(&self, input: &[u8], width: usize, height: usize) -> Result<Vec<u8>, Error> {
-        self.process(input, width, height).map_err(|e| Error::new(format!("RealEsrgan upscale failed: {}", e)))
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        let pixels = self.process(input, width, height).map_err(|e| Error::new(format!("RealEsrgan upscale failed: {}", e)))?;
+        copy_output(pixels, output)
+    }
+}
+
+impl Upscaler for ExternalUpscaler {
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        self.process(input, width, height, output)
+    }
+}
+
+impl Upscaler for PluginUpscaler {
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        self.process(input, width, height, output)
+    }
+}
+
+#[cfg(all(feature = "coreml", target_os = "macos"))]
+impl Upscaler for CoreMlUpscaler {
+    fn upscale(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        self.process(input, width, height, output)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Device {
+    Gpu,
+    Cpu,
+}
+
+impl Device {
+    fn name(&self) -> &'static str {
+        match self {
+            Device::Gpu => "gpu",
+            Device::Cpu => "cpu",
+        }
+    }
+
+    fn id(&self) -> i32 {
+        match self {
+            Device::Gpu => 0,
+            Device::Cpu => -1,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Throughput {
+    frame_seconds: Arc<Mutex<[Option<f64>; 2]>>,
+}
+
+impl Throughput {
+    const SMOOTHING: f64 = 0.2;
+
+    fn record(&self, device: Device, seconds: f64) {
+        let mut frame_seconds = self.frame_seconds.lock().unwrap();
+        let average = &mut frame_seconds[device as usize];
+        *average = Some(average.map_or(seconds, |average| average + (seconds - average) * Self::SMOOTHING));
+    }
+
+    fn cpu_share(&self, gpu_workers: usize, cpu_workers: usize) -> Option<f64> {
+        let frame_seconds = self.frame_seconds.lock().unwrap();
+        let gpu = gpu_workers as f64 / frame_seconds[Device::Gpu as usize]?;
+        let cpu = cpu_workers as f64 / frame_seconds[Device::Cpu as usize]?;
+        Some(cpu / (gpu + cpu))
+    }
+}
+
+// Loaded models shared across the files of a batch, least recently used first
+static UPSCALERS: Mutex<Vec<CachedUpscaler>> = Mutex::new(Vec::new());
+
+struct CachedUpscaler {
+    key: String,
+    name: String,
+    upscaler: Arc<dyn Upscaler>,
+}
+
+#[derive(Clone)]
+struct ChainedUpscaler {
+    upscaler: Arc<dyn Upscaler>,
+    scale: u8,
+    tiler: Option<Tiler>,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Clone)]
+struct UpscaleContext {
+    upscaler: Arc<dyn Upscaler>,
+    scale: u8,
+    tiler: Option<Tiler>,
+    chain: Vec<ChainedUpscaler>,
+    // the cheaper model and its scale for low-detail frames
+    adaptive: Option<(Arc<dyn Upscaler>, u8)>,
+    roi: Option<Roi>,
+    device: Device,
+    scheduler: Option<Scheduler>,
+    throttle: Option<Throttle>,
+    throughput: Throughput,
+    stats: Stats,
+    cache: Option<FrameCache>,
+    frame_errors: FrameErrors,
+    quality_gate: Option<QualityGate>,
+    preserve_grain: bool,
+    // upscaled frames go back to it when merge is done with them
+    pool: BufferPool,
+}
+
+// A failed frame keeps its input frame for the timing of what replaces it
+enum Processed {
+    Upscaled(Frame),
+    Failed(Frame),
+}
+
+impl Processed {
+    fn frame(&self) -> &Frame {
+        match self {
+            Processed::Upscaled(frame) | Processed::Failed(frame) => frame,
+        }
+    }
+}
+
+// With repeat-previous the last upscaled frame is held back until the next one is in, so a failed frame can take
+// its pixels and good frames are never copied
+struct Reorder {
+    pending: BTreeMap<usize, Processed>,
+    held: Option<Frame>,
+    sender: MeteredSender<Result<Frame, Error>>,
+}
+
+impl Reorder {
+    fn new(sender: MeteredSender<Result<Frame, Error>>) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            held: None,
+            sender,
+        }
     }
 }
 
+// The last worker to finish drops the reorder buffer and sends the frame still held
+impl Drop for Reorder {
+    fn drop(&mut self) {
+        if let Some(frame) = self.held.take() {
+            let _ = self.sender.send(Ok(frame));
+        }
+    }
+}
+
+pub struct Preflight {
+    upscaler: Arc<dyn Upscaler>,
+    tiler: Option<Tiler>,
+    scheduler: Scheduler,
+}
+
 pub struct Upscale;
 
 impl Upscale {
-    const MAX_JOBS: usize = 4;
+    pub const MAX_JOBS: usize = 4;
+    pub const BACKGROUND_JOBS: usize = 1;
+    const FALLBACK_TILE_SIZE: u32 = 256;
+    const MEMORY_PER_OUTPUT_PIXEL: u64 = 1024;
+    const MEMORY_BUDGET_RATIO: f64 = 0.8;
+    const PREFLIGHT_FILL: u8 = 128;
+    const POOL_SIZE: usize = 16;
+    // Enough for a GPU and a CPU instance of a two-pass chain
+    const CACHED_UPSCALERS: usize = 4;
+
+    #[cfg(all(feature = "coreml", target_os = "macos"))]
+    fn init_coreml(model: &Model) -> Result<Arc<dyn Upscaler>, Error> {
+        CoreMlUpscaler::new(model).map(|r| Arc::new(r) as _)
+    }
+
+    #[cfg(not(all(feature = "coreml", target_os = "macos")))]
+    fn init_coreml(model: &Model) -> Result<Arc<dyn Upscaler>, Error> {
+        Err(Error::new(format!("Cannot run {} with CoreML: this build has no CoreML backend", model)))
+    }
+
+    fn cached_upscaler(upscalers: &mut Vec<CachedUpscaler>, key: &str) -> Option<Arc<dyn Upscaler>> {
+        let position = upscalers.iter().position(|cached| cached.key == key)?;
+        let cached = upscalers.remove(position);
+        let upscaler = cached.upscaler.clone();
+        upscalers.push(cached);
+        Some(upscaler)
+    }
+
+    // Loading weights and setting up Vulkan is repeated for nothing when the next file of a batch uses the same model,
+    // so the most recently used instances are kept until no running or pending file needs them. Workers of several
+    // files can share one, as the workers of a single file already do
+    fn init_upscaler(model: &Model, device: Device, backend: Backend) -> Result<Arc<dyn Upscaler>, Error> {
+        let key = format!("{:?}|{}|{}", model, device.name(), backend);
+        if let Some(upscaler) = Self::cached_upscaler(&mut UPSCALERS.lock().unwrap(), &key) {
+            Logger::debug(format!("Reusing the {} upscaler on {}", model, device.name()));
+            return Ok(upscaler);
+        }
+        // loaded without the lock, so workers of other files are not held up; two workers loading the same model at
+        // once both load it and the first one stays cached
+        let upscaler = Self::create_upscaler(model, device, backend)?;
+        let mut upscalers = UPSCALERS.lock().unwrap();
+        if let Some(cached) = Self::cached_upscaler(&mut upscalers, &key) {
+            return Ok(cached);
+        }
+        let evicted = (upscalers.len() == Self::CACHED_UPSCALERS).then(|| upscalers.remove(0));
+        upscalers.push(CachedUpscaler { key, name: model.name(), upscaler: upscaler.clone() });
+        drop(upscalers);
+        drop(evicted);
+        Ok(upscaler)
+    }
+
+    // Drops the cached models no running file holds and `needed` does not ask for, which frees their memory and
+    // closes the processes of external upscalers
+    pub fn release_upscalers(needed: impl Fn(&str) -> bool) {
+        let released = {
+            let mut upscalers = UPSCALERS.lock().unwrap();
+            let (kept, released): (Vec<_>, Vec<_>) = std::mem::take(&mut *upscalers)
+                .into_iter()
+                .partition(|cached| Arc::strong_count(&cached.upscaler) > 1 || needed(&cached.name));
+            *upscalers = kept;
+            released
+        };
+        for cached in &released {
+            Logger::debug(format!("Releasing the {} upscaler", cached.name));
+        }
+    }
 
-    fn init_upscaler(model: &Model) -> Result<Arc<dyn Upscaler>, Error> {
+    // ncnn runs on whichever device it is given, so CPU workers and the CPU backend use it too
+    #[cfg(feature = "ncnn")]
+    fn init_ncnn(model: &Model, device: Device) -> Result<Arc<dyn Upscaler>, Error> {
         match model {
-            Model::RealCugan(scale) => {
-                let options = RealCuganOptions::default().model(match scale {
-                    2 => RealCuganOptionsModel::Se2xConservative,
-                    3 => RealCuganOptionsModel::Se3xConservative,
-                    4 => RealCuganOptionsModel::Se4xConservative,
-                    _ => return Err(Error::new(format!("Unsupported scale {} for RealCugan", scale))),
+            Model::RealCugan { scale, pro, syncgap } => {
+                let options = RealCuganOptions::default().gpu(device.id()).syncgap(*syncgap as u32).model(match (scale, pro) {
+                    (2, false) => RealCuganOptionsModel::Se2xConservative,
+                    (3, false) => RealCuganOptionsModel::Se3xConservative,
+                    (4, false) => RealCuganOptionsModel::Se4xConservative,
+                    (2, true) => RealCuganOptionsModel::Pro2xConservative,
+                    (3, true) => RealCuganOptionsModel::Pro3xConservative,
+                    _ => return Err(Error::new(format!("Unsupported scale {} for {}", scale, model))),
                 });
                 RealCugan::new(options)
                     .map_err(|e| Error::new(format!("Failed to initialize RealCugan upscaler: {}", e)))
                     .map(|r| Arc::new(r) as _)
             },
             Model::RealEsrAnime(scale) => {
-                let options = RealEsrganOptions::default().model(match scale {
+                let options = RealEsrganOptions::default().gpu(device.id()).model(match scale {
                     2 => RealEsrganOptionsModel::RealESRAnimeVideoV3x2,
                     3 => RealEsrganOptionsModel::RealESRAnimeVideoV3x3,
                     4 => RealEsrganOptionsModel::RealESRAnimeVideoV3x4,
@@ -57,126 +314,497 @@ impl Upscale {
                     .map_err(|e| Error::new(format!("Failed to initialize RealEsrAnime upscaler: {}", e)))
                     .map(|r| Arc::new(r) as _)
             },
-            Model::RealEsrgan => {
-                let options = RealEsrganOptions::default().model(RealEsrganOptionsModel::RealESRGANPlusx4);
+            Model::RealEsrgan(scale) => {
+                let options = RealEsrganOptions::default().gpu(device.id()).model(match scale {
+                    4 => RealEsrganOptionsModel::RealESRGANPlusx4,
+                    _ => return Err(Error::new(format!("Unsupported scale {} for RealEsrgan", scale))),
+                });
                 RealEsrgan::new(options)
                     .map_err(|e| Error::new(format!("Failed to initialize RealEsrgan upscaler: {}", e)))
                     .map(|r| Arc::new(r) as _)
             },
             Model::RealEsrganAnime => {
-                let options = RealEsrganOptions::default().model(RealEsrganOptionsModel::RealESRGANPlusx4Anime);
+                let options = RealEsrganOptions::default().gpu(device.id()).model(RealEsrganOptionsModel::RealESRGANPlusx4Anime);
                 RealEsrgan::new(options)
                     .map_err(|e| Error::new(format!("Failed to initialize RealEsrganAnime upscaler: {}", e)))
                     .map(|r| Arc::new(r) as _)
             },
+            Model::Plugin(..) | Model::External(..) => Err(Error::new(format!("{} does not run on ncnn", model))),
+        }
+    }
+
+    #[cfg(not(feature = "ncnn"))]
+    fn init_ncnn(model: &Model, _device: Device) -> Result<Arc<dyn Upscaler>, Error> {
+        Err(Error::new(format!("Cannot run {}: this build has no ncnn backend, use a plugin or -m external", model)))
+    }
+
+    // Plugins and external upscalers bring their own backend; CoreML picks its own compute units
+    fn create_upscaler(model: &Model, device: Device, backend: Backend) -> Result<Arc<dyn Upscaler>, Error> {
+        match model {
+            Model::Plugin(plugin, scale) => plugin.create(*scale, device.id()).map(|r| Arc::new(r) as _),
+            Model::External(command, scale) => Ok(Arc::new(command.create(*scale)) as _),
+            _ if backend == Backend::CoreMl => Self::init_coreml(model),
+            _ => Self::init_ncnn(model, device),
+        }
+    }
+
+    fn select_tiler(width: u32, height: u32, scale: u8) -> Option<Tiler> {
+        let free_memory = Gpu::free_memory()?;
+        let budget = (free_memory as f64 * Self::MEMORY_BUDGET_RATIO) as u64 / Self::MAX_JOBS as u64;
+        let pixels_per_tile = budget / (Self::MEMORY_PER_OUTPUT_PIXEL * scale as u64 * scale as u64);
+        let tile_size = ((pixels_per_tile as f64).sqrt() as u32 / 32 * 32).max(Tiler::MIN_TILE_SIZE);
+        (tile_size < width.max(height)).then(|| Tiler::new(tile_size))
+    }
+
+    fn try_upscale(upscaler: &dyn Upscaler, tiler: Option<Tiler>, width: u32, height: u32, scale: u8) -> Result<(), Error> {
+        let pixels = vec![Self::PREFLIGHT_FILL; Frame::size(width, height)];
+        let mut output = vec![0; Frame::size(width * scale as u32, height * scale as u32)];
+        let upscale = |pixels: &[u8], width: usize, height: usize, output: &mut [u8]| upscaler.upscale(pixels, width, height, output);
+        match tiler {
+            Some(tiler) => tiler.upscale(&pixels, width, height, scale as u32, &mut output, upscale),
+            None => upscale(&pixels, width as usize, height as usize, &mut output),
+        }
+    }
+
+    fn suggest_tile_size(upscaler: &dyn Upscaler, failed_size: u32, scale: u8) -> Option<u32> {
+        std::iter::successors(Some(failed_size / 2 / 32 * 32), |&tile_size| Some(tile_size / 2 / 32 * 32))
+            .take_while(|&tile_size| tile_size >= Tiler::MIN_TILE_SIZE)
+            .find(|&tile_size| Self::try_upscale(upscaler, None, tile_size, tile_size, scale).is_ok())
+    }
+
+    // One synthetic frame of the input size catches model init and VRAM failures before extraction starts
+    pub fn preflight(video: &Video, scheduler: &Scheduler) -> Result<Preflight, Error> {
+        let model = video.model.as_ref().ok_or_else(|| Error::new("No upscaling model specified"))?;
+        let scale = model.get_scale();
+        let (width, height) = (video.get_original_width() as u32, video.get_original_height() as u32);
+        let _slot = scheduler.acquire();
+
+        let device = Self::primary_device(video);
+        let upscaler = Self::init_upscaler(model, device, video.backend)
+            .map_err(|e| Error::new(format!("Preflight failed for {}: {}", video.input.display(), e)))?;
+        let tiler = video.tile_size.map(Tiler::new).or_else(|| match device {
+            Device::Gpu => Self::select_tiler(width, height, scale),
+            Device::Cpu => None,
+        });
+
+        if let Err(e) = Self::try_upscale(upscaler.as_ref(), tiler, width, height, scale) {
+            let failed_size = tiler.map_or(width.max(height), |tiler| tiler.get_tile_size());
+            return Err(Error::new(match Self::suggest_tile_size(upscaler.as_ref(), failed_size, scale) {
+                Some(tile_size) => format!(
+                    "Preflight upscale of a {}x{} frame failed: {}. It fits with --tile-size {}", width, height, e, tile_size
+                ),
+                None => format!(
+                    "Preflight upscale of a {}x{} frame failed even with {}px tiles: {}", width, height, Tiler::MIN_TILE_SIZE, e
+                ),
+            }));
+        }
+
+        Logger::debug(format!(
+            "Preflight upscale of a {}x{} frame passed{}", width, height,
+            tiler.map_or(String::new(), |tiler| format!(" with {}px tiles", tiler.get_tile_size()))
+        ));
+        Ok(Preflight { upscaler, tiler, scheduler: scheduler.clone() })
+    }
+
+    fn primary_device(video: &Video) -> Device {
+        match video.backend {
+            Backend::Cpu => Device::Cpu,
+            Backend::Ncnn | Backend::CoreMl => Device::Gpu,
+        }
+    }
+
+    fn init_chain(video: &Video, device: Device) -> Result<Vec<ChainedUpscaler>, Error> {
+        video.chain.iter().map(|pass| {
+            let (width, height, scale) = (pass.width as u32, pass.height as u32, pass.model.get_scale());
+            Ok(ChainedUpscaler {
+                upscaler: Self::init_upscaler(&pass.model, device, match device {
+                    Device::Gpu => video.backend,
+                    Device::Cpu => Backend::Cpu,
+                })?,
+                scale,
+                tiler: match device {
+                    Device::Gpu => video.tile_size.map(Tiler::new).or_else(|| Self::select_tiler(width, height, scale)),
+                    Device::Cpu => None,
+                },
+                width,
+                height,
+            })
+        }).collect()
+    }
+
+    fn upscale_pass(upscaler: &dyn Upscaler, tiler: Option<Tiler>, pixels: &[u8], width: u32, height: u32, scale: u8, output: &mut [u8]) -> Result<(), Error> {
+        let upscale = |pixels: &[u8], width: usize, height: usize, output: &mut [u8]| upscaler.upscale(pixels, width, height, output);
+        let scale = scale as u32;
+
+        if let Some(tiler) = tiler {
+            return tiler.upscale(pixels, width, height, scale, output, upscale);
+        }
+
+        upscale(pixels, width as usize, height as usize, output).or_else(|e| {
+            let tiler = Tiler::new(Self::FALLBACK_TILE_SIZE);
+            tiler
+                .upscale(pixels, width, height, scale, output, upscale)
+                .map_err(|tiled_error| Error::new(format!(
+                    "{} (retrying with {}px tiles also failed: {})", e, tiler.get_tile_size(), tiled_error
+                )))
+        })
+    }
+
+    fn resize(pixels: Vec<u8>, width: u32, height: u32, new_width: u32, new_height: u32) -> Result<Vec<u8>, Error> {
+        if (width, height) == (new_width, new_height) {
+            return Ok(pixels);
+        }
+        let image = RgbImage::from_raw(width, height, pixels)
+            .ok_or_else(|| Error::new(format!("Intermediate frame does not match its size {}x{}", width, height)))?;
+        Ok(imageops::resize(&image, new_width, new_height, FilterType::Lanczos3).into_raw())
+    }
+
+    // Only the last pass writes into the frame's output, the passes before it need buffers of their own sizes
+    fn upscale_pixels(pixels: &[u8], width: u32, height: u32, context: &UpscaleContext, output: &mut [u8]) -> Result<(), Error> {
+        let Some((last, passes)) = context.chain.split_last() else {
+            return Self::upscale_pass(context.upscaler.as_ref(), context.tiler, pixels, width, height, context.scale, output);
+        };
+        let mut upscaled = vec![0; Frame::size(width * context.scale as u32, height * context.scale as u32)];
+        Self::upscale_pass(context.upscaler.as_ref(), context.tiler, pixels, width, height, context.scale, &mut upscaled)?;
+        let (mut width, mut height) = (width * context.scale as u32, height * context.scale as u32);
+        for pass in passes {
+            let resized = Self::resize(upscaled, width, height, pass.width, pass.height)?;
+            (width, height) = (pass.width * pass.scale as u32, pass.height * pass.scale as u32);
+            upscaled = vec![0; Frame::size(width, height)];
+            Self::upscale_pass(pass.upscaler.as_ref(), pass.tiler, &resized, pass.width, pass.height, pass.scale, &mut upscaled)?;
+        }
+        let resized = Self::resize(upscaled, width, height, last.width, last.height)?;
+        Self::upscale_pass(last.upscaler.as_ref(), last.tiler, &resized, last.width, last.height, last.scale, output)
+    }
+
+    // The cheaper model falls short of the output size, lanczos makes up the rest
+    fn upscale_low_detail(frame: &Frame, adaptive: &(Arc<dyn Upscaler>, u8), context: &UpscaleContext, output: &mut [u8]) -> Result<(), Error> {
+        let (upscaler, scale) = adaptive;
+        let (width, height) = (frame.width * *scale as u32, frame.height * *scale as u32);
+        let mut upscaled = vec![0; Frame::size(width, height)];
+        Self::upscale_pass(upscaler.as_ref(), None, &frame.buffer, frame.width, frame.height, *scale, &mut upscaled)?;
+        let upscaled = RgbImage::from_raw(width, height, upscaled).expect("upscaled pixels match their size");
+        let (output_width, output_height) = Self::output_size(frame, context);
+        output.copy_from_slice(&imageops::resize(&upscaled, output_width, output_height, FilterType::Lanczos3));
+        Ok(())
+    }
+
+    fn upscale_roi(frame: &Frame, roi: Roi, context: &UpscaleContext, output: &mut [u8]) -> Result<(), Error> {
+        let scale = context.scale as u32;
+        let (x, y, width, height) = (roi.x as u32, roi.y as u32, roi.width as u32, roi.height as u32);
+        let image = frame.image();
+        let region = imageops::crop_imm(&image, x, y, width, height).to_image();
+        let mut upscaled = vec![0; Frame::size(width * scale, height * scale)];
+        Self::upscale_pixels(&region, width, height, context, &mut upscaled)?;
+        let upscaled = RgbImage::from_raw(width * scale, height * scale, upscaled).expect("upscaled pixels match their size");
+
+        let mut composite = imageops::resize(&image, frame.width * scale, frame.height * scale, FilterType::Triangle);
+        imageops::replace(&mut composite, &upscaled, (x * scale) as i64, (y * scale) as i64);
+        output.copy_from_slice(&composite);
+        Ok(())
+    }
+
+    fn output_size(frame: &Frame, context: &UpscaleContext) -> (u32, u32) {
+        match context.chain.last() {
+            Some(pass) => (pass.width * pass.scale as u32, pass.height * pass.scale as u32),
+            None => (frame.width * context.scale as u32, frame.height * context.scale as u32),
+        }
+    }
+
+    fn upscaled_frame(frame: &Frame, buffer: Buffer, context: &UpscaleContext) -> Frame {
+        let (width, height) = Self::output_size(frame, context);
+        Frame {
+            width,
+            height,
+            buffer,
+            ..*frame
+        }
+    }
+
+    fn output_buffer(frame: &Frame, context: &UpscaleContext) -> Buffer {
+        let (width, height) = Self::output_size(frame, context);
+        context.pool.acquire(Frame::size(width, height))
+    }
+
+    fn cached_frame(frame: &Frame, context: &UpscaleContext) -> Option<Frame> {
+        let cache = context.cache.as_ref()?;
+        let mut buffer = Self::output_buffer(frame, context);
+        cache.get(frame.index, &mut buffer).then(|| Self::upscaled_frame(frame, buffer, context))
+    }
+
+    fn upscale_source(source: &Frame, context: &UpscaleContext, output: &mut [u8]) -> Result<(), Error> {
+        match (context.roi, &context.adaptive) {
+            (Some(roi), _) => Self::upscale_roi(source, roi, context, output),
+            (None, Some(adaptive)) if source.low_detail => Self::upscale_low_detail(source, adaptive, context, output),
+            (None, _) => Self::upscale_pixels(&source.buffer, source.width, source.height, context, output),
         }
     }
 
     fn process_frame(
-        frame: Frame,
-        upscaler: &Arc<dyn Upscaler>,
-        scale: u8,
+        frame: &Frame,
+        context: &UpscaleContext,
     ) -> Result<Frame, Error> {
-        let width = frame.image.width();
-        let height = frame.image.height();
-        let frame_pixels = frame.image.to_rgb8().into_raw();
-        let upscaled_pixels = upscaler.upscale(&frame_pixels, width as usize, height as usize)?;
-        let upscaled_image = image::ImageBuffer::from_raw(
-            width * scale as u32,
-            height * scale as u32,
-            upscaled_pixels
-        ).unwrap();
-        Ok(Frame {
-            image: image::DynamicImage::ImageRgb8(upscaled_image),
-            ..frame
-        })
+        let (width, height) = Self::output_size(frame, context);
+        let mut buffer = Self::output_buffer(frame, context);
+        if frame.static_segment {
+            buffer.copy_from_slice(&imageops::resize(&frame.image(), width, height, FilterType::Lanczos3));
+            return Ok(Self::upscaled_frame(frame, buffer, context));
+        }
+        Self::upscale_source(frame, context, &mut buffer)?;
+        if let Some(quality_gate) = &context.quality_gate {
+            quality_gate.check(frame, &mut buffer, width, height);
+        }
+        if context.preserve_grain {
+            GrainLayer::extract(frame).apply(&mut buffer, width, height);
+        }
+        let frame = Self::upscaled_frame(frame, buffer, context);
+        if let Some(cache) = &context.cache {
+            if let Err(e) = cache.put(frame.index, &frame.buffer) {
+                Logger::warning(e.to_string());
+            }
+        }
+        Ok(frame)
     }
 
     fn send_processed_frames(
-        sender: &Sender<Result<Frame, Error>>,
-        processed_frames: &mut BTreeMap<usize, Frame>,
+        reorder: &mut Reorder,
         next_frame_index: &Arc<AtomicUsize>,
+        frame_errors: &FrameErrors,
     ) {
-        while let Some(frame) = processed_frames.remove(&next_frame_index.load(Ordering::SeqCst)) {
-            let duplicates = frame.duplicates;
-            if sender.send(Ok(frame)).is_err() {
-                return;
+        while let Some(processed) = reorder.pending.remove(&next_frame_index.load(Ordering::SeqCst)) {
+            let step = 1 + processed.frame().duplicates;
+            let frame = match processed {
+                Processed::Upscaled(frame) if frame_errors.repeat_previous() => reorder.held.replace(frame),
+                Processed::Upscaled(frame) => Some(frame),
+                Processed::Failed(frame) => reorder.held.take().inspect(|previous| {
+                    reorder.held = Some(Frame {
+                        duplicates: frame.duplicates,
+                        ..previous.repeated(frame.index, frame.pts, frame.duration)
+                    });
+                }),
+            };
+            if let Some(frame) = frame {
+                if reorder.sender.send(Ok(frame)).is_err() {
+                    return;
+                }
             }
-            next_frame_index.fetch_add(1 + duplicates, Ordering::SeqCst);
+            next_frame_index.fetch_add(step, Ordering::SeqCst);
         }
     }
 
     fn process_incoming_frames(
-        receiver: Receiver<Result<Frame, Error>>,
-        sender: Sender<Result<Frame, Error>>,
-        upscaler: Arc<dyn Upscaler>,
-        scale: u8,
+        receiver: Receiver<Frame>,
+        sender: MeteredSender<Result<Frame, Error>>,
+        context: UpscaleContext,
+        stage: String,
         next_frame_index: Arc<AtomicUsize>,
-        processed_frames: Arc<Mutex<BTreeMap<usize, Frame>>>,
-    ) { 
-        while let Ok(frame_result) = receiver.recv() {
-            let processed_frame = match frame_result {
-                Ok(frame) => Self::process_frame(frame, &upscaler, scale),
+        reorder: Arc<Mutex<Reorder>>,
+    ) {
+        while let Ok(frame) = receiver.recv() {
+            context.stats.sample_queue(&stage, receiver.len());
+            let processed_frame = if let Some(cached) = Self::cached_frame(&frame, &context) {
+                Ok(cached)
+            } else {
+                // waiting for a token before taking a GPU slot leaves the slot to other files meanwhile
+                if let Some(throttle) = &context.throttle {
+                    throttle.wait();
+                }
+                let _slot = context.scheduler.as_ref().map(Scheduler::acquire);
+                let started = Instant::now();
+                let processed_frame = context.stats.time(&stage, || Self::process_frame(&frame, &context));
+                let elapsed = started.elapsed();
+                context.throughput.record(context.device, elapsed.as_secs_f64());
+                if let Some(throttle) = &context.throttle {
+                    throttle.pause(elapsed);
+                }
+                processed_frame
+            };
+    
+            let processed = match processed_frame {
+                Ok(upscaled) => Processed::Upscaled(upscaled),
+                Err(e) if context.frame_errors.tolerated() => {
+                    context.frame_errors.record(format!("upscale frame {}: {}", frame.index, e));
+                    Processed::Failed(frame)
+                }
                 Err(e) => {
                     let _ = sender.send(Err(e));
                     return;
                 }
             };
-    
-            match processed_frame {
-                Ok(frame) => {
-                    let mut processed_frames = processed_frames.lock().unwrap();
-                    processed_frames.insert(frame.index, frame);
-                    Self::send_processed_frames(&sender, &mut processed_frames, &next_frame_index);
-                }
+
+            let mut reorder = reorder.lock().unwrap();
+            reorder.pending.insert(processed.frame().index, processed);
+            Self::send_processed_frames(&mut reorder, &next_frame_index, &context.frame_errors);
+        }
+    }
+
+    fn dispatch_frames(
+        receiver: Receiver<Result<Frame, Error>>,
+        gpu_sender: MeteredSender<Frame>,
+        cpu_sender: MeteredSender<Frame>,
+        sender: MeteredSender<Result<Frame, Error>>,
+        throughput: Throughput,
+        cpu_workers: usize,
+    ) {
+        let (mut dispatched, mut dispatched_to_cpu) = (0usize, 0usize);
+        while let Ok(frame_result) = receiver.recv() {
+            let frame = match frame_result {
+                Ok(frame) => frame,
                 Err(e) => {
                     let _ = sender.send(Err(e));
                     return;
                 }
+            };
+
+            dispatched += 1;
+            let use_cpu = cpu_workers > 0 && match throughput.cpu_share(Self::MAX_JOBS, cpu_workers) {
+                Some(share) => (dispatched_to_cpu as f64) < dispatched as f64 * share,
+                None => !cpu_sender.is_full(),
+            };
+            let sent = if use_cpu {
+                dispatched_to_cpu += 1;
+                cpu_sender.send(frame).is_ok()
+            } else {
+                gpu_sender.send(frame).is_ok()
+            };
+            if !sent {
+                return;
             }
         }
     }
 
-    fn spawn_worker_threads(
-        frames_receiver: Receiver<Result<Frame, Error>>,
-        upscaler: Arc<dyn Upscaler>,
-        scale: u8,
-    ) -> Receiver<Result<Frame, Error>> {
-        let (sender, receiver) = bounded(Self::MAX_JOBS);
-        let next_frame_index = Arc::new(AtomicUsize::new(0));
-        let processed_frames = Arc::new(Mutex::new(BTreeMap::new()));
-
-        for _ in 0..Self::MAX_JOBS {
-            let upscaler = upscaler.clone();
+    fn spawn_device_workers(
+        frames_receiver: &Receiver<Frame>,
+        sender: &MeteredSender<Result<Frame, Error>>,
+        context: &UpscaleContext,
+        workers: usize,
+        next_frame_index: &Arc<AtomicUsize>,
+        reorder: &Arc<Mutex<Reorder>>,
+    ) {
+        for worker in 0..workers {
+            let stage = format!("upscale {} #{}", context.device.name(), worker + 1);
+            let context = context.clone();
             let sender = sender.clone();
             let frames_receiver = frames_receiver.clone();
             let next_frame_index = next_frame_index.clone();
-            let processed_frames = processed_frames.clone();
+            let reorder = reorder.clone();
 
             thread::spawn(move || {
                 Self::process_incoming_frames(
                     frames_receiver,
                     sender,
-                    upscaler,
-                    scale,
+                    context,
+                    stage,
                     next_frame_index,
-                    processed_frames,
+                    reorder,
                 )
             });
         }
+    }
+
+    fn spawn_worker_threads(
+        frames_receiver: Receiver<Result<Frame, Error>>,
+        gpu_context: UpscaleContext,
+        cpu_context: Option<UpscaleContext>,
+        gpu_workers: usize,
+        cpu_workers: usize,
+        capacity: usize,
+    ) -> Receiver<Result<Frame, Error>> {
+        let stats = &gpu_context.stats;
+        let (sender, receiver) = stats.channel("upscale", capacity);
+        let next_frame_index = Arc::new(AtomicUsize::new(0));
+        let reorder = Arc::new(Mutex::new(Reorder::new(sender.clone())));
+        let (gpu_sender, gpu_receiver) = stats.channel("upscale gpu queue", capacity);
+        let (cpu_sender, cpu_receiver) = stats.channel("upscale cpu queue", cpu_workers.max(1));
+        let throughput = gpu_context.throughput.clone();
+
+        Self::spawn_device_workers(&gpu_receiver, &sender, &gpu_context, gpu_workers, &next_frame_index, &reorder);
+        if let Some(cpu_context) = &cpu_context {
+            Self::spawn_device_workers(&cpu_receiver, &sender, cpu_context, cpu_workers, &next_frame_index, &reorder);
+        }
+
+        thread::spawn(move || Self::dispatch_frames(frames_receiver, gpu_sender, cpu_sender, sender, throughput, cpu_workers));
 
         receiver
     }
 
-    pub fn execute(video: &Video, frames_receiver: Receiver<Result<Frame, Error>>) -> Result<Receiver<Result<Frame, Error>>, Error> {
+    pub fn execute(
+        video: &Video,
+        frames_receiver: Receiver<Result<Frame, Error>>,
+        preflight: Preflight,
+        throttle: Option<Throttle>,
+        stats: &Stats,
+        frame_errors: &FrameErrors,
+        quality_gate: Option<&QualityGate>,
+    ) -> Result<Receiver<Result<Frame, Error>>, Error> {
         let model = video.model.as_ref().ok_or_else(|| Error::new("No upscaling model specified"))?;
         let scale = model.get_scale();
         if scale == 1 {
             return Err(Error::new("Upscale scale must be greater than 1"));
         }
 
-        let upscaler = Self::init_upscaler(model)?;
-        let receiver = Self::spawn_worker_threads(frames_receiver, upscaler, scale);
+        let throughput = Throughput::default();
+        let pool = BufferPool::new(Self::POOL_SIZE);
+        let cache = video.cache_dir.map(|cache_dir| FrameCache::new(cache_dir, video)).transpose()?;
+        // with the CPU backend the primary workers are the CPU workers, and there is no second pool to share with
+        let device = Self::primary_device(video);
+        let (gpu_workers, cpu_workers) = match device {
+            Device::Gpu => (Self::MAX_JOBS, video.cpu_workers),
+            Device::Cpu => (video.cpu_workers.max(1), 0),
+        };
+        let gpu_context = UpscaleContext {
+            upscaler: preflight.upscaler,
+            scale,
+            tiler: preflight.tiler,
+            chain: Self::init_chain(video, device)?,
+            adaptive: video.adaptive_model.map(|model| {
+                let backend = match device {
+                    Device::Gpu => video.backend,
+                    Device::Cpu => Backend::Cpu,
+                };
+                Self::init_upscaler(&model, device, backend).map(|upscaler| (upscaler, model.get_scale()))
+            }).transpose()?,
+            roi: video.roi,
+            device,
+            scheduler: matches!(device, Device::Gpu).then_some(preflight.scheduler),
+            throttle: match device {
+                Device::Gpu => throttle.clone(),
+                Device::Cpu => throttle.as_ref().and_then(Throttle::for_cpu),
+            },
+            throughput: throughput.clone(),
+            stats: stats.clone(),
+            cache: cache.clone(),
+            frame_errors: frame_errors.clone(),
+            quality_gate: quality_gate.cloned(),
+            preserve_grain: video.grain == Some(Grain::Preserve),
+            pool: pool.clone(),
+        };
+        let cpu_context = match cpu_workers {
+            0 => None,
+            _ => Some(UpscaleContext {
+                upscaler: Self::init_upscaler(model, Device::Cpu, Backend::Cpu)?,
+                scale,
+                tiler: None,
+                chain: Self::init_chain(video, Device::Cpu)?,
+                adaptive: video.adaptive_model
+                    .map(|model| Self::init_upscaler(&model, Device::Cpu, Backend::Cpu).map(|upscaler| (upscaler, model.get_scale())))
+                    .transpose()?,
+                roi: video.roi,
+                device: Device::Cpu,
+                scheduler: None,
+                throttle: throttle.as_ref().and_then(Throttle::for_cpu),
+                throughput,
+                stats: stats.clone(),
+                cache,
+                frame_errors: frame_errors.clone(),
+                quality_gate: quality_gate.cloned(),
+                preserve_grain: video.grain == Some(Grain::Preserve),
+                pool,
+            }),
+        };
+
+        let capacity = video.channel_capacity.get("upscale", Self::MAX_JOBS);
+        let receiver = Self::spawn_worker_threads(frames_receiver, gpu_context, cpu_context, gpu_workers, cpu_workers, capacity);
         Ok(receiver)
     }
 }
\ No newline at end of file
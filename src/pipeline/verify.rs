@@ -0,0 +1,153 @@
+use crate::error::Error;
+use crate::video::Video;
+use crate::logger::Logger;
+use crate::binary::Binary;
+use crate::media_info::{MediaInfo, StreamKind};
+use crate::external_tracks::TrackKind;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::process::Stdio;
+
+pub struct Verify;
+
+impl Verify {
+    const MIN_DURATION_TOLERANCE: f64 = 0.5;
+    const MIN_FRAME_TOLERANCE: usize = 2;
+    const TOLERANCE: f64 = 0.01;
+    const THUMBNAIL_SIZE: usize = 16;
+    const BLACK_LEVEL: u8 = 24;
+
+    fn tolerance(expected: f64, minimum: f64) -> f64 {
+        (expected * Self::TOLERANCE).max(minimum)
+    }
+
+    fn check_resolution(video: &Video, media: &MediaInfo) -> Result<(), Error> {
+        let stream = media.video_stream(0)
+            .ok_or_else(|| Error::new(format!("Verification failed: {} has no video stream", video.output.display())))?;
+        if stream.width != video.width || stream.height != video.height {
+            return Err(Error::new(format!(
+                "Verification failed: {} is {}x{}, expected {}x{}", video.output.display(), stream.width, stream.height, video.width, video.height
+            )));
+        }
+        Ok(())
+    }
+
+    // Every video and audio stream of the input is carried over, along with added audio tracks; subtitles may be burned
+    // or dropped by the format
+    fn check_streams(video: &Video, media: &MediaInfo) -> Result<(), Error> {
+        let Some(input) = &video.media else {
+            return Ok(());
+        };
+        for (kind, track, name) in [(StreamKind::Video, None, "video"), (StreamKind::Audio, Some(TrackKind::Audio), "audio")] {
+            let original = match track {
+                Some(track) if video.replaces_streams(track) => 0,
+                _ => input.streams(kind.clone()).count(),
+            };
+            let expected = original + track.map_or(0, |track| video.count_external_tracks(track));
+            let found = media.streams(kind).count();
+            if expected != found {
+                return Err(Error::new(format!(
+                    "Verification failed: {} has {} {} streams, expected {}", video.output.display(), found, name, expected
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_duration(video: &Video, media: &MediaInfo) -> Result<f64, Error> {
+        let expected = video.chapter.map(|chapter| chapter.duration())
+            .or(video.media.as_ref().and_then(|media| media.duration))
+            .unwrap_or(video.frame_count as f64 / video.frame_rate);
+        let duration = media.duration
+            .ok_or_else(|| Error::new(format!("Verification failed: {} has no duration", video.output.display())))?;
+        if (duration - expected).abs() > Self::tolerance(expected, Self::MIN_DURATION_TOLERANCE) {
+            return Err(Error::new(format!(
+                "Verification failed: {} lasts {:.2}s, expected {:.2}s", video.output.display(), duration, expected
+            )));
+        }
+        Ok(duration)
+    }
+
+    // frame_count is an estimate for variable frame rate and detelecined inputs, hence the tolerance
+    fn check_frame_count(video: &Video, output: &Path) -> Result<(), Error> {
+        let frames = MediaInfo::count_frames(output, 0)?;
+        let tolerance = Self::tolerance(video.frame_count as f64, Self::MIN_FRAME_TOLERANCE as f64);
+        if (frames as f64 - video.frame_count as f64).abs() > tolerance {
+            return Err(Error::new(format!(
+                "Verification failed: {} has {} frames, expected {}", video.output.display(), frames, video.frame_count
+            )));
+        }
+        Ok(())
+    }
+
+    // Decodes one frame as a small grayscale thumbnail, failing on any decode error
+    fn decode_thumbnail(input: &Path, stream: usize, time: f64) -> Result<Vec<u8>, Error> {
+        let size = Self::THUMBNAIL_SIZE.to_string();
+        let output = Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-v", "error", "-xerror", "-ss", &format!("{:.3}", time)])
+            .arg("-i").arg(Binary::path_arg(input))
+            .args([
+                "-map", &format!("0:v:{}", stream),
+                "-frames:v", "1",
+                "-vf", &format!("scale={}:{},format=gray", size, size),
+                "-f", "rawvideo", "-",
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
+
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            Logger::debug(format!("[verify] {}", line));
+        }
+        if !output.status.success() || output.stdout.len() != Self::THUMBNAIL_SIZE * Self::THUMBNAIL_SIZE {
+            return Err(Error::new(format!("Verification failed: the frame at {:.2}s of {} cannot be decoded", time, input.display())));
+        }
+        Ok(output.stdout)
+    }
+
+    fn is_black(thumbnail: &[u8]) -> bool {
+        thumbnail.iter().all(|&pixel| pixel < Self::BLACK_LEVEL)
+    }
+
+    fn random_times(count: usize, duration: f64) -> Vec<f64> {
+        let seed = RandomState::new().build_hasher().finish();
+        (0..count as u64)
+            .map(|index| {
+                let value = seed.wrapping_add(index).wrapping_mul(0x9e3779b97f4a7c15) >> 11;
+                value as f64 / (1u64 << 53) as f64 * duration
+            })
+            .collect()
+    }
+
+    // A black output frame is only an error when the input is not black at the same time
+    fn check_frames(video: &Video, output: &Path, duration: f64) -> Result<(), Error> {
+        let span = (duration - 1.0 / video.frame_rate).max(0.0);
+        for time in Self::random_times(video.verify_frames, span) {
+            let thumbnail = Self::decode_thumbnail(output, 0, time)?;
+            if !Self::is_black(&thumbnail) || video.sequence.is_some() {
+                continue;
+            }
+            if !Self::is_black(&Self::decode_thumbnail(video.input, video.stream, time)?) {
+                return Err(Error::new(format!(
+                    "Verification failed: the frame at {:.2}s of {} is black but the input is not", time, video.output.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn execute(video: &Video) -> Result<(), Error> {
+        let output = video.get_partial_output();
+        Logger::info(format!("Verifying {}", video.output.display()));
+        let media = MediaInfo::probe(&output)?;
+        Self::check_resolution(video, &media)?;
+        Self::check_streams(video, &media)?;
+        let duration = Self::check_duration(video, &media)?;
+        Self::check_frame_count(video, &output)?;
+        Self::check_frames(video, &output, duration)?;
+        Logger::info(format!("Verified {}", video.output.display()));
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::Error;
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::c_void;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use libloading::Library;
+
+// Layout shared with plugins, see "Upscaler plugins" in the README
+#[repr(C)]
+struct PluginApi {
+    abi_version: u32,
+    scales: *const u8,
+    scales_len: usize,
+    create: unsafe extern "C" fn(scale: u8, gpu: i32) -> *mut c_void,
+    process: unsafe extern "C" fn(handle: *mut c_void, input: *const u8, width: usize, height: usize, output: *mut u8) -> i32,
+    destroy: unsafe extern "C" fn(handle: *mut c_void),
+}
+
+type PluginEntry = unsafe extern "C" fn() -> *const PluginApi;
+
+pub struct Plugin {
+    name: String,
+    api: &'static PluginApi,
+    scales: Vec<usize>,
+    _library: Library,
+}
+
+// The API table is immutable and plugins must accept calls from any thread
+unsafe impl Send for Plugin {}
+unsafe impl Sync for Plugin {}
+
+impl Plugin {
+    pub const PREFIX: &'static str = "plugin:";
+    const ABI_VERSION: u32 = 1;
+    const ENTRY_SYMBOL: &'static [u8] = b"upscaler_plugin_api";
+    const PATH_ENV_VAR: &'static str = "UPSCALER_PLUGIN_PATH";
+    const BUNDLED_DIRECTORY: &'static str = "plugins";
+
+    fn registry() -> &'static Mutex<HashMap<String, &'static Plugin>> {
+        static PLUGINS: OnceLock<Mutex<HashMap<String, &'static Plugin>>> = OnceLock::new();
+        PLUGINS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    pub fn name_of(model_name: &str) -> Option<&str> {
+        model_name.strip_prefix(Self::PREFIX).filter(|name| !name.is_empty())
+    }
+
+    pub fn search_directories() -> Vec<PathBuf> {
+        let search_path = env::var_os(Self::PATH_ENV_VAR).map(|path| env::split_paths(&path).collect::<Vec<_>>()).unwrap_or_default();
+        let bundled = env::current_exe().ok().and_then(|exe| exe.parent().map(|parent| parent.join(Self::BUNDLED_DIRECTORY)));
+        search_path.into_iter().chain(bundled).collect()
+    }
+
+    fn find(name: &str) -> Result<PathBuf, Error> {
+        let file_name = libloading::library_filename(name);
+
+        Self::search_directories()
+            .into_iter()
+            .map(|directory| directory.join(&file_name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| Error::new(format!(
+                "Cannot find plugin {}: {} not found in ${} or the plugins directory", name, file_name.to_string_lossy(), Self::PATH_ENV_VAR
+            )))
+    }
+
+    fn open(name: &str) -> Result<Self, Error> {
+        let path = Self::find(name)?;
+        // SAFETY: loading a plugin runs its initialisers, which is what selecting it asks for
+        let library = unsafe { Library::new(&path) }
+            .map_err(|e| Error::new(format!("Failed to load plugin {}: {}", path.display(), e)))?;
+        // SAFETY: the entry point signature is part of the plugin ABI, and the table it returns
+        // stays valid because the library is kept alongside it and plugins are never unloaded
+        let api = unsafe {
+            let entry = library
+                .get::<PluginEntry>(Self::ENTRY_SYMBOL)
+                .map_err(|e| Error::new(format!("Plugin {} does not export upscaler_plugin_api: {}", name, e)))?;
+            entry().as_ref()
+        }.ok_or_else(|| Error::new(format!("Plugin {} returned no API table", name)))?;
+
+        if api.abi_version != Self::ABI_VERSION {
+            return Err(Error::new(format!(
+                "Plugin {} uses ABI version {}, expected {}", name, api.abi_version, Self::ABI_VERSION
+            )));
+        }
+
+        let scales = match api.scales_len {
+            0 => Vec::new(),
+            // SAFETY: the plugin guarantees scales points at scales_len bytes for the library lifetime
+            len => unsafe { std::slice::from_raw_parts(api.scales, len) }.iter().map(|&scale| scale as usize).collect::<Vec<_>>(),
+        };
+        if scales.is_empty() || scales.iter().any(|&scale| scale < 2) {
+            return Err(Error::new(format!("Plugin {} must support at least one scale of 2 or more", name)));
+        }
+
+        Ok(Self { name: name.to_string(), api, scales, _library: library })
+    }
+
+    pub fn load(name: &str) -> Result<&'static Self, Error> {
+        let mut plugins = Self::registry().lock().unwrap();
+        if let Some(plugin) = plugins.get(name) {
+            return Ok(plugin);
+        }
+
+        let plugin: &'static Self = Box::leak(Box::new(Self::open(name)?));
+        plugins.insert(name.to_string(), plugin);
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn scales(&self) -> &[usize] {
+        &self.scales
+    }
+
+    pub fn create(&'static self, scale: u8, gpu: i32) -> Result<PluginUpscaler, Error> {
+        // SAFETY: create is called with a scale the plugin advertised
+        let handle = unsafe { (self.api.create)(scale, gpu) };
+        if handle.is_null() {
+            return Err(Error::new(format!("Plugin {} failed to create a x{} upscaler", self.name, scale)));
+        }
+        Ok(PluginUpscaler { plugin: self, handle, scale: scale as usize })
+    }
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Plugin({})", self.name)
+    }
+}
+
+pub struct PluginUpscaler {
+    plugin: &'static Plugin,
+    handle: *mut c_void,
+    scale: usize,
+}
+
+// Plugins must allow concurrent process calls on one handle, like the built-in backends
+unsafe impl Send for PluginUpscaler {}
+unsafe impl Sync for PluginUpscaler {}
+
+impl PluginUpscaler {
+    pub fn process(&self, input: &[u8], width: usize, height: usize, output: &mut [u8]) -> Result<(), Error> {
+        if input.len() != width * height * 3 {
+            return Err(Error::new(format!("Plugin {} got a frame of unexpected size: {}", self.plugin.name, input.len())));
+        }
+        if output.len() != width * self.scale * height * self.scale * 3 {
+            return Err(Error::new(format!("Plugin {} got an output buffer of unexpected size: {}", self.plugin.name, output.len())));
+        }
+
+        // SAFETY: input holds width * height RGB pixels and output has room for the scaled frame
+        let status = unsafe { (self.plugin.api.process)(self.handle, input.as_ptr(), width, height, output.as_mut_ptr()) };
+        if status != 0 {
+            return Err(Error::new(format!("Plugin {} upscale failed with status {}", self.plugin.name, status)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PluginUpscaler {
+    fn drop(&mut self) {
+        // SAFETY: handle came from create and is destroyed exactly once
+        unsafe { (self.plugin.api.destroy)(self.handle) }
+    }
+}
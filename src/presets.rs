@@ -0,0 +1,101 @@
+use crate::error::Error;
+use crate::format::Format;
+use crate::bit_depth::BitDepth;
+
+// Delivery presets that fix the container, encoder, pixel format, level constraints and audio codec for common players
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    Web,
+    Mobile,
+    Tv,
+    Archive,
+}
+
+impl Preset {
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Preset::Web | Preset::Mobile => "libx264",
+            Preset::Tv | Preset::Archive => "libx265",
+        }
+    }
+
+    pub fn bit_depth(&self) -> BitDepth {
+        match self {
+            Preset::Web | Preset::Mobile => BitDepth::Eight,
+            Preset::Tv | Preset::Archive => BitDepth::Ten,
+        }
+    }
+
+    pub fn pixel_format(&self) -> &'static str {
+        match self.bit_depth() {
+            BitDepth::Eight => "yuv420p",
+            BitDepth::Ten => "yuv420p10le",
+        }
+    }
+
+    // mp4 outputs get +faststart from the format's muxer flags
+    pub fn format(&self) -> Format {
+        match self {
+            Preset::Web | Preset::Mobile | Preset::Tv => Format::Mp4,
+            Preset::Archive => Format::Mkv,
+        }
+    }
+
+    // Stereo aac plays everywhere; the archive keeps the original tracks untouched
+    pub fn audio_codec(&self) -> &'static str {
+        match self {
+            Preset::Web | Preset::Mobile | Preset::Tv => "aac",
+            Preset::Archive => "copy",
+        }
+    }
+
+    pub fn get_args(&self, stream: usize) -> Vec<String> {
+        let args: &[(&str, &str)] = match self {
+            Preset::Web => &[("profile", "high"), ("level", "4.2")],
+            Preset::Mobile => &[("profile", "main"), ("level", "4.0")],
+            // hvc1 is the tag Apple players require for hevc in mp4
+            Preset::Tv => &[("profile", "main10"), ("tag", "hvc1")],
+            Preset::Archive => &[("crf", "16"), ("preset", "slow")],
+        };
+        args.iter()
+            .flat_map(|(option, value)| [format!("-{}:v:{}", option, stream), value.to_string()])
+            .collect()
+    }
+
+    pub fn validate(&self, encoder: &str, format: Option<Format>) -> Result<(), Error> {
+        if encoder != self.encoder() {
+            return Err(Error::new(format!("--preset {} encodes with {}, it cannot be combined with encoder {}", self, self.encoder(), encoder)));
+        }
+        match format {
+            Some(format) if format != self.format() => Err(Error::new(format!(
+                "--preset {} writes {} files, it cannot be combined with format {}", self, self.format(), format
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "web" => Ok(Preset::Web),
+            "mobile" => Ok(Preset::Mobile),
+            "tv" => Ok(Preset::Tv),
+            "archive" => Ok(Preset::Archive),
+            _ => Err(Error::new(format!("Invalid preset: {}. Must be web, mobile, tv or archive", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Web => write!(f, "web"),
+            Preset::Mobile => write!(f, "mobile"),
+            Preset::Tv => write!(f, "tv"),
+            Preset::Archive => write!(f, "archive"),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::error::Error;
+use crate::logger::Logger;
+
+use std::process::{self, Command, Stdio};
+
+pub struct Priority;
+
+impl Priority {
+    #[cfg(unix)]
+    const NICENESS: &'static str = "10";
+
+    fn run(program: &str, args: &[&str]) -> Result<(), Error> {
+        let status = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| Error::new(format!("Failed to run {}: {}", program, e)))?;
+
+        if !status.success() {
+            return Err(Error::new(format!("{} exited with {}", program, status)));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn lower_cpu() -> Result<(), Error> {
+        Self::run("renice", &["-n", Self::NICENESS, "-p", &process::id().to_string()])
+    }
+
+    #[cfg(windows)]
+    fn lower_cpu() -> Result<(), Error> {
+        let script = format!("(Get-Process -Id {}).PriorityClass = 'BelowNormal'", process::id());
+        Self::run("powershell", &["-NoProfile", "-Command", &script])
+    }
+
+    #[cfg(target_os = "linux")]
+    fn lower_io() -> Result<(), Error> {
+        Self::run("ionice", &["-c", "3", "-p", &process::id().to_string()])
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn lower_io() -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Must run before any thread or ffmpeg process is spawned so they inherit it
+    pub fn lower() {
+        for result in [Self::lower_cpu(), Self::lower_io()] {
+            if let Err(e) = result {
+                Logger::warning(format!("Failed to lower process priority: {}", e));
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::Error;
+use crate::format::Format;
+
+// Intermediate (mezzanine) presets for editing, each fixing the encoder, pixel format and container
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    ProresHq,
+    Prores4444,
+    DnxhrHqx,
+}
+
+impl Profile {
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Profile::ProresHq | Profile::Prores4444 => "prores_ks",
+            Profile::DnxhrHqx => "dnxhd",
+        }
+    }
+
+    pub fn pixel_format(&self) -> &'static str {
+        match self {
+            Profile::ProresHq | Profile::DnxhrHqx => "yuv422p10le",
+            Profile::Prores4444 => "yuv444p10le",
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        match self {
+            Profile::ProresHq | Profile::Prores4444 => Format::Mov,
+            Profile::DnxhrHqx => Format::Mxf,
+        }
+    }
+
+    pub fn get_args(&self, stream: usize) -> Vec<String> {
+        let profile = match self {
+            Profile::ProresHq => "hq",
+            Profile::Prores4444 => "4444",
+            Profile::DnxhrHqx => "dnxhr_hqx",
+        };
+        vec![format!("-profile:v:{}", stream), profile.to_string()]
+    }
+
+    pub fn validate(&self, encoder: &str, format: Option<Format>) -> Result<(), Error> {
+        if encoder != self.encoder() {
+            return Err(Error::new(format!("--profile {} encodes with {}, it cannot be combined with encoder {}", self, self.encoder(), encoder)));
+        }
+        match format {
+            Some(format) if format != self.format() => Err(Error::new(format!(
+                "--profile {} writes {} files, it cannot be combined with format {}", self, self.format(), format
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "prores-hq" => Ok(Profile::ProresHq),
+            "prores-4444" => Ok(Profile::Prores4444),
+            "dnxhr-hqx" => Ok(Profile::DnxhrHqx),
+            _ => Err(Error::new(format!("Invalid profile: {}. Must be prores-hq, prores-4444 or dnxhr-hqx", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::ProresHq => write!(f, "prores-hq"),
+            Profile::Prores4444 => write!(f, "prores-4444"),
+            Profile::DnxhrHqx => write!(f, "dnxhr-hqx"),
+        }
+    }
+}
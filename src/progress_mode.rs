@@ -0,0 +1,46 @@
+use crate::error::Error;
+
+use std::io::IsTerminal;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressMode {
+    Bar,
+    Plain,
+    Json,
+    None,
+}
+
+impl ProgressMode {
+    // Progress bars are drawn on stderr, so redirected or containerised runs get plain lines instead
+    pub fn detect() -> Self {
+        match std::io::stderr().is_terminal() {
+            true => ProgressMode::Bar,
+            false => ProgressMode::Plain,
+        }
+    }
+}
+
+impl std::str::FromStr for ProgressMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "bar" => Ok(ProgressMode::Bar),
+            "plain" => Ok(ProgressMode::Plain),
+            "json" => Ok(ProgressMode::Json),
+            "none" => Ok(ProgressMode::None),
+            _ => Err(Error::new(format!("Invalid progress mode: {}. Must be plain, bar, json or none", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for ProgressMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressMode::Bar => write!(f, "bar"),
+            ProgressMode::Plain => write!(f, "plain"),
+            ProgressMode::Json => write!(f, "json"),
+            ProgressMode::None => write!(f, "none"),
+        }
+    }
+}
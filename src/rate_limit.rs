@@ -0,0 +1,50 @@
+use crate::error::Error;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// Token bucket shared by every upscale worker of every file: each frame takes a token and tokens refill at `fps`.
+// Holding at most one token keeps the rate steady instead of catching up in bursts after a slow stretch
+#[derive(Clone)]
+pub struct RateLimit {
+    fps: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimit {
+    const CAPACITY: f64 = 1.0;
+
+    // Takes a token, waiting for the bucket to refill when it is empty
+    pub fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.fps;
+            bucket.tokens = (bucket.tokens + refill).min(Self::CAPACITY) - 1.0;
+            bucket.updated = now;
+            // a negative balance reserves the next token, so waiting workers are served in turn
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / self.fps))
+        };
+        if let Some(wait) = wait {
+            thread::sleep(wait);
+        }
+    }
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let fps = value.trim().parse::<f64>()
+            .ok()
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .ok_or_else(|| Error::new(format!("Invalid max fps: {}. Must be a number of frames per second above 0", value)))?;
+        Ok(Self { fps, bucket: Arc::new(Mutex::new(Bucket { tokens: Self::CAPACITY, updated: Instant::now() })) })
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+pub struct Remote;
+
+impl Remote {
+    const FALLBACK_NAME: &'static str = "remote";
+    const LIVE_SCHEMES: [&'static str; 8] = ["rtsp", "rtsps", "rtmp", "rtmps", "srt", "udp", "rtp", "tcp"];
+
+    // Any scheme ffmpeg has a protocol for, like https:// or smb://
+    pub fn is_url(path: &Path) -> bool {
+        let Some(path) = path.to_str() else {
+            return false;
+        };
+        path.split_once("://").is_some_and(|(scheme, _)| {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        })
+    }
+
+    // Streaming protocols, which have no end and no frame count
+    pub fn is_live(path: &Path) -> bool {
+        Self::is_url(path) && path.to_str()
+            .and_then(|path| path.split_once("://"))
+            .is_some_and(|(scheme, _)| Self::LIVE_SCHEMES.contains(&scheme.to_lowercase().as_str()))
+    }
+
+    // Local stand-in used to name outputs, for a URL its last path segment without query or fragment
+    pub fn file_path(input: &Path) -> PathBuf {
+        if !Self::is_url(input) {
+            return input.to_path_buf();
+        }
+
+        let url = input.to_string_lossy();
+        let (_, rest) = url.split_once("://").unwrap_or_default();
+        let path = rest.split(['?', '#']).next().unwrap_or_default();
+        let name = path
+            .split_once('/')
+            .and_then(|(_, path)| path.rsplit('/').find(|segment| !segment.is_empty()))
+            .unwrap_or(Self::FALLBACK_NAME);
+        PathBuf::from(name)
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::Error;
+use crate::job::Job;
+use crate::video::Video;
+use crate::logger::Logger;
+use crate::cancel::Cancel;
+
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Default)]
+struct Row {
+    input: PathBuf,
+    output: PathBuf,
+    status: Option<&'static str>,
+    input_size: Option<u64>,
+    output_size: Option<u64>,
+    input_resolution: Option<(usize, usize)>,
+    output_resolution: Option<(usize, usize)>,
+    model: Option<String>,
+    frames: usize,
+    elapsed: Duration,
+    warnings: Vec<String>,
+}
+
+impl Row {
+    fn resolution(resolution: Option<(usize, usize)>) -> String {
+        resolution.map_or(String::from("-"), |(width, height)| format!("{}x{}", width, height))
+    }
+
+    fn fps(&self) -> Option<f64> {
+        (self.frames > 0).then(|| self.frames as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON))
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "input": self.input.display().to_string(),
+            "output": self.output.display().to_string(),
+            "status": self.status,
+            "input_size": self.input_size,
+            "output_size": self.output_size,
+            "input_resolution": self.input_resolution.map(|_| Self::resolution(self.input_resolution)),
+            "output_resolution": self.output_resolution.map(|_| Self::resolution(self.output_resolution)),
+            "model": self.model,
+            "average_fps": self.fps(),
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+            "warnings": self.warnings,
+        })
+    }
+
+    fn to_csv(&self) -> String {
+        [
+            self.input.display().to_string(),
+            self.output.display().to_string(),
+            self.status.unwrap_or_default().to_string(),
+            self.input_size.map(|size| size.to_string()).unwrap_or_default(),
+            self.output_size.map(|size| size.to_string()).unwrap_or_default(),
+            self.input_resolution.map(|_| Self::resolution(self.input_resolution)).unwrap_or_default(),
+            self.output_resolution.map(|_| Self::resolution(self.output_resolution)).unwrap_or_default(),
+            self.model.clone().unwrap_or_default(),
+            self.fps().map(|fps| format!("{:.2}", fps)).unwrap_or_default(),
+            format!("{:.1}", self.elapsed.as_secs_f64()),
+            self.warnings.join("; "),
+        ].iter().map(|field| Report::csv_field(field)).collect::<Vec<_>>().join(",")
+    }
+}
+
+// Collects what happened to each file of a run, printed as a table at the end and optionally written as JSON or CSV
+#[derive(Clone, Default)]
+pub struct Report {
+    path: Option<PathBuf>,
+    enabled: bool,
+    rows: Arc<Mutex<Vec<Row>>>,
+}
+
+impl Report {
+    const CSV_HEADER: &'static str = "input,output,status,input_size,output_size,input_resolution,output_resolution,model,average_fps,elapsed_seconds,warnings";
+    const BYTES_PER_MIB: f64 = 1024.0 * 1024.0;
+
+    // Single-file runs only get a report when one is asked for
+    pub fn new(path: Option<PathBuf>, files: usize) -> Self {
+        Self { enabled: path.is_some() || files > 1, path, rows: Arc::default() }
+    }
+
+    pub fn validate_path(path: &Path) -> Result<(), Error> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") | Some("csv") => Ok(()),
+            _ => Err(Error::new(format!("Invalid report: {}. Must be a .json or .csv file", path.display()))),
+        }
+    }
+
+    fn with_row(&self, input: &Path, update: impl FnOnce(&mut Row)) {
+        if !self.enabled {
+            return;
+        }
+        let mut rows = self.rows.lock().unwrap();
+        match rows.iter_mut().find(|row| row.input == input) {
+            Some(row) => update(row),
+            None => {
+                let mut row = Row { input: input.to_path_buf(), ..Row::default() };
+                update(&mut row);
+                rows.push(row);
+            },
+        }
+    }
+
+    pub fn describe(&self, video: &Video) {
+        self.with_row(video.input, |row| {
+            // chapters and resumed parts are written elsewhere before they are joined into the output
+            if video.chapter.is_none() {
+                row.output = video.output.clone();
+            }
+            row.input_resolution = Some((video.get_original_width(), video.get_original_height()));
+            row.output_resolution = Some((video.width, video.height));
+            row.model = video.model.as_ref().map(ToString::to_string);
+        });
+    }
+
+    pub fn count_frames(&self, input: &Path, frames: usize) {
+        self.with_row(input, |row| row.frames += frames);
+    }
+
+    pub fn warn(&self, input: &Path, warning: String) {
+        self.with_row(input, |row| row.warnings.push(warning));
+    }
+
+    pub fn skip(&self, job: &Job) {
+        self.with_row(&job.input, |row| {
+            row.output = job.output.clone();
+            row.status = Some("skipped");
+        });
+    }
+
+    pub fn finish(&self, job: &Job, result: &Result<(), Error>, elapsed: Duration) {
+        self.with_row(&job.input, |row| {
+            if row.output.as_os_str().is_empty() {
+                row.output = job.output.clone();
+            }
+            row.status = match result {
+                Ok(()) => row.status.or(Some("completed")),
+                Err(_) if Cancel::requested() => Some("cancelled"),
+                Err(e) => {
+                    row.warnings.push(e.to_string());
+                    Some("failed")
+                },
+            };
+            row.input_size = fs::metadata(&job.input).ok().map(|metadata| metadata.len());
+            row.output_size = fs::metadata(&row.output).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len());
+            row.elapsed = elapsed;
+        });
+    }
+
+    fn csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn format_size(size: Option<u64>) -> String {
+        size.map_or(String::from("-"), |size| format!("{:.1} MiB", size as f64 / Self::BYTES_PER_MIB))
+    }
+
+    fn print(rows: &[Row]) {
+        Logger::info("Summary:");
+        Logger::info(format!(
+            "  {:<9} {:>11} {:>11} {:>23} {:<16} {:>7} {:>9}  {}",
+            "STATUS", "INPUT", "OUTPUT", "RESOLUTION", "MODEL", "FPS", "TIME", "FILE"
+        ));
+        for row in rows {
+            let resolution = format!("{} -> {}", Row::resolution(row.input_resolution), Row::resolution(row.output_resolution));
+            Logger::info(format!(
+                "  {:<9} {:>11} {:>11} {:>23} {:<16} {:>7} {:>8.1}s  {}",
+                row.status.unwrap_or("-"),
+                Self::format_size(row.input_size),
+                Self::format_size(row.output_size),
+                resolution,
+                row.model.as_deref().unwrap_or("-"),
+                row.fps().map_or(String::from("-"), |fps| format!("{:.1}", fps)),
+                row.elapsed.as_secs_f64(),
+                row.input.display()
+            ));
+            for warning in &row.warnings {
+                Logger::warning(format!("    {}", warning));
+            }
+        }
+    }
+
+    fn write(path: &Path, rows: &[Row]) -> Result<(), Error> {
+        let content = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => std::iter::once(Self::CSV_HEADER.to_string())
+                .chain(rows.iter().map(Row::to_csv))
+                .collect::<Vec<_>>()
+                .join("\n") + "\n",
+            _ => serde_json::to_string_pretty(&json!({ "files": rows.iter().map(Row::to_json).collect::<Vec<_>>() }))
+                .map_err(|e| Error::new(format!("Failed to serialize report: {}", e)))?,
+        };
+        fs::write(path, content).map_err(|e| Error::new(format!("Failed to write report {}: {}", path.display(), e)))
+    }
+
+    // A report that cannot be written is only a warning, the files themselves are done
+    pub fn complete(&self) {
+        if !self.enabled {
+            return;
+        }
+        let rows = self.rows.lock().unwrap();
+        if rows.is_empty() {
+            return;
+        }
+        Self::print(&rows);
+        if let Some(path) = &self.path {
+            if let Err(e) = Self::write(path, &rows) {
+                Logger::warning(e.to_string());
+            }
+        }
+    }
+}
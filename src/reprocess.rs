@@ -0,0 +1,140 @@
+use crate::error::Error;
+use crate::arguments::Arguments;
+use crate::binary::Binary;
+use crate::job::Job;
+use crate::logger::Logger;
+use crate::media_info::MediaInfo;
+use crate::pipeline::Pipeline;
+use crate::timestamp::Timestamp;
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReprocessRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl ReprocessRange {
+    const CLIP_CODEC: &'static str = "ffv1";
+    const CONCAT_LIST: &'static str = "parts.txt";
+
+    pub fn validate(&self, files: &[Job]) -> Result<(), Error> {
+        match files {
+            [job] if job.output.is_file() => Ok(()),
+            [job] => Err(Error::new(format!("--reprocess-range needs the existing output: {}", job.output.display()))),
+            _ => Err(Error::new("--reprocess-range works on a single input file")),
+        }
+    }
+
+    // Parts of the existing output are stream copies, so the range widens to its keyframes
+    fn snap(&self, keyframes: &[f64]) -> (f64, Option<f64>) {
+        let start = keyframes.iter().copied().filter(|&keyframe| keyframe <= self.start).fold(0.0, f64::max);
+        let end = keyframes.iter().copied().filter(|&keyframe| keyframe >= self.end).reduce(f64::min);
+        (start, end)
+    }
+
+    fn cut_input(job: &Job, start: f64, end: Option<f64>, clip: &Path) -> Result<(), Error> {
+        let mut args: Vec<OsString> = vec!["-hide_banner".into(), "-ss".into(), format!("{:.3}", start).into()];
+        if let Some(end) = end {
+            args.extend(["-to".into(), format!("{:.3}", end).into()]);
+        }
+        args.extend([
+            "-i".into(), Binary::path_arg(&job.input),
+            "-map".into(), format!("0:v:{}", job.settings.video_stream).into(),
+            "-c:v".into(), Self::CLIP_CODEC.into(), "-an".into(), "-sn".into(),
+            "-y".into(), Binary::path_arg(clip),
+        ]);
+        Binary::Ffmpeg.run(args, "cut the range to reprocess")
+    }
+
+    fn copy_part(output: &Path, seek: &[OsString], part: &Path) -> Result<(), Error> {
+        let mut args: Vec<OsString> = vec!["-hide_banner".into()];
+        args.extend(seek.iter().cloned());
+        args.extend([
+            "-i".into(), Binary::path_arg(output),
+            "-map".into(), "0:v:0".into(), "-c".into(), "copy".into(),
+            "-y".into(), Binary::path_arg(part),
+        ]);
+        Binary::Ffmpeg.run(args, "copy part of the existing output")
+    }
+
+    // The video is concatenated from the parts, every other stream is copied from the existing output
+    fn stitch(output: &Path, directory: &Path, parts: &[PathBuf], stitched: &Path) -> Result<(), Error> {
+        let list = parts.iter()
+            .filter_map(|part| part.file_name())
+            .map(|name| format!("file '{}'\n", name.to_string_lossy().replace('\'', "'\\''")))
+            .collect::<String>();
+        let list_path = directory.join(Self::CONCAT_LIST);
+        fs::write(&list_path, list)
+            .map_err(|e| Error::new(format!("Failed to write {}: {}", list_path.display(), e)))?;
+
+        Binary::Ffmpeg.run(vec![
+            "-hide_banner".into(), "-f".into(), "concat".into(), "-safe".into(), "0".into(),
+            "-i".into(), Binary::path_arg(&list_path), "-i".into(), Binary::path_arg(output),
+            "-map".into(), "0:v".into(), "-map".into(), "1".into(), "-map".into(), "-1:v".into(),
+            "-map_metadata".into(), "1".into(), "-c".into(), "copy".into(),
+            "-y".into(), Binary::path_arg(stitched),
+        ], "stitch the reprocessed range into the existing output")
+    }
+
+    fn run(&self, arguments: Arguments, job: &Job, directory: &Path) -> Result<(), Error> {
+        let (start, end) = self.snap(&MediaInfo::keyframes(&job.output)?);
+        Logger::info(format!(
+            "Reprocessing {} from {:.3}s to {} (widened to the keyframes of {})",
+            job.input.display(), start, end.map_or(String::from("the end"), |end| format!("{:.3}s", end)), job.output.display()
+        ));
+
+        let clip = directory.join("clip.mkv");
+        Self::cut_input(job, start, end, &clip)?;
+
+        let mut settings = job.settings.clone();
+        settings.video_stream = 0;
+        settings.format = None;
+        let mut middle = Job::new(clip, settings);
+        middle.output = directory.join("middle.mkv");
+        let mut parts = vec![middle.output.clone()];
+        Pipeline::execute(arguments.with_files(vec![middle]))?;
+
+        if start > 0.0 {
+            let head = directory.join("head.mkv");
+            Self::copy_part(&job.output, &["-t".into(), format!("{:.3}", start).into()], &head)?;
+            parts.insert(0, head);
+        }
+        if let Some(end) = end {
+            let tail = directory.join("tail.mkv");
+            Self::copy_part(&job.output, &["-ss".into(), format!("{:.3}", end).into()], &tail)?;
+            parts.push(tail);
+        }
+
+        let extension = job.output.extension().unwrap_or_default().to_string_lossy();
+        let stitched = directory.join(format!("stitched.{}", extension));
+        Self::stitch(&job.output, directory, &parts, &stitched)?;
+        fs::rename(&stitched, &job.output)
+            .map_err(|e| Error::new(format!("Failed to replace {}: {}", job.output.display(), e)))
+    }
+
+    pub fn execute(self, arguments: Arguments) -> Result<(), Error> {
+        let job = arguments.files[0].clone();
+        // Next to the output so the result can be renamed into place
+        let directory = job.output.with_file_name(format!(".reprocess-{}", process::id()));
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::new(format!("Failed to create {}: {}", directory.display(), e)))?;
+        let result = self.run(arguments, &job, &directory);
+        let _ = fs::remove_dir_all(&directory);
+        result
+    }
+}
+
+impl std::str::FromStr for ReprocessRange {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Timestamp::parse_range(value, "..")
+            .map(|(start, end)| Self { start, end })
+            .ok_or_else(|| Error::new(format!("Invalid reprocess range: {}. Must be START..END, e.g. 1:20:00..1:30:00", value)))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::error::Error;
+use crate::crop::Crop;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Roi {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Roi {
+    pub fn fits(&self, width: usize, height: usize) -> bool {
+        self.width > 0 && self.height > 0 && self.x + self.width <= width && self.y + self.height <= height
+    }
+
+    // The region is relative to the frame after any --crop
+    pub fn to_crop(self, crop: Option<Crop>) -> Crop {
+        let (x, y) = crop.map_or((0, 0), |crop| (crop.x, crop.y));
+        Crop { width: self.width, height: self.height, x: x + self.x, y: y + self.y }
+    }
+}
+
+impl std::str::FromStr for Roi {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<usize>>>();
+        match parts.as_deref() {
+            Some(&[x, y, width, height]) => Ok(Self { x, y, width, height }),
+            _ => Err(Error::new(format!("Invalid roi: {}. Must be X,Y,W,H", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for Roi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::error::Error;
+
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screenshots {
+    pub every: f64,
+    pub directory: PathBuf,
+}
+
+impl Screenshots {
+    const DEFAULT_EVERY: f64 = 300.0;
+    const DEFAULT_DIRECTORY: &'static str = "screenshots";
+
+    fn parse_interval(value: &str) -> Option<f64> {
+        let value = value.trim();
+        let (number, unit) = match value.char_indices().last()? {
+            (index, 'h') => (&value[..index], 3600.0),
+            (index, 'm') => (&value[..index], 60.0),
+            (index, 's') => (&value[..index], 1.0),
+            _ => (value, 1.0),
+        };
+        number.parse::<f64>().ok().filter(|&number| number > 0.0).map(|number| number * unit)
+    }
+
+    // Stills are named by their position in the source, like 00h05m00s_original.png
+    pub fn file_name(pts: f64, suffix: &str) -> String {
+        let seconds = pts.max(0.0) as u64;
+        format!("{:02}h{:02}m{:02}s_{}.png", seconds / 3600, seconds / 60 % 60, seconds % 60, suffix)
+    }
+}
+
+impl std::str::FromStr for Screenshots {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut screenshots = Self { every: Self::DEFAULT_EVERY, directory: PathBuf::from(Self::DEFAULT_DIRECTORY) };
+        for option in value.split(',').filter(|option| !option.trim().is_empty()) {
+            match option.split_once('=').map(|(key, value)| (key.trim(), value)) {
+                Some(("every", every)) => {
+                    screenshots.every = Self::parse_interval(every)
+                        .ok_or_else(|| Error::new(format!("Invalid screenshot interval: {}. Use seconds or a duration like 30s, 5m or 1h", every)))?;
+                },
+                Some(("dir", directory)) if !directory.is_empty() => screenshots.directory = PathBuf::from(directory),
+                _ => return Err(Error::new(format!("Invalid screenshots option: {}. Must be every=DURATION or dir=DIR", option))),
+            }
+        }
+        Ok(screenshots)
+    }
+}
+
+impl std::fmt::Display for Screenshots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "every={}s,dir={}", self.every, self.directory.display())
+    }
+}
@@ -0,0 +1,32 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// Rolling output for --segment-time without --package: numbered files next to the output
+pub struct Segments;
+
+impl Segments {
+    // out.mkv -> out-00000.mkv, out-00001.mkv, ...
+    pub fn pattern(output: &Path, name: &str, extension: &str) -> PathBuf {
+        let mut file_name = OsString::from(name);
+        file_name.push(format!("-%05d.{}", extension));
+        output.with_file_name(file_name)
+    }
+
+    // Segments can only be cut on a keyframe, so one is forced on every boundary
+    pub fn keyframe_args(seconds: f64) -> Vec<String> {
+        vec![String::from("-force_key_frames"), format!("expr:gte(t,n_forced*{})", seconds)]
+    }
+
+    pub fn get_args(seconds: f64, muxer: Option<String>) -> Vec<String> {
+        let mut args = Self::keyframe_args(seconds);
+        args.extend([
+            String::from("-f"), String::from("segment"),
+            String::from("-segment_time"), seconds.to_string(),
+            String::from("-reset_timestamps"), String::from("1"),
+        ]);
+        if let Some(muxer) = muxer {
+            args.extend([String::from("-segment_format"), muxer]);
+        }
+        args
+    }
+}
@@ -0,0 +1,55 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    pub start: usize,
+    pub count: usize,
+}
+
+impl Sequence {
+    const START_SEARCH_RANGE: usize = 10_000;
+
+    fn placeholder(file_name: &str) -> Option<(usize, usize, usize)> {
+        let start = file_name.rfind('%')?;
+        let digits = &file_name[start + 1..];
+        let end = digits.find('d')?;
+        let width = &digits[..end];
+        if !width.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some((start, start + end + 2, width.parse().unwrap_or(0)))
+    }
+
+    fn file_name(path: &Path) -> Option<&str> {
+        path.file_name()?.to_str()
+    }
+
+    pub fn is_pattern(path: &Path) -> bool {
+        Self::file_name(path).and_then(Self::placeholder).is_some()
+    }
+
+    pub fn frame_path(pattern: &Path, number: usize) -> PathBuf {
+        let Some(file_name) = Self::file_name(pattern) else {
+            return pattern.to_path_buf();
+        };
+        match Self::placeholder(file_name) {
+            Some((start, end, width)) => pattern.with_file_name(format!(
+                "{}{:0width$}{}", &file_name[..start], number, &file_name[end..], width = width
+            )),
+            None => pattern.to_path_buf(),
+        }
+    }
+
+    pub fn probe(pattern: &Path) -> Result<Self, Error> {
+        let start = (0..Self::START_SEARCH_RANGE)
+            .find(|&number| Self::frame_path(pattern, number).is_file())
+            .ok_or_else(|| Error::new(format!("No frames found for image sequence: {}", pattern.display())))?;
+        let count = (start..)
+            .take_while(|&number| Self::frame_path(pattern, number).is_file())
+            .count();
+
+        Ok(Self { start, count })
+    }
+}
@@ -0,0 +1,501 @@
+use crate::error::Error;
+use crate::format::Format;
+use crate::duplicate_mode::DuplicateMode;
+use crate::duplicate_detector::DuplicateDetector;
+use crate::duplicate_threshold::DuplicateThreshold;
+use crate::skip_mode::SkipMode;
+use crate::backend::Backend;
+use crate::chapters::ChapterSelection;
+use crate::chapter_output::ChapterOutput;
+use crate::frame_error_mode::FrameErrorMode;
+use crate::final_scale::FinalScale;
+use crate::aspect_policy::AspectPolicy;
+use crate::crop::CropMode;
+use crate::roi::Roi;
+use crate::telecine::Detelecine;
+use crate::tonemap::Tonemap;
+use crate::color::Range;
+use crate::hwdec::HwDecode;
+use crate::plugin::Plugin;
+use crate::external_upscaler::ExternalCommand;
+use crate::model::Model;
+use crate::av1::Av1Tuning;
+use crate::grain::Grain;
+use crate::subtitles::BurnSubtitles;
+use crate::watermark::Watermark;
+use crate::package::Package;
+use crate::external_tracks::{ExternalTrack, TrackKind};
+use crate::external_track_mode::ExternalTrackMode;
+use crate::frames_format::FramesFormat;
+use crate::bit_depth::BitDepth;
+use crate::profile::Profile;
+use crate::presets::Preset;
+use crate::screenshots::Screenshots;
+use crate::channel_capacity::ChannelCapacity;
+use crate::ffmpeg_args::FfmpegArgs;
+use crate::chain::Chain;
+use crate::capabilities::Capabilities;
+
+use std::path::PathBuf;
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub crop: Option<CropMode>,
+    pub roi: Option<Roi>,
+    pub roi_only: bool,
+    pub detelecine: Detelecine,
+    pub tonemap: Tonemap,
+    pub color_range: Option<Range>,
+    pub hwdec: HwDecode,
+    pub gpu_pipeline: bool,
+    pub video_stream: usize,
+    pub encoder: String,
+    pub format: Option<Format>,
+    pub model: String,
+    pub external_upscaler: Option<String>,
+    pub duplicate_threshold: DuplicateThreshold,
+    pub duplicate_mode: DuplicateMode,
+    pub duplicate_detector: DuplicateDetector,
+    pub max_duplicate_run: Option<usize>,
+    pub cpu_workers: usize,
+    pub tile_size: Option<u32>,
+    pub syncgap: Option<u8>,
+    pub on_skip: SkipMode,
+    pub on_frame_error: FrameErrorMode,
+    pub fps: Option<f64>,
+    pub segment_time: Option<f64>,
+    pub segment_name: Option<String>,
+    pub package: Option<Package>,
+    pub final_scale: FinalScale,
+    pub aspect_policy: AspectPolicy,
+    pub no_downscale: bool,
+    pub cache_dir: Option<String>,
+    pub svt_preset: Option<u8>,
+    pub film_grain: Option<u8>,
+    pub grain: Option<Grain>,
+    pub burn_subtitles: Option<BurnSubtitles>,
+    pub watermark: Option<Watermark>,
+    pub keep_frames: Option<PathBuf>,
+    pub frames_format: FramesFormat,
+    pub frames_only: bool,
+    pub screenshots: Option<Screenshots>,
+    pub channel_capacity: ChannelCapacity,
+    pub chain: Option<Chain>,
+    pub bit_depth: BitDepth,
+    pub profile: Option<Profile>,
+    pub external_tracks: Vec<ExternalTrack>,
+    pub external_track_mode: ExternalTrackMode,
+    pub preset: Option<Preset>,
+    pub quality_gate: bool,
+    pub verify: bool,
+    pub verify_frames: usize,
+    pub reference: Option<PathBuf>,
+    pub backend: Backend,
+    pub chapters: Option<ChapterSelection>,
+    pub chapter_output: ChapterOutput,
+    pub skip_static_segments: bool,
+    pub adaptive_scale: bool,
+    pub fragmented: bool,
+    pub input_args: FfmpegArgs,
+    pub encoder_args: FfmpegArgs,
+    pub output_args: FfmpegArgs,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            crop: None,
+            roi: None,
+            roi_only: false,
+            detelecine: Detelecine::Off,
+            tonemap: Tonemap::Off,
+            color_range: None,
+            hwdec: HwDecode::Off,
+            gpu_pipeline: false,
+            video_stream: 0,
+            encoder: String::from("libx264"),
+            format: None,
+            model: String::from("realesrgan"),
+            external_upscaler: None,
+            duplicate_threshold: DuplicateThreshold::Fixed(1.0),
+            duplicate_mode: DuplicateMode::Collapse,
+            duplicate_detector: DuplicateDetector::Fast,
+            max_duplicate_run: None,
+            cpu_workers: 0,
+            tile_size: None,
+            syncgap: None,
+            on_skip: SkipMode::Skip,
+            on_frame_error: FrameErrorMode::Abort,
+            fps: None,
+            segment_time: None,
+            segment_name: None,
+            package: None,
+            final_scale: FinalScale::Lanczos,
+            aspect_policy: AspectPolicy::Keep,
+            no_downscale: false,
+            cache_dir: None,
+            svt_preset: None,
+            film_grain: None,
+            grain: None,
+            burn_subtitles: None,
+            watermark: None,
+            keep_frames: None,
+            frames_format: FramesFormat::Png,
+            frames_only: false,
+            screenshots: None,
+            channel_capacity: ChannelCapacity::default(),
+            chain: None,
+            bit_depth: BitDepth::Eight,
+            profile: None,
+            external_tracks: Vec::new(),
+            external_track_mode: ExternalTrackMode::Add,
+            preset: None,
+            quality_gate: false,
+            verify: false,
+            verify_frames: 0,
+            reference: None,
+            backend: Backend::default_backend(),
+            chapters: None,
+            chapter_output: ChapterOutput::Join,
+            skip_static_segments: false,
+            adaptive_scale: false,
+            fragmented: false,
+            input_args: FfmpegArgs::default(),
+            encoder_args: FfmpegArgs::default(),
+            output_args: FfmpegArgs::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn parse_numeric<O: std::str::FromStr>(key: &str, value: &str) -> Result<O, Error> {
+        value.trim().parse().map_err(|_| Error::new(format!("Option '{}' must be a number", key)))
+    }
+
+    fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+        value.trim().parse().map_err(|_| Error::new(format!("Option '{}' must be true or false", key)))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key.replace('-', "_").as_str() {
+            "width" => self.width = Some(Self::parse_numeric(key, value)?),
+            "height" => self.height = Some(Self::parse_numeric(key, value)?),
+            "crop" => self.crop = Some(value.parse()?),
+            "roi" => self.roi = Some(value.parse()?),
+            "roi_only" => self.roi_only = Self::parse_bool(key, value)?,
+            "detelecine" => self.detelecine = value.parse()?,
+            "tonemap" => self.tonemap = value.parse()?,
+            "color_range" => self.color_range = Some(value.parse()?),
+            "hwdec" => self.hwdec = value.parse()?,
+            "gpu_pipeline" => self.gpu_pipeline = Self::parse_bool(key, value)?,
+            "video_stream" => self.video_stream = Self::parse_numeric(key, value)?,
+            "encoder" => self.encoder = value.to_string(),
+            "format" => self.format = Some(value.parse()?),
+            "model" => self.model = value.to_string(),
+            "external_upscaler" => self.set_external_upscaler(value),
+            "duplicate_threshold" => self.duplicate_threshold = value.parse()?,
+            "duplicates" => self.duplicate_mode = value.parse()?,
+            "duplicate_detector" => self.duplicate_detector = value.parse()?,
+            "max_duplicate_run" => self.max_duplicate_run = Some(Self::parse_numeric(key, value)?),
+            "final_scale" => self.final_scale = value.parse()?,
+            "ar_policy" => self.aspect_policy = value.parse()?,
+            "no_downscale" => self.no_downscale = Self::parse_bool(key, value)?,
+            "cache_dir" => self.cache_dir = Some(value.to_string()),
+            "fps" => self.fps = Some(Self::parse_numeric(key, value)?),
+            "segment_time" => self.segment_time = Some(Self::parse_numeric(key, value)?),
+            "segment_name" => self.segment_name = Some(value.to_string()),
+            "package" => self.package = Some(value.parse()?),
+            "on_skip" => self.on_skip = value.parse()?,
+            "on_frame_error" => self.on_frame_error = value.parse()?,
+            "cpu_workers" => self.cpu_workers = Self::parse_numeric(key, value)?,
+            "tile_size" => self.tile_size = Some(Self::parse_numeric(key, value)?),
+            "syncgap" => self.syncgap = Some(Self::parse_numeric(key, value)?),
+            "svt_preset" => self.svt_preset = Some(Self::parse_numeric(key, value)?),
+            "film_grain" => self.film_grain = Some(Self::parse_numeric(key, value)?),
+            "grain" => self.grain = Some(value.parse()?),
+            "burn_subtitles" => self.burn_subtitles = Some(value.parse()?),
+            "watermark" => self.watermark = Some(value.parse()?),
+            "add_audio" => self.external_tracks.push(ExternalTrack::parse(TrackKind::Audio, value)?),
+            "add_subs" => self.external_tracks.push(ExternalTrack::parse(TrackKind::Subtitle, value)?),
+            "external_tracks" => self.external_track_mode = value.parse()?,
+            "bit_depth" => self.bit_depth = value.parse()?,
+            "profile" => self.set_profile(value.parse()?),
+            "preset" => self.set_preset(value.parse()?),
+            "skip_static_segments" => self.skip_static_segments = Self::parse_bool(key, value)?,
+            "adaptive_scale" => self.adaptive_scale = Self::parse_bool(key, value)?,
+            "fragmented" => self.fragmented = Self::parse_bool(key, value)?,
+            "quality_gate" => self.quality_gate = Self::parse_bool(key, value)?,
+            "verify" => self.verify = Self::parse_bool(key, value)?,
+            "verify_frames" => self.verify_frames = value.trim().parse()
+                .map_err(|_| Error::new(format!("Invalid verify frames: {}. Must be a number of frames", value.trim())))?,
+            "backend" => self.backend = value.parse()?,
+            "chapters" => self.chapters = Some(value.parse()?),
+            "chapter_output" => self.chapter_output = value.parse()?,
+            "chain" => self.chain = Some(value.parse()?),
+            "channel_capacity" => self.channel_capacity = value.parse()?,
+            "input_args" => self.input_args = value.parse()?,
+            "encoder_args" => self.encoder_args = value.parse()?,
+            "output_args" => self.output_args = value.parse()?,
+            "screenshots" => self.screenshots = Some(value.parse()?),
+            "keep_frames" => self.keep_frames = Some(PathBuf::from(value)),
+            "reference" => self.reference = Some(PathBuf::from(value)),
+            "frames_format" => self.frames_format = value.parse()?,
+            "frames_only" => self.frames_only = Self::parse_bool(key, value)?,
+            _ => return Err(Error::new(format!("Invalid option: {}", key))),
+        }
+        Ok(())
+    }
+
+    pub fn set_external_upscaler(&mut self, command: &str) {
+        self.external_upscaler = Some(command.to_string());
+        self.model = ExternalCommand::MODEL.to_string();
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = Some(profile);
+        self.encoder = profile.encoder().to_string();
+        self.format = Some(profile.format());
+    }
+
+    pub fn set_preset(&mut self, preset: Preset) {
+        self.preset = Some(preset);
+        self.encoder = preset.encoder().to_string();
+        self.format = Some(preset.format());
+    }
+
+    pub fn validate(&self, encoders: &str) -> Result<(), Error> {
+        self.validate_encoder(encoders)?;
+        self.validate_format()?;
+        self.av1_tuning().validate(&self.encoder)?;
+        Capabilities::get().validate(self)?;
+        if self.film_grain.is_some() && self.grain.is_some_and(|grain| grain.get_film_grain(&self.encoder).is_some()) {
+            return Err(Error::new("--film-grain and --grain synthesize both set the AV1 film grain, use one of them"));
+        }
+        match (self.profile, self.preset) {
+            (Some(profile), Some(preset)) => return Err(Error::new(format!("--profile {} cannot be combined with --preset {}", profile, preset))),
+            (Some(profile), None) => profile.validate(&self.encoder, self.format)?,
+            (None, Some(preset)) => preset.validate(&self.encoder, self.format)?,
+            (None, None) => self.bit_depth.pixel_format(&self.encoder).map(|_| ())?,
+        }
+        self.burn_subtitles.as_ref().map_or(Ok(()), BurnSubtitles::validate)?;
+        self.watermark.as_ref().map_or(Ok(()), Watermark::validate)?;
+        self.validate_external_tracks()?;
+        self.validate_model()?;
+        self.validate_fps()?;
+        self.validate_segment_time()?;
+        self.validate_package()?;
+        self.validate_segment_name()?;
+        self.validate_max_duplicate_run()?;
+        self.validate_keep_frames()?;
+        self.validate_reference()?;
+        self.validate_gpu_pipeline()?;
+        self.validate_roi()?;
+        self.validate_resolution_and_scale()
+    }
+
+    pub fn av1_tuning(&self) -> Av1Tuning {
+        let synthesized = self.grain.and_then(|grain| grain.get_film_grain(&self.encoder));
+        Av1Tuning { preset: self.svt_preset, film_grain: self.film_grain.or(synthesized) }
+    }
+
+    fn validate_external_tracks(&self) -> Result<(), Error> {
+        for track in &self.external_tracks {
+            track.validate()?;
+            if track.kind == TrackKind::Subtitle && self.format.is_some_and(|format| !format.supports_subtitles()) {
+                return Err(Error::new(format!("--add-subs {}: this format has no subtitles", track.path.display())));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_keep_frames(&self) -> Result<(), Error> {
+        if self.frames_only && self.keep_frames.is_none() {
+            return Err(Error::new("--frames-only requires --keep-frames"));
+        }
+        if self.frames_only && self.chapters.is_some() {
+            return Err(Error::new("--chapters cannot be combined with --frames-only"));
+        }
+
+        Ok(())
+    }
+
+    fn validate_reference(&self) -> Result<(), Error> {
+        match &self.reference {
+            Some(reference) if !reference.is_file() => Err(Error::new(format!("Reference not found: {}", reference.display()))),
+            Some(_) if self.frames_only => Err(Error::new("--reference cannot be combined with --frames-only")),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_gpu_pipeline(&self) -> Result<(), Error> {
+        if !self.gpu_pipeline {
+            return Ok(());
+        }
+        if !self.encoder.ends_with("_nvenc") {
+            return Err(Error::new(format!("--gpu-pipeline encodes with nvenc, it cannot be combined with encoder {}", self.encoder)));
+        }
+        if !matches!(self.hwdec, HwDecode::Off | HwDecode::Nvdec) {
+            return Err(Error::new(format!("--gpu-pipeline decodes with nvdec, it cannot be combined with --hwdec {}", self.hwdec)));
+        }
+        Ok(())
+    }
+
+    fn validate_roi(&self) -> Result<(), Error> {
+        if self.roi_only && self.roi.is_none() {
+            return Err(Error::new("--roi-only requires --roi"));
+        }
+        if self.roi.is_some() && !self.roi_only && self.chain.is_some() {
+            return Err(Error::new("--roi cannot be combined with --chain, use --roi-only to upscale just the region"));
+        }
+
+        Ok(())
+    }
+
+    fn validate_fps(&self) -> Result<(), Error> {
+        match self.fps {
+            Some(fps) if fps.is_nan() || fps <= 0.0 => Err(Error::new(format!("Invalid fps: {}. Must be greater than 0", fps))),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_segment_time(&self) -> Result<(), Error> {
+        match self.segment_time {
+            Some(seconds) if seconds.is_nan() || seconds <= 0.0 => Err(Error::new(format!("Invalid segment time: {}. Must be greater than 0", seconds))),
+            Some(_) if self.frames_only => Err(Error::new("--segment-time cannot be combined with --frames-only")),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_package(&self) -> Result<(), Error> {
+        match self.package {
+            Some(_) if self.frames_only => Err(Error::new("--package cannot be combined with --frames-only")),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_segment_name(&self) -> Result<(), Error> {
+        match &self.segment_name {
+            Some(name) if name.is_empty() || name.contains(['/', '\\']) => {
+                Err(Error::new(format!("Invalid segment name: {}. Must be a file name without folders", name)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_max_duplicate_run(&self) -> Result<(), Error> {
+        match self.max_duplicate_run {
+            Some(0) => Err(Error::new("Invalid max duplicate run: 0. Must be at least 1")),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_model_name(model: &str) -> Result<(), Error> {
+        match model {
+            "auto" | "realcugan" | "realcugan-pro" | "realesrgan" | "realesrgan-anime" | "realesr-anime" | ExternalCommand::MODEL => Ok(()),
+            model => match Plugin::name_of(model) {
+                Some(name) => Plugin::load(name).map(|_| ()),
+                None => Err(Error::new(format!(
+                    "Invalid model: {}. Must be auto, realcugan, realcugan-pro, realesrgan, realesrgan-anime, realesr-anime, external or plugin:NAME", model
+                ))),
+            },
+        }
+    }
+
+    fn validate_model(&self) -> Result<(), Error> {
+        Self::validate_model_name(&self.model)?;
+        self.validate_syncgap()?;
+        self.validate_backend()?;
+        self.validate_external_upscaler()?;
+        self.validate_chain()
+    }
+
+    fn validate_external_upscaler(&self) -> Result<(), Error> {
+        let chain_models = self.chain.iter().flat_map(|chain| &chain.steps).filter_map(|step| step.model.as_deref());
+        let uses_external = std::iter::once(self.model.as_str()).chain(chain_models).any(|model| model == ExternalCommand::MODEL);
+        match &self.external_upscaler {
+            Some(command) => ExternalCommand::validate(command),
+            None if uses_external => Err(Error::new("-m external requires --external-upscaler")),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_backend(&self) -> Result<(), Error> {
+        let chain_models = self.chain.iter().flat_map(|chain| &chain.steps).filter_map(|step| step.model.as_deref());
+        let is_plugin = |model: &str| Plugin::name_of(model).is_some() || model == ExternalCommand::MODEL;
+        let models = std::iter::once(self.model.as_str()).chain(chain_models).collect::<Vec<_>>();
+        if self.backend == Backend::CoreMl && models.iter().any(|model| is_plugin(model)) {
+            return Err(Error::new("--backend coreml only runs the built-in models, plugins and external upscalers bring their own backend"));
+        }
+        if !Backend::GPU_AVAILABLE && models.iter().any(|model| !is_plugin(model)) {
+            return Err(Error::new("This build has no ncnn backend for the built-in models, use a plugin or -m external"));
+        }
+        Ok(())
+    }
+
+    // Steps using the auto model are checked once the content is detected
+    fn validate_chain(&self) -> Result<(), Error> {
+        for step in self.chain.iter().flat_map(|chain| &chain.steps) {
+            let model = step.model.as_deref().unwrap_or(&self.model);
+            if step.model.is_some() && model == "auto" {
+                return Err(Error::new("Chain steps cannot use the auto model"));
+            }
+            Self::validate_model_name(model)?;
+            if model != "auto" && !Model::supported_scales(model).contains(&step.scale) {
+                return Err(Error::new(format!("Model {} does not support x{} in --chain", model, step.scale)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_syncgap(&self) -> Result<(), Error> {
+        let Some(syncgap) = self.syncgap else {
+            return Ok(());
+        };
+        if !matches!(self.model.as_str(), "auto" | "realcugan" | "realcugan-pro") {
+            return Err(Error::new(format!("--syncgap requires a realcugan model, not {}", self.model)));
+        }
+        if syncgap > Model::MAX_SYNCGAP {
+            return Err(Error::new(format!("Invalid syncgap: {}. Must be between 0 and {}", syncgap, Model::MAX_SYNCGAP)));
+        }
+
+        Ok(())
+    }
+
+    fn validate_format(&self) -> Result<(), Error> {
+        match self.format {
+            Some(format) if !format.supports_encoder(&self.encoder) => {
+                Err(Error::new(format!("Encoder {} is not compatible with the {} format", self.encoder, format)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_resolution_and_scale(&self) -> Result<(), Error> {
+        if let Some(width) = self.width {
+            if !(16..=7680).contains(&width) {
+                return Err(Error::new(format!("Invalid width: {}. Must be between 16 and 7680", width)));
+            }
+        }
+
+        if let Some(height) = self.height {
+            if !(16..=4320).contains(&height) {
+                return Err(Error::new(format!("Invalid height: {}. Must be between 16 and 4320", height)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_encoder(&self, encoders: &str) -> Result<(), Error> {
+        if !encoders.contains(&self.encoder) {
+            return Err(Error::new(format!("Invalid encoder: {}. Available encoders: {}", self.encoder, encoders)));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::error::Error;
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// Per-file options read from `<input>.upscale.toml`, a flat list of `key = value` lines using the manifest keys
+pub struct Sidecar;
+
+impl Sidecar {
+    const SUFFIX: &'static str = ".upscale.toml";
+
+    pub fn path(input: &Path) -> PathBuf {
+        let mut path = OsString::from(input.as_os_str());
+        path.push(Self::SUFFIX);
+        PathBuf::from(path)
+    }
+
+    fn parse_value(value: &str) -> Option<String> {
+        let value = value.trim();
+        if let Some(quoted) = value.strip_prefix('"') {
+            let (text, rest) = quoted.split_once('"')?;
+            let rest = rest.trim();
+            return (rest.is_empty() || rest.starts_with('#')).then(|| text.to_string());
+        }
+        let value = value.split_once('#').map_or(value, |(value, _)| value).trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Vec<(String, String)>, Error> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                line.split_once('=')
+                    .and_then(|(key, value)| Some((key.trim().trim_matches('"').to_string(), Self::parse_value(value)?)))
+                    .ok_or_else(|| Error::new(format!("{} line {}: expected key = value", path.display(), index + 1)))
+            })
+            .collect()
+    }
+
+    pub fn load(input: &Path) -> Result<Option<Vec<(String, String)>>, Error> {
+        let path = Self::path(input);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| Error::new(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&path, &content).map(Some)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkipMode {
+    Copy,
+    Transcode,
+    Skip,
+}
+
+impl std::str::FromStr for SkipMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "copy" => Ok(SkipMode::Copy),
+            "transcode" => Ok(SkipMode::Transcode),
+            "skip" => Ok(SkipMode::Skip),
+            _ => Err(Error::new(format!("Invalid on-skip mode: {}. Must be copy, transcode or skip", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for SkipMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipMode::Copy => write!(f, "copy"),
+            SkipMode::Transcode => write!(f, "transcode"),
+            SkipMode::Skip => write!(f, "skip"),
+        }
+    }
+}
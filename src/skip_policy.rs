@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::binary::Binary;
+use crate::media_info::MediaInfo;
+use crate::logger::Logger;
+
+use std::path::Path;
+use std::process::Stdio;
+
+// What to do with an output that already exists; --replace-output always redoes it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkipPolicy {
+    Exists,
+    Valid,
+    Never,
+}
+
+impl SkipPolicy {
+    const TOLERANCE: f64 = 0.01;
+    const MIN_TOLERANCE: f64 = 0.5;
+    const TAIL_SECONDS: f64 = 3.0;
+
+    // The last seconds are where a crashed run leaves a truncated file
+    fn decodes_tail(output: &Path) -> bool {
+        Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-v", "error", "-xerror", "-sseof", &format!("-{}", Self::TAIL_SECONDS)])
+            .arg("-i").arg(Binary::path_arg(output))
+            .args(["-map", "0:v:0", "-f", "null", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    fn check(output: &Path, expected: Option<f64>) -> Result<(), String> {
+        let media = MediaInfo::probe(output).map_err(|e| e.to_string())?;
+        let duration = media.duration.ok_or("it has no duration")?;
+        if let Some(expected) = expected {
+            if (duration - expected).abs() > (expected * Self::TOLERANCE).max(Self::MIN_TOLERANCE) {
+                return Err(format!("it lasts {:.2}s, expected {:.2}s", duration, expected));
+            }
+        }
+        if !Self::decodes_tail(output) {
+            return Err(String::from("its last frames cannot be decoded"));
+        }
+        Ok(())
+    }
+
+    // expected is only probed when the output has to be checked
+    pub fn skips(&self, output: &Path, expected: impl FnOnce() -> Option<f64>) -> bool {
+        match self {
+            SkipPolicy::Exists => true,
+            SkipPolicy::Never => false,
+            SkipPolicy::Valid => match Self::check(output, expected()) {
+                Ok(()) => true,
+                Err(reason) => {
+                    Logger::info(format!("Redoing {}: {}", output.display(), reason));
+                    false
+                },
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for SkipPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "exists" => Ok(SkipPolicy::Exists),
+            "valid" => Ok(SkipPolicy::Valid),
+            "never" => Ok(SkipPolicy::Never),
+            _ => Err(Error::new(format!("Invalid skip policy: {}. Must be exists, valid or never", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for SkipPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipPolicy::Exists => write!(f, "exists"),
+            SkipPolicy::Valid => write!(f, "valid"),
+            SkipPolicy::Never => write!(f, "never"),
+        }
+    }
+}
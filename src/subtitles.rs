@@ -0,0 +1,64 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BurnSubtitles {
+    Stream(usize),
+    File(PathBuf),
+}
+
+impl BurnSubtitles {
+    pub fn escape(path: &Path) -> String {
+        let escape_chars = |value: &str, chars: &[char]| value.chars().fold(String::new(), |mut escaped, c| {
+            if chars.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        });
+        let option = escape_chars(&path.to_string_lossy(), &['\\', '\'', ':']);
+        escape_chars(&option, &['\\', '\'', '[', ']', ',', ';'])
+    }
+
+    // libass scales styles from the script's PlayRes to the frame it draws on, so burning after
+    // the upscale renders text at the output resolution
+    pub fn get_filter(&self, input: &Path) -> String {
+        match self {
+            BurnSubtitles::Stream(index) => format!("subtitles=filename={}:si={}", Self::escape(input), index),
+            BurnSubtitles::File(path) => format!("subtitles=filename={}", Self::escape(path)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            BurnSubtitles::File(path) if !path.is_file() => {
+                Err(Error::new(format!("Subtitle file not found: {}", path.display())))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for BurnSubtitles {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.is_empty() {
+            return Err(Error::new("Invalid burn-subtitles value: must be a subtitle stream index or a file"));
+        }
+        Ok(match value.parse() {
+            Ok(index) => BurnSubtitles::Stream(index),
+            Err(_) => BurnSubtitles::File(PathBuf::from(value)),
+        })
+    }
+}
+
+impl std::fmt::Display for BurnSubtitles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BurnSubtitles::Stream(index) => write!(f, "subtitle stream {}", index),
+            BurnSubtitles::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::error::Error;
+use crate::binary::Binary;
+
+use std::path::Path;
+use std::process::Stdio;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Detelecine {
+    Auto,
+    On,
+    Off,
+}
+
+impl Detelecine {
+    const SAMPLE_FRAMES: usize = 500;
+    const TELECINE_FRAME_RATE: f64 = 30000.0 / 1001.0;
+    const REPEATED_FIELD_RATIO: f64 = 0.2;
+    pub const DECIMATION: f64 = 4.0 / 5.0;
+    pub const FILTER: &'static str = "fieldmatch,decimate";
+
+    fn parse_count(line: &str, label: &str) -> usize {
+        line.split(label)
+            .nth(1)
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn detect(input: &Path, stream: usize, frame_rate: f64) -> Result<bool, Error> {
+        if (frame_rate - Self::TELECINE_FRAME_RATE).abs() > 0.01 {
+            return Ok(false);
+        }
+
+        let output = Binary::Ffmpeg.command()
+            .args(["-hide_banner", "-i"])
+            .arg(Binary::path_arg(input))
+            .args(["-map", &format!("0:v:{}", stream)])
+            .args([
+                "-vf", "idet",
+                "-frames:v", &Self::SAMPLE_FRAMES.to_string(),
+                "-an",
+                "-f", "null",
+                "-"
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .output()
+            .map_err(|e| Error::new(format!("Failed to execute ffmpeg: {}", e)))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let Some(line) = stderr.lines().find(|line| line.contains("Repeated Fields:")) else {
+            return Ok(false);
+        };
+
+        let neither = Self::parse_count(line, "Neither:");
+        let repeated = Self::parse_count(line, "Top:") + Self::parse_count(line, "Bottom:");
+        let total = neither + repeated;
+        Ok(total > 0 && repeated as f64 / total as f64 >= Self::REPEATED_FIELD_RATIO)
+    }
+
+    pub fn resolve(&self, input: &Path, stream: usize, frame_rate: f64) -> Result<bool, Error> {
+        match self {
+            Detelecine::On => Ok(true),
+            Detelecine::Off => Ok(false),
+            Detelecine::Auto => Self::detect(input, stream, frame_rate),
+        }
+    }
+}
+
+impl std::str::FromStr for Detelecine {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(Detelecine::Auto),
+            "on" => Ok(Detelecine::On),
+            "off" => Ok(Detelecine::Off),
+            _ => Err(Error::new(format!("Invalid detelecine mode: {}. Must be auto, on or off", value))),
+        }
+    }
+}
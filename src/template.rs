@@ -0,0 +1,74 @@
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::Format;
+use crate::organize::Episode;
+
+pub struct OutputTemplate;
+
+impl OutputTemplate {
+    pub const DEFAULT: &'static str = "{stem}_converted.{ext}";
+    const SECONDS_PER_DAY: u64 = 86_400;
+
+    fn replace(text: &OsStr, token: &str, value: &OsStr) -> OsString {
+        let token = token.as_bytes();
+        let mut rest = text.as_encoded_bytes();
+        let mut output = OsString::new();
+        while let Some(position) = rest.windows(token.len()).position(|window| window == token) {
+            // SAFETY: splitting right before and after an ASCII token keeps both halves valid encoded bytes
+            output.push(unsafe { OsStr::from_encoded_bytes_unchecked(&rest[..position]) });
+            output.push(value);
+            rest = &rest[position + token.len()..];
+        }
+        // SAFETY: see above, the remainder starts right after a token or is the whole input
+        output.push(unsafe { OsStr::from_encoded_bytes_unchecked(rest) });
+        output
+    }
+
+    pub fn render(template: &OsStr, tokens: &[(&str, &OsStr)]) -> OsString {
+        tokens.iter().fold(template.to_os_string(), |output, (name, value)| {
+            Self::replace(&output, &format!("{{{}}}", name), value)
+        })
+    }
+
+    pub fn render_file_name(template: &str, input: &Path, format: Option<Format>) -> OsString {
+        let stem = input.file_stem().unwrap_or_default();
+        let ext = match format {
+            Some(format) => OsStr::new(format.extension()),
+            None => input.extension().unwrap_or_default(),
+        };
+        let date = Self::date();
+        // episode tokens are empty for files without an SxxEyy marker
+        let episode = Episode::detect(input);
+        let (season, number, show) = episode
+            .map(|episode| (episode.season(), episode.episode(), episode.show))
+            .unwrap_or_default();
+        Self::render(OsStr::new(template), &[
+            ("stem", stem),
+            ("ext", ext),
+            ("date", OsStr::new(&date)),
+            ("show", OsStr::new(&show)),
+            ("season", OsStr::new(&season)),
+            ("episode", OsStr::new(&number)),
+        ])
+    }
+
+    fn date() -> String {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() / Self::SECONDS_PER_DAY)
+            .unwrap_or_default() as i64;
+
+        let shifted = days + 719_468;
+        let era = shifted.div_euclid(146_097);
+        let day_of_era = shifted.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
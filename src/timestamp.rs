@@ -0,0 +1,18 @@
+pub struct Timestamp;
+
+impl Timestamp {
+    // Seconds or [HH:]MM:SS, with optional fractions
+    pub fn parse(value: &str) -> Option<f64> {
+        value.trim().rsplit(':').enumerate().try_fold(0.0, |seconds, (index, part)| {
+            let part = part.parse::<f64>().ok().filter(|part| *part >= 0.0)?;
+            (index < 3).then(|| seconds + part * 60f64.powi(index as i32))
+        })
+    }
+
+    pub fn parse_range(value: &str, separator: &str) -> Option<(f64, f64)> {
+        value
+            .split_once(separator)
+            .and_then(|(start, end)| Some((Self::parse(start)?, Self::parse(end)?)))
+            .filter(|(start, end)| end > start)
+    }
+}
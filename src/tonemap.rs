@@ -0,0 +1,45 @@
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tonemap {
+    Hable,
+    Reinhard,
+    Off,
+}
+
+impl Tonemap {
+    const HDR_TRANSFERS: [&'static str; 2] = ["smpte2084", "arib-std-b67"];
+    pub const SDR_TAGS: [&'static str; 4] = [
+        "-color_primaries:v:0", "bt709",
+        "-color_trc:v:0", "bt709",
+    ];
+
+    pub fn is_hdr(color_transfer: &str) -> bool {
+        Self::HDR_TRANSFERS.contains(&color_transfer)
+    }
+
+    pub fn get_filter(&self) -> Option<String> {
+        let algorithm = match self {
+            Tonemap::Hable => "hable",
+            Tonemap::Reinhard => "reinhard",
+            Tonemap::Off => return None,
+        };
+        Some(format!(
+            "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+            algorithm
+        ))
+    }
+}
+
+impl std::str::FromStr for Tonemap {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "hable" => Ok(Tonemap::Hable),
+            "reinhard" => Ok(Tonemap::Reinhard),
+            "off" => Ok(Tonemap::Off),
+            _ => Err(Error::new(format!("Invalid tonemap: {}. Must be hable, reinhard or off", value))),
+        }
+    }
+}
@@ -1,8 +1,52 @@
-use crate::arguments::Arguments;
+use crate::job::Job;
+use crate::settings::Settings;
 use crate::model::Model;
+use crate::plugin::Plugin;
+use crate::external_upscaler::ExternalCommand;
+use crate::format::Format;
+use crate::content::Content;
+use crate::duplicate_mode::DuplicateMode;
+use crate::duplicate_detector::DuplicateDetector;
+use crate::duplicate_threshold::DuplicateThreshold;
+use crate::skip_mode::SkipMode;
+use crate::frame_error_mode::FrameErrorMode;
+use crate::template::OutputTemplate;
+use crate::sequence::Sequence;
+use crate::final_scale::FinalScale;
+use crate::aspect_policy::AspectPolicy;
+use crate::crop::{Crop, CropMode};
+use crate::roi::Roi;
 use crate::error::Error;
+use crate::logger::Logger;
+use crate::telecine::Detelecine;
+use crate::tonemap::Tonemap;
+use crate::hwdec::HwDecode;
+use crate::color::Color;
+use crate::media_info::{MediaInfo, StreamInfo, StreamKind};
+use crate::av1::Av1Tuning;
+use crate::grain::Grain;
+use crate::subtitles::BurnSubtitles;
+use crate::watermark::Watermark;
+use crate::frames_format::FramesFormat;
+use crate::bit_depth::BitDepth;
+use crate::profile::Profile;
+use crate::external_tracks::{ExternalTrack, TrackKind};
+use crate::external_track_mode::ExternalTrackMode;
+use crate::presets::Preset;
+use crate::screenshots::Screenshots;
+use crate::channel_capacity::ChannelCapacity;
+use crate::chain::{Chain, ChainPass};
+use crate::remote::Remote;
+use crate::segments::Segments;
+use crate::package::Package;
+use crate::binary::Binary;
+use crate::backend::Backend;
+use crate::chapters::Chapter;
+use crate::ffmpeg_args::FfmpegArgs;
 
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::thread;
 
 #[derive(Clone)]
 pub struct Video<'a> {
@@ -11,158 +55,827 @@ pub struct Video<'a> {
     pub frame_rate: f64,
     pub frame_count: usize,
     pub model: Option<Model>,
-    pub input: &'a str,
-    pub output: &'a str,
+    // the 2x model low-detail scenes are routed through with --adaptive-scale
+    pub adaptive_model: Option<Model>,
+    pub input: &'a Path,
+    pub output: PathBuf,
     pub encoder: &'a str,
-    pub duplicate_threshold: f64,
+    pub format: Option<Format>,
+    pub duplicate_threshold: DuplicateThreshold,
+    pub duplicate_mode: DuplicateMode,
+    pub duplicate_detector: DuplicateDetector,
+    pub max_duplicate_run: Option<usize>,
     pub scale: usize,
+    pub rotation: u16,
+    pub crop: Option<Crop>,
+    pub roi: Option<Roi>,
+    pub detelecine: bool,
+    pub tonemap: Tonemap,
+    pub hwdec: HwDecode,
+    pub gpu_pipeline: bool,
+    pub color: Color,
+    pub stream: usize,
+    pub cpu_workers: usize,
+    pub tile_size: Option<u32>,
+    pub on_skip: SkipMode,
+    pub on_frame_error: FrameErrorMode,
+    pub sequence: Option<Sequence>,
+    pub final_scale: FinalScale,
+    pub aspect_policy: AspectPolicy,
+    pub cache_dir: Option<&'a str>,
+    pub av1: Av1Tuning,
+    pub grain: Option<Grain>,
+    pub burn_subtitles: Option<&'a BurnSubtitles>,
+    pub watermark: Option<&'a Watermark>,
+    pub media: Option<MediaInfo>,
+    pub keep_frames: Option<&'a Path>,
+    pub frames_format: FramesFormat,
+    pub frames_only: bool,
+    pub screenshots: Option<&'a Screenshots>,
+    pub channel_capacity: &'a ChannelCapacity,
+    pub chain: Vec<ChainPass>,
+    pub bit_depth: BitDepth,
+    pub pixel_format: &'static str,
+    pub profile: Option<Profile>,
+    pub external_tracks: &'a [ExternalTrack],
+    pub external_track_mode: ExternalTrackMode,
+    pub preset: Option<Preset>,
+    pub quality_gate: bool,
+    pub verify: bool,
+    pub verify_frames: usize,
+    pub reference: Option<&'a Path>,
+    pub backend: Backend,
+    pub chapter: Option<Chapter>,
+    pub skip_static_segments: bool,
+    pub fragmented: bool,
+    // a stream with no end, whose frame count stays 0
+    pub live: bool,
+    pub segment_time: Option<f64>,
+    pub segment_name: Option<&'a str>,
+    pub package: Option<Package>,
+    pub input_args: &'a FfmpegArgs,
+    pub encoder_args: &'a FfmpegArgs,
+    pub output_args: &'a FfmpegArgs,
     original_width: usize,
     original_height: usize,
+    sample_aspect_ratio: Option<(usize, usize)>,
 }
 
 impl<'a> Video<'a> {
-    pub fn new(arguments: &'a Arguments, input: &'a str, output: &'a str) -> Result<Self, Error> {
+    const DEFAULT_SEQUENCE_FRAME_RATE: f64 = 24.0;
+    const PARTIAL_SUFFIX: &'static str = ".part";
+
+    pub fn new(job: &'a Job) -> Result<Self, Error> {
+        let settings = &job.settings;
         let mut video = Self {
             width: 0,
             height: 0,
             original_width: 0,
             original_height: 0,
+            sample_aspect_ratio: None,
             frame_rate: 0.0,
             frame_count: 0,
             scale: 2,
+            rotation: 0,
+            crop: None,
+            roi: None,
+            detelecine: false,
+            tonemap: Tonemap::Off,
+            hwdec: if settings.gpu_pipeline { HwDecode::Nvdec } else { settings.hwdec },
+            gpu_pipeline: settings.gpu_pipeline,
+            color: Color::SDR,
+            stream: settings.video_stream,
             model: None,
-            input,
-            output,
-            encoder: &arguments.encoder,
-            duplicate_threshold: arguments.duplicate_threshold,
+            adaptive_model: None,
+            input: &job.input,
+            output: job.output.clone(),
+            encoder: &settings.encoder,
+            format: settings.format,
+            duplicate_threshold: settings.duplicate_threshold,
+            duplicate_mode: settings.duplicate_mode,
+            duplicate_detector: settings.duplicate_detector,
+            max_duplicate_run: settings.max_duplicate_run,
+            // the CPU backend runs every frame on CPU workers, one per core unless set
+            cpu_workers: match (settings.backend.resolve(), settings.cpu_workers) {
+                (Backend::Cpu, 0) => thread::available_parallelism().map_or(1, |threads| threads.get()),
+                (_, workers) => workers,
+            },
+            tile_size: settings.tile_size,
+            on_skip: settings.on_skip,
+            on_frame_error: settings.on_frame_error,
+            sequence: None,
+            final_scale: settings.final_scale,
+            aspect_policy: settings.aspect_policy,
+            cache_dir: settings.cache_dir.as_deref(),
+            av1: settings.av1_tuning(),
+            grain: settings.grain,
+            burn_subtitles: settings.burn_subtitles.as_ref(),
+            watermark: settings.watermark.as_ref(),
+            media: None,
+            keep_frames: settings.keep_frames.as_deref(),
+            frames_format: settings.frames_format,
+            frames_only: settings.frames_only,
+            screenshots: settings.screenshots.as_ref(),
+            channel_capacity: &settings.channel_capacity,
+            chain: Vec::new(),
+            // profiles are 10-bit, so they get the same 16-bit scaling
+            bit_depth: match (settings.profile, settings.preset) {
+                (Some(_), _) => BitDepth::Ten,
+                (None, Some(preset)) => preset.bit_depth(),
+                (None, None) => settings.bit_depth,
+            },
+            pixel_format: match (settings.profile, settings.preset) {
+                (Some(profile), _) => profile.pixel_format(),
+                (None, Some(preset)) => preset.pixel_format(),
+                (None, None) => settings.bit_depth.pixel_format(&settings.encoder)?,
+            },
+            profile: settings.profile,
+            external_tracks: &settings.external_tracks,
+            external_track_mode: settings.external_track_mode,
+            preset: settings.preset,
+            quality_gate: settings.quality_gate,
+            verify: settings.verify,
+            verify_frames: settings.verify_frames,
+            reference: settings.reference.as_deref(),
+            backend: settings.backend.resolve(),
+            chapter: job.chapter,
+            skip_static_segments: settings.skip_static_segments,
+            fragmented: settings.fragmented,
+            live: Remote::is_live(&job.input),
+            segment_time: settings.segment_time,
+            segment_name: settings.segment_name.as_deref(),
+            package: settings.package.or_else(|| Package::detect(&job.output)),
+            input_args: &settings.input_args,
+            encoder_args: &settings.encoder_args,
+            output_args: &settings.output_args,
         };
 
-        video.fetch_video_metadata()?;
-        video.set_model_and_resolution(arguments);
-        video.set_model(arguments);
-        video.warn_if_resolution_adjusted(arguments);
+        if Sequence::is_pattern(video.input) {
+            if let Some(BurnSubtitles::Stream(_)) = video.burn_subtitles {
+                return Err(Error::new("Image sequence inputs have no subtitle streams to burn in, use a subtitle file"));
+            }
+            video.fetch_sequence_metadata(settings)?;
+            video.apply_range(settings);
+        } else {
+            video.fetch_video_metadata()?;
+            video.apply_range(settings);
+            video.apply_detelecine(settings)?;
+            video.apply_tonemap(settings);
+        }
+        video.apply_crop(settings)?;
+        video.apply_roi(settings)?;
+        let model_name = video.resolve_model_name(settings)?;
+        match &settings.chain {
+            Some(chain) => video.apply_chain(settings, chain, model_name)?,
+            None => {
+                video.set_model_and_resolution(settings, model_name);
+                video.set_model(settings, model_name);
+            },
+        }
+        video.apply_adaptive_scale(settings);
+        video.apply_final_scale(settings);
+        video.warn_if_resolution_adjusted(settings);
+        video.render_output(model_name);
+        video.validate_container()?;
 
         Ok(video)
     }
 
+    pub fn get_original_width(&self) -> usize {
+        self.original_width
+    }
+
+    pub fn get_original_height(&self) -> usize {
+        self.original_height
+    }
+
+    pub fn get_partial_output(&self) -> PathBuf {
+        if Sequence::is_pattern(&self.output) || self.is_segmented() {
+            return self.output.clone();
+        }
+        let mut partial_output = OsString::from(self.output.as_os_str());
+        partial_output.push(Self::PARTIAL_SUFFIX);
+        PathBuf::from(partial_output)
+    }
+
+    // Segments are written in place as they complete, so there is no partial output to rename
+    pub fn is_segmented(&self) -> bool {
+        self.segment_time.is_some() || self.package.is_some()
+    }
+
+    // Segment files are named after the output unless --segment-name is given
+    fn get_segment_name(&self) -> String {
+        self.segment_name.map_or_else(|| self.output.file_stem().unwrap_or_default().to_string_lossy().to_string(), str::to_string)
+    }
+
+    fn get_input_stem(&self) -> OsString {
+        Remote::file_path(self.input).file_stem().unwrap_or_default().to_os_string()
+    }
+
+    pub fn get_frames_directory(&self) -> Option<PathBuf> {
+        self.keep_frames.map(|directory| directory.join(self.get_input_stem()))
+    }
+
+    pub fn get_screenshots_directory(&self) -> Option<PathBuf> {
+        self.screenshots.map(|screenshots| screenshots.directory.join(self.get_input_stem()))
+    }
+
+    pub fn get_stream_info(&self) -> Option<&StreamInfo> {
+        self.media.as_ref()?.video_stream(self.stream)
+    }
+
+    // The upscaled stream is read from the pipe, so its language and disposition are taken from the input stream
+    pub fn get_video_tag_args(&self) -> Vec<String> {
+        self.get_stream_info().map(|stream| stream.get_tag_args(0)).unwrap_or_default()
+    }
+
+    pub fn count_external_tracks(&self, kind: TrackKind) -> usize {
+        self.external_tracks.iter().filter(|track| track.kind == kind).count()
+    }
+
+    fn has_external_track(&self, kind: TrackKind) -> bool {
+        self.count_external_tracks(kind) > 0
+    }
+
+    pub fn replaces_streams(&self, kind: TrackKind) -> bool {
+        self.external_track_mode == ExternalTrackMode::Replace && self.has_external_track(kind)
+    }
+
+    // External tracks follow the original streams of their kind, numbered from `first_input` in the command
+    fn get_external_track_args(&self, first_input: usize, audio_streams: usize, subtitle_streams: usize) -> Vec<String> {
+        let mut counts = [audio_streams, subtitle_streams];
+        self.external_tracks.iter().enumerate().flat_map(|(index, track)| {
+            let count = &mut counts[(track.kind == TrackKind::Subtitle) as usize];
+            *count += 1;
+            track.get_args(first_input + index, *count - 1)
+        }).collect()
+    }
+
+    // Inputs for the external tracks, cut to the same chapter as the video
+    pub fn get_external_inputs(&self) -> Vec<OsString> {
+        self.external_tracks.iter()
+            .flat_map(|track| {
+                let seek_args = self.get_seek_args().into_iter().map(OsString::from);
+                seek_args.chain([OsString::from("-i"), Binary::path_arg(&track.path)])
+            })
+            .collect()
+    }
+
+    // Maps audio and subtitles from the probed streams and the external tracks, with codec options only for kinds that are present
+    pub fn get_stream_args(&self, codecs: Option<(&str, &str)>, first_external_input: usize) -> Vec<String> {
+        let codec_args = |audio: bool, subtitles: bool| codecs.into_iter().flat_map(move |(audio_codec, subtitle_codec)| {
+            let audio_args = audio.then(|| [String::from("-c:a"), audio_codec.to_string()]);
+            let subtitle_args = subtitles.then(|| [String::from("-c:s"), subtitle_codec.to_string()]);
+            audio_args.into_iter().chain(subtitle_args).flatten()
+        });
+
+        // packages are left without subtitles, which players expect as separate WebVTT renditions
+        let keeps_subtitles = self.format.is_none_or(|format| format.supports_subtitles()) && self.package.is_none();
+        // only image sequences go unprobed, and they have no audio or subtitles of their own
+        let Some(media) = &self.media else {
+            let maps = [
+                (!self.replaces_streams(TrackKind::Audio)).then_some("0:a?"),
+                (keeps_subtitles && !self.replaces_streams(TrackKind::Subtitle)).then_some("0:s?"),
+            ];
+            return maps.into_iter().flatten()
+                .flat_map(|map| [String::from("-map"), map.to_string()])
+                .chain(self.get_external_track_args(first_external_input, 0, 0))
+                .chain(codec_args(true, keeps_subtitles))
+                .collect();
+        };
+
+        let audio = media.streams(StreamKind::Audio).filter(|_| !self.replaces_streams(TrackKind::Audio));
+        let converts_subtitles = self.format.is_some_and(|format| format.subtitle_codec() != "copy");
+        let subtitles = media.streams(StreamKind::Subtitle)
+            .filter(|_| !self.replaces_streams(TrackKind::Subtitle))
+            .filter(|stream| {
+                if !keeps_subtitles {
+                    Logger::warning(format!("Dropping subtitle stream {} from {}: this format has no subtitles", stream.index, self.input.display()));
+                }
+                keeps_subtitles
+            })
+            .enumerate()
+            .filter(|(index, _)| self.burn_subtitles != Some(&BurnSubtitles::Stream(*index)))
+            .map(|(_, stream)| stream)
+            .filter(|stream| {
+                let supported = !converts_subtitles || MediaInfo::is_text_subtitle(stream);
+                if !supported {
+                    Logger::warning(format!(
+                        "Dropping {} subtitle stream {} from {}: it cannot be converted for this format", stream.codec, stream.index, self.input.display()
+                    ));
+                }
+                supported
+            });
+
+        let streams = audio.chain(subtitles).collect::<Vec<_>>();
+        let count = |kind: StreamKind| streams.iter().filter(|stream| stream.kind == kind).count();
+        let (audio_streams, subtitle_streams) = (count(StreamKind::Audio), count(StreamKind::Subtitle));
+        let codec_args = codec_args(
+            audio_streams > 0 || self.has_external_track(TrackKind::Audio),
+            subtitle_streams > 0 || self.has_external_track(TrackKind::Subtitle),
+        );
+
+        // -map_metadata 0 only covers the container, so each stream's language and disposition are set explicitly
+        let mut counts = [0, 0];
+        streams
+            .iter()
+            .flat_map(|stream| {
+                let count = &mut counts[(stream.kind == StreamKind::Subtitle) as usize];
+                *count += 1;
+                [String::from("-map"), format!("0:{}", stream.index)].into_iter().chain(stream.get_tag_args(*count - 1))
+            })
+            .chain(self.get_external_track_args(first_external_input, audio_streams, subtitle_streams))
+            .chain(codec_args)
+            .collect()
+    }
+
+    pub fn get_muxer(&self) -> Option<String> {
+        let extension = self.output.extension()?.to_string_lossy().to_lowercase();
+        Some(match self.format.or_else(|| extension.parse::<Format>().ok()) {
+            Some(format) => format.muxer().to_string(),
+            None => extension,
+        })
+    }
+
+    fn validate_container(&self) -> Result<(), Error> {
+        if Sequence::is_pattern(&self.output) && !self.external_tracks.is_empty() {
+            return Err(Error::new(format!("--add-audio and --add-subs need a video output, not the sequence {}", self.output.display())));
+        }
+        if self.live && !self.external_tracks.is_empty() {
+            return Err(Error::new(format!("--add-audio and --add-subs cannot be kept in sync with the live input {}", self.input.display())));
+        }
+        if Sequence::is_pattern(&self.output) && self.is_segmented() {
+            return Err(Error::new(format!("--segment-time and --package need a video output, not the sequence {}", self.output.display())));
+        }
+        if Sequence::is_pattern(&self.output) {
+            return Ok(());
+        }
+
+        match self.get_container() {
+            Some(format) if !format.supports_encoder(self.encoder) => Err(Error::new(format!(
+                "Encoder {} is not compatible with the {} container of {}", self.encoder, format, self.output.display()
+            ))),
+            Some(Format::Mxf) if self.fragmented => Err(Error::new(format!("--fragmented cannot write the mxf file {}", self.output.display()))),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_container(&self) -> Option<Format> {
+        self.format.or_else(|| self.output.extension()?.to_str()?.parse::<Format>().ok())
+    }
+
+    pub fn get_muxer_flags(&self) -> &'static [&'static str] {
+        match (self.fragmented, self.get_container()) {
+            (true, Some(format)) => format.fragmented_flags(),
+            (true, None) => &[],
+            (false, _) => self.format.map_or(&[], |format| format.muxer_flags()),
+        }
+    }
+
+    // Muxer and its flags, or the segmenting muxer with --package or --segment-time
+    pub fn get_container_args(&self) -> Vec<String> {
+        if let Some(package) = self.package {
+            let seconds = self.segment_time.unwrap_or(Package::DEFAULT_SEGMENT_TIME);
+            return package.get_args(&self.output, seconds, &self.get_segment_name(), self.live);
+        }
+        if let Some(seconds) = self.segment_time {
+            return Segments::get_args(seconds, self.get_muxer());
+        }
+        let mut args = self.get_muxer_flags().iter().map(|flag| flag.to_string()).collect::<Vec<_>>();
+        if let Some(muxer) = self.get_muxer() {
+            args.extend([String::from("-f"), muxer]);
+        }
+        args
+    }
+
+    pub fn get_output_target(&self) -> PathBuf {
+        match (self.package, self.segment_time) {
+            (Some(_), _) => self.output.clone(),
+            (None, Some(_)) => {
+                let extension = self.output.extension().unwrap_or_default().to_string_lossy();
+                Segments::pattern(&self.output, &self.get_segment_name(), &extension)
+            },
+            (None, None) => self.get_partial_output(),
+        }
+    }
+
+    pub fn get_grain_filter(&self) -> Option<String> {
+        self.grain?.get_filter(self.encoder)
+    }
+
+    pub fn get_subtitle_filter(&self) -> Option<String> {
+        self.burn_subtitles.map(|subtitles| subtitles.get_filter(self.input))
+    }
+
+    pub fn get_watermark_filter(&self) -> Option<String> {
+        self.watermark.map(Watermark::get_filter)
+    }
+
+    pub fn get_encoder_args(&self, stream: usize) -> Vec<String> {
+        let mut args = self.av1.get_args(self.encoder, stream, self.frame_rate);
+        args.extend(self.profile.iter().flat_map(|profile| profile.get_args(stream)));
+        args.extend(self.preset.iter().flat_map(|preset| preset.get_args(stream)));
+        args
+    }
+
+    // Audio and subtitle codecs for the output container, with the preset's audio codec taking precedence
+    pub fn get_output_codecs(&self) -> Option<(&'static str, &'static str)> {
+        if self.package.is_some() {
+            return Some((Package::AUDIO_CODEC, "copy"));
+        }
+        self.format.map(|format| (
+            self.preset.map_or(format.audio_codec(), |preset| preset.audio_codec()),
+            format.subtitle_codec(),
+        ))
+    }
+
     pub fn get_scaled_width(&self) -> usize {
-        self.original_width * self.scale
+        match self.chain.last() {
+            Some(pass) => pass.width * pass.model.get_scale() as usize,
+            None => self.original_width * self.scale,
+        }
     }
 
     pub fn get_scaled_height(&self) -> usize {
-        self.original_height * self.scale
+        match self.chain.last() {
+            Some(pass) => pass.height * pass.model.get_scale() as usize,
+            None => self.original_height * self.scale,
+        }
+    }
+
+    fn fetch_video_metadata(&mut self) -> Result<(), Error> {
+        let media = MediaInfo::probe(self.input)?;
+        let stream = media.video_stream(self.stream)
+            .ok_or_else(|| Error::new(format!("Video stream {} not found in {}", self.stream, self.input.display())))?;
+
+        self.original_width = stream.width;
+        self.original_height = stream.height;
+        self.frame_rate = stream.frame_rate
+            .ok_or_else(|| Error::new(format!("Failed to read the frame rate of {}", self.input.display())))?;
+        self.frame_count = match stream.frame_count {
+            Some(frame_count) => frame_count,
+            // counting would decode the stream forever
+            None if self.live => 0,
+            None => MediaInfo::count_frames(self.input, self.stream)?,
+        };
+        if let Some(chapter) = self.chapter {
+            self.frame_count = (chapter.duration() * self.frame_rate).round() as usize;
+        }
+        self.rotation = stream.rotation;
+        self.sample_aspect_ratio = stream.sample_aspect_ratio;
+        self.color = Color::detect(&stream.color_space, &stream.color_range, &stream.pixel_format, self.original_height);
+
+        if self.rotation == 90 || self.rotation == 270 {
+            std::mem::swap(&mut self.original_width, &mut self.original_height);
+            self.sample_aspect_ratio = self.sample_aspect_ratio.map(|(num, den)| (den, num));
+        }
+        if let Some((num, den)) = self.sample_aspect_ratio {
+            Logger::info(format!("{} has non-square pixels ({}:{}), sizing it by its display aspect ratio", self.input.display(), num, den));
+        }
+
+        Logger::debug(format!(
+            "{}: {} {:.1}s {} kb/s, video {} {}x{} {}/{}/{}/{}",
+            self.input.display(),
+            media.format,
+            media.duration.unwrap_or_default(),
+            media.bit_rate.or(stream.bit_rate).unwrap_or_default() / 1000,
+            stream.codec,
+            stream.width,
+            stream.height,
+            stream.color_space,
+            stream.color_range,
+            stream.color_transfer,
+            stream.color_primaries,
+        ));
+        self.media = Some(media);
+        Ok(())
+    }
+
+    fn fetch_sequence_metadata(&mut self, settings: &Settings) -> Result<(), Error> {
+        let sequence = Sequence::probe(self.input)?;
+        let first_frame = Sequence::frame_path(self.input, sequence.start);
+        let (width, height) = image::image_dimensions(&first_frame)
+            .map_err(|e| Error::new(format!("Failed to read {}: {}", first_frame.display(), e)))?;
+
+        self.original_width = width as usize;
+        self.original_height = height as usize;
+        self.frame_count = sequence.count;
+        self.frame_rate = settings.fps.unwrap_or(Self::DEFAULT_SEQUENCE_FRAME_RATE);
+        self.sequence = Some(sequence);
+        Ok(())
+    }
+
+    fn apply_detelecine(&mut self, settings: &Settings) -> Result<(), Error> {
+        // detection samples the input, which a live stream would have to serve before the job starts
+        if self.live && settings.detelecine == Detelecine::Auto {
+            return Ok(());
+        }
+        if settings.detelecine.resolve(self.input, self.stream, self.frame_rate)? {
+            Logger::info(format!("Detelecining {}", self.input.display()));
+            self.detelecine = true;
+            self.frame_rate *= Detelecine::DECIMATION;
+            self.frame_count = (self.frame_count as f64 * Detelecine::DECIMATION).round() as usize;
+        }
+
+        Ok(())
     }
 
-    fn parse_frame_rate(value: &str) -> Result<f64, Error> {
-        let fps_parts: Vec<&str> = value.split('/').collect();
-        if fps_parts.len() == 2 {
-            let num = fps_parts[0].parse::<f64>()
-                .map_err(|_| Error::new(format!("Failed to parse frame rate numerator: {}", fps_parts[0])))?;
-            let den = fps_parts[1].parse::<f64>()
-                .map_err(|_| Error::new(format!("Failed to parse frame rate denominator: {}", fps_parts[1])))?;
-            Ok(num / den)
+    // For sources tagged with the wrong range; the output keeps the range of the source
+    fn apply_range(&mut self, settings: &Settings) {
+        if let Some(range) = settings.color_range.filter(|range| *range != self.color.range) {
+            Logger::info(format!("Reading {} as {} range instead of {}", self.input.display(), range, self.color.range));
+            self.color.range = range;
+        }
+    }
+
+    fn apply_tonemap(&mut self, settings: &Settings) {
+        if settings.tonemap == Tonemap::Off {
+            return;
+        }
+
+        let color_transfer = self.get_stream_info().map(|stream| stream.color_transfer.as_str()).unwrap_or_default();
+        if Tonemap::is_hdr(color_transfer) {
+            Logger::info(format!("Tone-mapping {} to SDR", self.input.display()));
+            self.tonemap = settings.tonemap;
+            self.color = Color::SDR;
         } else {
-            Err(Error::new(format!("Invalid frame rate format: {}", value)))
+            Logger::debug(format!("{} is not HDR ({}), skipping tone-mapping", self.input.display(), color_transfer));
         }
     }
 
-    fn fetch_video_metadata(&mut self) -> Result<(), Error> {
-        let output = Command::new("ffprobe")
-            .args(&[
-                "-hide_banner", "-v", "error",
-                "-select_streams", "v:0",
-                "-count_frames",
-                "-show_entries", "stream=nb_read_frames,r_frame_rate,width,height",
-                "-of", "default=noprint_wrappers=1",
-                self.input,
-            ])
-            .output()
-            .map_err(|e| Error::new(format!("Failed to execute ffprobe: {}", e)))?;
-
-        let data = String::from_utf8(output.stdout)
-            .map_err(|e| Error::new(format!("Failed to parse ffprobe output: {}", e)))?;
-        
-        for line in data.lines() {
-            if let Some((key, value)) = line.split_once('=') {
-                match key {
-                    "nb_read_frames" => self.frame_count = value.parse()
-                        .map_err(|_| Error::new(format!("Failed to parse frame count: {}", value)))?,
-                    "r_frame_rate" => self.frame_rate = Self::parse_frame_rate(value)?,
-                    "width" => self.original_width = value.parse()
-                        .map_err(|_| Error::new(format!("Failed to parse width: {}", value)))?,
-                    "height" => self.original_height = value.parse()
-                        .map_err(|_| Error::new(format!("Failed to parse height: {}", value)))?,
-                    _ => {}
-                }
-            }
+    fn apply_crop(&mut self, settings: &Settings) -> Result<(), Error> {
+        let crop = match settings.crop {
+            Some(CropMode::Manual(crop)) => crop,
+            Some(CropMode::Auto) if self.live => {
+                Logger::warning(format!("--crop auto cannot sample the live input {}, leaving it uncropped", self.input.display()));
+                return Ok(());
+            },
+            Some(CropMode::Auto) => match Crop::detect(self.input, self.stream, self.frame_count, self.frame_rate)? {
+                Some(crop) => crop,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        if !crop.fits(self.original_width, self.original_height) {
+            return Err(Error::new(format!(
+                "Invalid crop: {}. Must fit within {}x{}", crop, self.original_width, self.original_height
+            )));
+        }
+
+        if crop.width != self.original_width || crop.height != self.original_height {
+            Logger::info(format!("Cropping {} to {}", self.input.display(), crop));
+            self.original_width = crop.width;
+            self.original_height = crop.height;
+            self.crop = Some(crop);
+        }
+
+        Ok(())
+    }
+
+    // --roi-only is a crop, otherwise the region is upscaled and composited over the scaled frame
+    fn apply_roi(&mut self, settings: &Settings) -> Result<(), Error> {
+        let Some(roi) = settings.roi else {
+            return Ok(());
+        };
+
+        if !roi.fits(self.original_width, self.original_height) {
+            return Err(Error::new(format!(
+                "Invalid roi: {}. Must fit within {}x{}", roi, self.original_width, self.original_height
+            )));
+        }
+
+        if settings.roi_only {
+            self.crop = Some(roi.to_crop(self.crop));
+            self.original_width = roi.width;
+            self.original_height = roi.height;
+        } else {
+            self.roi = Some(roi);
         }
 
         Ok(())
     }
 
-    fn calculate_target_dimensions(&self, arguments: &Arguments, original_aspect_ratio: f64) -> (usize, usize) {
-        match (arguments.width, arguments.height) {
+    pub fn get_seek_args(&self) -> Vec<String> {
+        self.chapter.map(|chapter| chapter.get_seek_args()).unwrap_or_default()
+    }
+
+    pub fn get_hwdownload_filter(&self) -> Option<String> {
+        let stream = self.media.as_ref()?.video_stream(self.stream)?;
+        self.hwdec.get_download_filter(&stream.pixel_format)
+    }
+
+    pub fn get_rotation_filter(&self) -> Option<&'static str> {
+        match self.rotation {
+            90 => Some("transpose=clock"),
+            180 => Some("hflip,vflip"),
+            270 => Some("transpose=cclock"),
+            _ => None,
+        }
+    }
+
+    // Anamorphic sources are sized by the shape they are shown in, never by squeezing their coded size
+    fn get_display_size(&self) -> (usize, usize) {
+        match self.sample_aspect_ratio {
+            Some((num, den)) if num > den => (((self.original_width * num) as f64 / den as f64).round() as usize, self.original_height),
+            Some((num, den)) => (self.original_width, ((self.original_height * den) as f64 / num as f64).round() as usize),
+            None => (self.original_width, self.original_height),
+        }
+    }
+
+    fn get_display_aspect_ratio(&self) -> f64 {
+        let (width, height) = self.get_display_size();
+        width as f64 / height as f64
+    }
+
+    fn calculate_target_dimensions(&self, settings: &Settings, original_aspect_ratio: f64) -> (usize, usize) {
+        let (display_width, display_height) = self.get_display_size();
+        match (settings.width, settings.height) {
             (Some(w), Some(h)) => (w, h),
             (Some(w), None) => (w, (w as f64 / original_aspect_ratio).round() as usize),
             (None, Some(h)) => ((h as f64 * original_aspect_ratio).round() as usize, h),
-            (None, None) => (self.original_width * self.scale, self.original_height * self.scale),
+            (None, None) => (display_width * self.scale, display_height * self.scale),
         }
     }
 
+    // The size the model output has to reach; the output itself is that or the exact target, depending on the policy
     fn adjust_for_aspect_ratio(&self, width: usize, height: usize, original_aspect_ratio: f64) -> (usize, usize) {
-        let target_aspect_ratio = width as f64 / height as f64;
+        self.aspect_policy.content_size(width, height, original_aspect_ratio)
+    }
 
-        if (target_aspect_ratio - original_aspect_ratio).abs() <= 0.01 {
-            return (width, height);
+    fn resolve_model_name(&self, settings: &'a Settings) -> Result<&'a str, Error> {
+        if settings.model != "auto" {
+            return Ok(&settings.model);
         }
 
-        if target_aspect_ratio > original_aspect_ratio {
-            let new_width = (height as f64 * original_aspect_ratio).round() as usize;
-            (new_width, height)
-        } else {
-            let new_height = (width as f64 / original_aspect_ratio).round() as usize;
-            (width, new_height)
+        // cameras and broadcasts are taken as live action rather than sampled before the job starts
+        if self.live {
+            let model_name = Content::LiveAction.preferred_model();
+            Logger::info(format!("Using {} for the live input {}", model_name, self.input.display()));
+            return Ok(model_name);
         }
+
+        let content = Content::detect(self.input, self.stream, self.frame_count, self.frame_rate)?;
+        let model_name = content.preferred_model();
+        Logger::info(format!("Detected {} content in {}, using {}", content, self.input.display(), model_name));
+        Ok(model_name)
     }
 
-    fn set_model_and_resolution(&mut self, arguments: &Arguments) { 
-        let original_aspect_ratio = self.original_width as f64 / self.original_height as f64;
-        let (target_width, target_height) = self.calculate_target_dimensions(arguments, original_aspect_ratio);
+    fn set_model_and_resolution(&mut self, settings: &Settings, model_name: &str) {
+        let original_aspect_ratio = self.get_display_aspect_ratio();
+        let (target_width, target_height) = self.calculate_target_dimensions(settings, original_aspect_ratio);
         let (final_width, final_height) = self.adjust_for_aspect_ratio(target_width, target_height, original_aspect_ratio);
 
-        self.scale = if arguments.model != "realcugan" && arguments.model != "realesr-anime" {
-            4
-        } else {
-            1 + (0..=3).rev()
-                .find(|&scale| final_width > self.original_width * scale || final_height > self.original_height * scale)
-                .unwrap_or(0)
+        let (display_width, display_height) = self.get_display_size();
+        let required_scale = 1 + (0..=3).rev()
+            .find(|&scale| final_width > display_width * scale || final_height > display_height * scale)
+            .unwrap_or(0);
+        let supported_scales = Model::supported_scales(model_name);
+
+        self.scale = match required_scale {
+            1 => 1,
+            _ => supported_scales
+                .iter()
+                .copied()
+                .find(|&scale| scale >= required_scale)
+                .unwrap_or(supported_scales[supported_scales.len() - 1]),
         };
 
-        self.width = final_width.min(final_width * self.scale);
-        self.height = final_height.min(final_height * self.scale);
+        (self.width, self.height) = self.aspect_policy.output_size((target_width, target_height), (final_width, final_height));
     }
 
-    fn set_model(&mut self, arguments: &Arguments) {
-        self.model = match (self.scale, arguments.model.as_str()) {
+    fn create_model(settings: &Settings, model_name: &str, scale: usize) -> Option<Model> {
+        let syncgap = settings.syncgap.unwrap_or(Model::DEFAULT_SYNCGAP);
+        match (scale, model_name) {
             (1, _) => None,
-            (_, "realcugan") => Some(Model::RealCugan(self.scale as u8)),
-            (_, "realesr-anime") => Some(Model::RealEsrAnime(self.scale as u8)),
-            (_, "realesrgan") => Some(Model::RealEsrgan),
+            (_, "realcugan") => Some(Model::RealCugan { scale: scale as u8, pro: false, syncgap }),
+            (_, "realcugan-pro") => Some(Model::RealCugan { scale: scale as u8, pro: true, syncgap }),
+            (_, "realesr-anime") => Some(Model::RealEsrAnime(scale as u8)),
+            (_, "realesrgan") => Some(Model::RealEsrgan(scale as u8)),
             (_, "realesrgan-anime") => Some(Model::RealEsrganAnime),
-            _ => None,
+            (scale, ExternalCommand::MODEL) => settings.external_upscaler.as_deref()
+                .map(|command| Model::External(ExternalCommand::load(command), scale as u8)),
+            (scale, name) => Plugin::name_of(name)
+                .and_then(|name| Plugin::load(name).ok())
+                .map(|plugin| Model::Plugin(plugin, scale as u8)),
+        }
+    }
+
+    fn apply_adaptive_scale(&mut self, settings: &Settings) {
+        let Some(model) = self.model.filter(|_| settings.adaptive_scale) else {
+            return;
+        };
+        self.adaptive_model = match (model.cheaper(), self.chain.is_empty(), self.roi) {
+            (Some(cheaper), true, None) => Some(cheaper),
+            (None, _, _) => {
+                Logger::warning(format!("--adaptive-scale has no cheaper model than {} for {}", model, self.input.display()));
+                None
+            },
+            _ => {
+                Logger::warning(format!("--adaptive-scale does not work with --chain or --roi, ignoring it for {}", self.input.display()));
+                None
+            },
         };
     }
 
-    fn warn_if_resolution_adjusted(&self, arguments: &Arguments) {
-        let requested_width = arguments.width.unwrap_or(0);
-        let requested_height = arguments.height.unwrap_or(0);
+    fn set_model(&mut self, settings: &Settings, model_name: &str) {
+        self.model = Self::create_model(settings, model_name, self.scale);
+    }
+
+    // Between passes frames are downscaled so the remaining passes land on the target instead of overshooting it
+    fn apply_chain(&mut self, settings: &Settings, chain: &Chain, model_name: &str) -> Result<(), Error> {
+        let original_aspect_ratio = self.get_display_aspect_ratio();
+        self.scale = chain.total_scale();
+        let (target_width, target_height) = self.calculate_target_dimensions(settings, original_aspect_ratio);
+        let (final_width, final_height) = self.adjust_for_aspect_ratio(target_width, target_height, original_aspect_ratio);
+        (self.width, self.height) = self.aspect_policy.output_size((target_width, target_height), (final_width, final_height));
+
+        let (display_width, display_height) = self.get_display_size();
+        if final_width <= display_width && final_height <= display_height {
+            self.scale = 1;
+            return Ok(());
+        }
+
+        let mut passes = Vec::new();
+        let (mut width, mut height, mut remaining) = (self.original_width, self.original_height, self.scale);
+        for step in &chain.steps {
+            let name = step.model.as_deref().unwrap_or(model_name);
+            if !Model::supported_scales(name).contains(&step.scale) {
+                return Err(Error::new(format!("Model {} does not support x{} in --chain", name, step.scale)));
+            }
+            let model = Self::create_model(settings, name, step.scale)
+                .ok_or_else(|| Error::new(format!("Cannot create the {} model for --chain", name)))?;
+            if !passes.is_empty() {
+                width = width.min(final_width.div_ceil(remaining));
+                height = height.min(final_height.div_ceil(remaining));
+            }
+            passes.push(ChainPass { model, width, height });
+            width *= step.scale;
+            height *= step.scale;
+            remaining /= step.scale;
+        }
+
+        let first = passes.remove(0);
+        self.scale = first.model.get_scale() as usize;
+        self.model = Some(first.model);
+        self.chain = passes;
+        Ok(())
+    }
+
+    fn apply_final_scale(&mut self, settings: &Settings) {
+        let (scaled_width, scaled_height) = (self.get_scaled_width(), self.get_scaled_height());
+        let downscaled = self.width < scaled_width || self.height < scaled_height;
+        if self.model.is_some() && (settings.final_scale == FinalScale::None || (settings.no_downscale && downscaled)) {
+            self.width = scaled_width;
+            self.height = scaled_height;
+        }
+    }
+
+    pub fn get_scale_filter(&self) -> Option<String> {
+        if self.width == self.get_scaled_width() && self.height == self.get_scaled_height() {
+            return None;
+        }
+        self.final_scale.flags().map(|flags| self.aspect_policy.get_filter(self.width, self.height, flags))
+    }
+
+    // Extracted frames lose the sample aspect ratio: a resampled output has square pixels, the model output keeps the source's
+    pub fn get_sar_filter(&self) -> Option<String> {
+        let (num, den) = self.sample_aspect_ratio?;
+        match self.width == self.get_scaled_width() && self.height == self.get_scaled_height() {
+            true => Some(format!("setsar={}/{}", num, den)),
+            false => Some(String::from("setsar=1")),
+        }
+    }
+
+    // Bilinear, since scale_cuda has no lanczos before ffmpeg 5.0
+    pub fn get_cuda_scale_filter(&self) -> Option<String> {
+        if self.width == self.get_scaled_width() && self.height == self.get_scaled_height() {
+            return None;
+        }
+        Some(format!("scale_cuda={}:{}", self.width, self.height))
+    }
+
+    fn render_output(&mut self, model_name: &str) {
+        let model = self.model.map_or_else(|| model_name.to_string(), |model| model.to_string());
+        let (scale, width, height) = (self.scale.to_string(), self.width.to_string(), self.height.to_string());
+        self.output = PathBuf::from(OutputTemplate::render(self.output.as_os_str(), &[
+            ("model", OsStr::new(&model)),
+            ("scale", OsStr::new(&scale)),
+            ("width", OsStr::new(&width)),
+            ("height", OsStr::new(&height)),
+        ]));
+    }
+
+    fn warn_if_resolution_adjusted(&self, settings: &Settings) {
+        let requested_width = settings.width.unwrap_or(0);
+        let requested_height = settings.height.unwrap_or(0);
 
         if (requested_width > 0 && self.width != requested_width) || 
            (requested_height > 0 && self.height != requested_height) {
-            println!(
-                "Warning: Resolution adjusted from {}x{} to {}x{} to maintain aspect ratio and scaling factor.",
+            Logger::warning(format!(
+                "Resolution adjusted from {}x{} to {}x{} to maintain aspect ratio and scaling factor.",
                 requested_width, requested_height, self.width, self.height
-            );
+            ));
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use crate::error::Error;
+use crate::subtitles::BurnSubtitles;
+
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl WatermarkPosition {
+    // Corners keep a margin of 2% of the frame height
+    fn get_coordinates(&self) -> &'static str {
+        match self {
+            WatermarkPosition::TopLeft => "x=main_h*0.02:y=main_h*0.02",
+            WatermarkPosition::TopRight => "x=main_w-overlay_w-main_h*0.02:y=main_h*0.02",
+            WatermarkPosition::BottomLeft => "x=main_h*0.02:y=main_h-overlay_h-main_h*0.02",
+            WatermarkPosition::BottomRight => "x=main_w-overlay_w-main_h*0.02:y=main_h-overlay_h-main_h*0.02",
+            WatermarkPosition::Center => "x=(main_w-overlay_w)/2:y=(main_h-overlay_h)/2",
+        }
+    }
+}
+
+impl std::str::FromStr for WatermarkPosition {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "tl" => Ok(WatermarkPosition::TopLeft),
+            "tr" => Ok(WatermarkPosition::TopRight),
+            "bl" => Ok(WatermarkPosition::BottomLeft),
+            "br" => Ok(WatermarkPosition::BottomRight),
+            "center" => Ok(WatermarkPosition::Center),
+            _ => Err(Error::new(format!("Invalid watermark position: {}. Must be tl, tr, bl, br or center", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for WatermarkPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatermarkPosition::TopLeft => write!(f, "tl"),
+            WatermarkPosition::TopRight => write!(f, "tr"),
+            WatermarkPosition::BottomLeft => write!(f, "bl"),
+            WatermarkPosition::BottomRight => write!(f, "br"),
+            WatermarkPosition::Center => write!(f, "center"),
+        }
+    }
+}
+
+// An image, animated image or video composited onto the output frames at their final size; animations loop
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watermark {
+    pub path: PathBuf,
+    pub position: WatermarkPosition,
+    pub opacity: f64,
+}
+
+impl Watermark {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.path.is_file() {
+            return Err(Error::new(format!("Watermark file not found: {}", self.path.display())));
+        }
+        Ok(())
+    }
+
+    // The watermark is read by a movie source inside the output filter chain, so the merge keeps a single input;
+    // the null filter labels whatever came before it, even when the watermark is the first filter
+    pub fn get_filter(&self) -> String {
+        let opacity = match self.opacity < 1.0 {
+            true => format!(",colorchannelmixer=aa={}", self.opacity),
+            false => String::new(),
+        };
+        format!(
+            "null[base];movie=filename={}:loop=0,setpts=N/FRAME_RATE/TB,format=rgba{}[watermark];[base][watermark]overlay={}:shortest=1",
+            BurnSubtitles::escape(&self.path), opacity, self.position.get_coordinates()
+        )
+    }
+}
+
+impl std::str::FromStr for Watermark {
+    type Err = Error;
+
+    // FILE[:pos=POSITION][:opacity=OPACITY], with the options taken from the end so paths may contain colons
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut watermark = Watermark { path: PathBuf::new(), position: WatermarkPosition::BottomRight, opacity: 1.0 };
+        let mut path = value;
+        while let Some((rest, option)) = path.rsplit_once(':') {
+            match option.split_once('=') {
+                Some(("pos", position)) => watermark.position = position.parse()?,
+                Some(("opacity", opacity)) => {
+                    watermark.opacity = opacity.parse().ok().filter(|opacity| (0.0..=1.0).contains(opacity)).ok_or_else(|| {
+                        Error::new(format!("Invalid watermark opacity: {}. Must be between 0 and 1", opacity))
+                    })?;
+                },
+                _ => break,
+            }
+            path = rest;
+        }
+        if path.is_empty() {
+            return Err(Error::new(format!("Invalid watermark: {}. Must be FILE[:pos=tl|tr|bl|br|center][:opacity=0-1]", value)));
+        }
+        watermark.path = PathBuf::from(path);
+        Ok(watermark)
+    }
+}
+
+impl std::fmt::Display for Watermark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:pos={}:opacity={}", self.path.display(), self.position, self.opacity)
+    }
+}
@@ -0,0 +1,178 @@
+// Synthetic fixtures and helpers for the end-to-end tests. The tests run the built binary against videos generated
+// with ffmpeg, and swap the model for a nearest-neighbour --external-upscaler so they need no GPU or model files.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+// Nearest-neighbour scaling of packed RGB24 frames, in perl since it is on every CI image that has ffmpeg
+const NEAREST_UPSCALER: &str = "perl -e 'binmode STDIN; binmode STDOUT; $| = 1; my ($w, $h, $s) = @ARGV; \
+    while (read(STDIN, my $f, $w * $h * 3) == $w * $h * 3) { for my $y (0 .. $h - 1) { \
+    my $row = join(\"\", map { substr($f, ($y * $w + $_) * 3, 3) x $s } 0 .. $w - 1); print $row x $s } }' \
+    {width} {height} {scale}";
+
+fn found(program: &str, arg: &str) -> bool {
+    Command::new(program).arg(arg).stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok()
+}
+
+// The tests are #[ignore]d, so once they are asked for a missing tool is a failure rather than a skip
+pub fn require_tools() {
+    for (program, arg) in [("ffmpeg", "-version"), ("ffprobe", "-version"), ("perl", "-v")] {
+        assert!(found(program, arg), "{} is needed for the end-to-end tests and is not on PATH", program);
+    }
+}
+
+// A scratch directory per test, removed when the test ends
+pub struct Workspace {
+    pub directory: PathBuf,
+}
+
+impl Workspace {
+    pub fn new(name: &str) -> Self {
+        let directory = std::env::temp_dir().join(format!("upscaler-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("failed to create the test directory");
+        Self { directory }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.directory.join(name)
+    }
+
+    // Runs ffmpeg with `args` between its global options and the output file
+    pub fn generate(&self, name: &str, args: &[&str]) -> PathBuf {
+        let output = self.path(name);
+        let status = Command::new("ffmpeg")
+            .args(["-v", "error", "-y"])
+            .args(args)
+            .arg(&output)
+            .status()
+            .expect("failed to run ffmpeg");
+        assert!(status.success(), "ffmpeg failed to generate {}", name);
+        output
+    }
+
+    // Color bars with a tone, one second at 24 fps
+    pub fn color_bars(&self, name: &str, width: u32, height: u32) -> PathBuf {
+        let video = format!("testsrc2=size={}x{}:rate=24:duration=1", width, height);
+        self.generate(name, &[
+            "-f", "lavfi", "-i", &video,
+            "-f", "lavfi", "-i", "sine=frequency=440:duration=1",
+            "-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac", "-shortest",
+        ])
+    }
+
+    // A white box crossing a black frame, without audio
+    pub fn moving_box(&self, name: &str) -> PathBuf {
+        self.generate(name, &[
+            "-filter_complex", "color=c=black:s=160x120:r=24:d=1[background];color=c=white:s=16x16:r=24:d=1[box];\
+                [background][box]overlay=x=t*100:y=52:shortest=1",
+            "-c:v", "libx264", "-pix_fmt", "yuv420p",
+        ])
+    }
+
+    // 4 fps content repeated to 24 fps, so every picture is followed by a run of 5 duplicates
+    pub fn duplicate_runs(&self, name: &str) -> PathBuf {
+        self.generate(name, &[
+            "-f", "lavfi", "-i", "testsrc2=size=160x120:rate=4:duration=2",
+            "-vf", "fps=24",
+            "-c:v", "libx264", "-pix_fmt", "yuv420p",
+        ])
+    }
+
+    // Color bars with an SRT subtitle track in matroska, and a tone between them when `audio` is set
+    pub fn subtitled(&self, name: &str, audio: bool) -> PathBuf {
+        let subtitles = self.path(&format!("{}.srt", name));
+        fs::write(&subtitles, "1\n00:00:00,000 --> 00:00:00,900\nSubtitle\n").expect("failed to write the subtitles");
+        let subtitles = subtitles.to_string_lossy().into_owned();
+        let mut args = vec!["-f", "lavfi", "-i", "testsrc2=size=160x120:rate=24:duration=1", "-i", &subtitles];
+        if audio {
+            args.extend(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"]);
+        }
+        args.extend(["-map", "0:v"]);
+        if audio {
+            args.extend(["-map", "2:a", "-c:a", "aac"]);
+        }
+        args.extend(["-map", "1:s", "-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:s", "srt"]);
+        self.generate(name, &args)
+    }
+
+    // Moves a fixture to `path`, creating its folders, for names ffmpeg is not given directly
+    pub fn relocate(&self, fixture: PathBuf, path: PathBuf) -> PathBuf {
+        fs::create_dir_all(path.parent().expect("the path has no parent")).expect("failed to create the fixture folders");
+        fs::rename(&fixture, &path).expect("failed to move the fixture");
+        path
+    }
+
+    // Odd sizes cannot be 4:2:0, so the fixture is lossless 4:4:4 in matroska
+    pub fn odd_resolution(&self, name: &str) -> PathBuf {
+        self.generate(name, &[
+            "-f", "lavfi", "-i", "testsrc2=size=161x97:rate=24:duration=1",
+            "-c:v", "ffv1", "-pix_fmt", "yuv444p",
+        ])
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+// Upscales `input` to `output` with the nearest-neighbour stand-in for a model
+pub fn upscale(input: &Path, output: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_simple_video_upscaler"))
+        .arg("-i").arg(input)
+        .arg("-o").arg(output)
+        .args(["--external-upscaler", NEAREST_UPSCALER, "--backend", "cpu", "--cpu-workers", "2", "--progress", "none"])
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run the upscaler");
+    assert!(
+        output.status.success(),
+        "upscaling {} failed ({}):\n{}", input.display(), output.status, String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stream {
+    pub kind: String,
+    pub codec: String,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub frames: Option<u64>,
+}
+
+// Every stream of `path`, with video frames counted by decoding
+pub fn probe(path: &Path) -> Vec<Stream> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-count_frames", "-show_entries", "stream=codec_type,codec_name,width,height,nb_read_frames", "-of", "json"])
+        .arg(path)
+        .output()
+        .expect("failed to run ffprobe");
+    assert!(output.status.success(), "ffprobe failed on {}", path.display());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("ffprobe printed invalid JSON");
+    let number = |stream: &serde_json::Value, key: &str| match &stream[key] {
+        serde_json::Value::Number(number) => number.as_u64(),
+        serde_json::Value::String(number) => number.parse().ok(),
+        _ => None,
+    };
+    json["streams"].as_array().into_iter().flatten().map(|stream| Stream {
+        kind: stream["codec_type"].as_str().unwrap_or_default().to_string(),
+        codec: stream["codec_name"].as_str().unwrap_or_default().to_string(),
+        width: number(stream, "width"),
+        height: number(stream, "height"),
+        frames: number(stream, "nb_read_frames"),
+    }).collect()
+}
+
+pub fn video_stream(streams: &[Stream]) -> &Stream {
+    streams.iter().find(|stream| stream.kind == "video").expect("no video stream")
+}
+
+pub fn kinds(streams: &[Stream]) -> Vec<&str> {
+    streams.iter().map(|stream| stream.kind.as_str()).collect()
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::{kinds, probe, upscale, video_stream, Workspace};
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn color_bars_keep_audio_and_frame_count() {
+    common::require_tools();
+    let workspace = Workspace::new("color-bars");
+    let input = workspace.color_bars("bars.mp4", 320, 240);
+    let output = workspace.path("bars_upscaled.mkv");
+    upscale(&input, &output, &[]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video", "audio"]);
+    let video = video_stream(&streams);
+    assert_eq!((video.width, video.height), (Some(640), Some(480)));
+    assert_eq!(video.frames, Some(24));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn moving_box_without_audio() {
+    common::require_tools();
+    let workspace = Workspace::new("moving-box");
+    let input = workspace.moving_box("box.mp4");
+    let output = workspace.path("box_upscaled.mp4");
+    upscale(&input, &output, &["--width", "480"]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video"]);
+    let video = video_stream(&streams);
+    assert_eq!((video.width, video.height), (Some(480), Some(360)));
+    assert_eq!(video.frames, Some(24));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn subtitles_without_audio_are_kept() {
+    common::require_tools();
+    let workspace = Workspace::new("subtitles-without-audio");
+    let input = workspace.subtitled("silent.mkv", false);
+    let output = workspace.path("silent_upscaled.mkv");
+    upscale(&input, &output, &[]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video", "subtitle"]);
+    assert_eq!(streams[1].codec, "subrip");
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn audio_and_subtitles_are_kept() {
+    common::require_tools();
+    let workspace = Workspace::new("audio-and-subtitles");
+    let input = workspace.subtitled("subtitled.mkv", true);
+    let output = workspace.path("subtitled_upscaled.mkv");
+    upscale(&input, &output, &[]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video", "audio", "subtitle"]);
+    assert_eq!(video_stream(&streams).frames, Some(24));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn subtitle_less_input_to_mp4_keeps_audio() {
+    common::require_tools();
+    let workspace = Workspace::new("subtitle-less-mp4");
+    let input = workspace.color_bars("bars.mp4", 160, 120);
+    let output = workspace.path("bars_upscaled.mp4");
+    upscale(&input, &output, &[]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video", "audio"]);
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn duplicate_runs_keep_their_timing() {
+    common::require_tools();
+    let workspace = Workspace::new("duplicates");
+    let input = workspace.duplicate_runs("duplicates.mp4");
+    for mode in ["collapse", "keep", "decimate"] {
+        let output = workspace.path(&format!("duplicates_{}.mkv", mode));
+        upscale(&input, &output, &["--duplicates", mode]);
+
+        let video = probe(&output).into_iter().find(|stream| stream.kind == "video").expect("no video stream");
+        assert_eq!((video.width, video.height), (Some(320), Some(240)), "{}", mode);
+        assert_eq!(video.frames, Some(48), "{}", mode);
+    }
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn odd_resolution_upscales_to_double() {
+    common::require_tools();
+    let workspace = Workspace::new("odd-resolution");
+    let input = workspace.odd_resolution("odd.mkv");
+    let output = workspace.path("odd_upscaled.mkv");
+    upscale(&input, &output, &[]);
+
+    let streams = probe(&output);
+    assert_eq!(kinds(&streams), ["video"]);
+    let video = video_stream(&streams);
+    assert_eq!((video.width, video.height), (Some(322), Some(194)));
+    assert_eq!(video.frames, Some(24));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn image_sequence_output_writes_every_frame() {
+    common::require_tools();
+    let workspace = Workspace::new("sequence");
+    // the tone must not end up in the sequence as numbered files of its own
+    let input = workspace.color_bars("bars.mp4", 160, 120);
+    let frames = workspace.path("frames");
+    std::fs::create_dir_all(&frames).expect("failed to create the frames directory");
+    upscale(&input, &frames.join("%06d.png"), &[]);
+
+    let written = std::fs::read_dir(&frames).expect("failed to list the frames").flatten().collect::<Vec<_>>();
+    assert_eq!(written.len(), 24);
+    assert!(written.iter().all(|entry| entry.path().extension().is_some_and(|extension| extension == "png")));
+    let first = probe(&frames.join("000001.png"));
+    assert_eq!((video_stream(&first).width, video_stream(&first).height), (Some(320), Some(240)));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn unicode_paths_are_kept() {
+    common::require_tools();
+    let workspace = Workspace::new("unicode");
+    let fixture = workspace.moving_box("box.mp4");
+    let input = workspace.relocate(fixture, workspace.path("動画 🎬").join("箱 📦.mp4"));
+    let output = workspace.path("出力 ✨").join("箱 📦 upscaled.mkv");
+    std::fs::create_dir_all(output.parent().unwrap()).expect("failed to create the output folder");
+    upscale(&input, &output, &[]);
+
+    assert!(output.exists());
+    let streams = probe(&output);
+    assert_eq!((video_stream(&streams).width, video_stream(&streams).height), (Some(320), Some(240)));
+}
+
+// Windows names are UTF-16 and take no raw bytes, so only unix gets a name that is not UTF-8 at all
+#[cfg(unix)]
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn non_utf8_paths_are_kept() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    common::require_tools();
+    let workspace = Workspace::new("non-utf8");
+    let fixture = workspace.moving_box("box.mp4");
+    let input = workspace.relocate(fixture, workspace.path("box").join(OsStr::from_bytes(b"box-\xff\xfe.mp4")));
+    let output = workspace.directory.join(OsStr::from_bytes(b"out-\xc3\x28")).join(OsStr::from_bytes(b"box-\xff\xfe-upscaled.mkv"));
+    std::fs::create_dir_all(output.parent().unwrap()).expect("failed to create the output folder");
+    upscale(&input, &output, &[]);
+
+    assert!(output.exists());
+    let streams = probe(&output);
+    assert_eq!((video_stream(&streams).width, video_stream(&streams).height), (Some(320), Some(240)));
+}
+
+#[test]
+#[ignore = "needs ffmpeg, ffprobe and perl, run with cargo test -- --ignored"]
+fn paths_longer_than_260_characters_are_kept() {
+    common::require_tools();
+    let workspace = Workspace::new("long-path");
+    let fixture = workspace.moving_box("box.mp4");
+    let directory = (0..6).fold(workspace.directory.clone(), |path, level| path.join(format!("{:02}-{}", level, "nested".repeat(8))));
+    let input = workspace.relocate(fixture, directory.join("box.mp4"));
+    let output = directory.join("box_upscaled.mkv");
+    assert!(output.as_os_str().len() > 260);
+    upscale(&input, &output, &[]);
+
+    assert!(output.exists());
+    let streams = probe(&output);
+    assert_eq!((video_stream(&streams).width, video_stream(&streams).height), (Some(320), Some(240)));
+}